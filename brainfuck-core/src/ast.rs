@@ -0,0 +1,364 @@
+//! Syntax tree of Brainfuck source.
+//!
+//! Unlike the IR, the tree keeps the program as it was written: every command is a node
+//! of its own, comments are kept, and every node knows its span in the source. Loops are
+//! nested nodes, so tools like formatters, analyzers and converters get the bracket
+//! matching for free. [`Ast::walk`] visits the nodes with a [`Visitor`], and [`Ast::iter`]
+//! iterates over them depth first. [`emit_bf`] turns a (possibly transformed) tree back
+//! into source.
+
+use crate::format::{format, FormatOptions};
+use crate::interpret::RunError;
+
+/// A range of character positions in the source, `end` exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Position of the first character
+    pub start: usize,
+    /// Position after the last character
+    pub end: usize,
+}
+
+/// A Brainfuck command other than a bracket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// `>`
+    Right,
+    /// `<`
+    Left,
+    /// `+`
+    Increment,
+    /// `-`
+    Decrement,
+    /// `.`
+    Output,
+    /// `,`
+    Input,
+}
+
+impl Command {
+    /// The command written as `ch`, if any
+    pub fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            '>' => Some(Command::Right),
+            '<' => Some(Command::Left),
+            '+' => Some(Command::Increment),
+            '-' => Some(Command::Decrement),
+            '.' => Some(Command::Output),
+            ',' => Some(Command::Input),
+            _ => None,
+        }
+    }
+
+    /// The character of the command
+    pub fn to_char(self) -> char {
+        match self {
+            Command::Right => '>',
+            Command::Left => '<',
+            Command::Increment => '+',
+            Command::Decrement => '-',
+            Command::Output => '.',
+            Command::Input => ',',
+        }
+    }
+}
+
+/// A node of the syntax tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A single command
+    Command {
+        /// The command
+        command: Command,
+        /// The span of its character
+        span: Span,
+    },
+    /// A loop with its body
+    Loop {
+        /// The nodes between the brackets
+        body: Vec<Node>,
+        /// The span from `[` to `]`, both included
+        span: Span,
+    },
+    /// A run of characters that are not commands
+    Comment {
+        /// The characters of the comment
+        text: String,
+        /// The span of the comment
+        span: Span,
+    },
+}
+
+impl Node {
+    /// The span of the node in the source
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Command { span, .. } | Node::Loop { span, .. } | Node::Comment { span, .. } => {
+                *span
+            }
+        }
+    }
+}
+
+/// The syntax tree of a program
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Ast {
+    /// The top-level nodes
+    pub nodes: Vec<Node>,
+}
+
+/// Callbacks for the nodes of a syntax tree, called by [`Ast::walk`] in source order.
+///
+/// Every method does nothing by default, so a visitor only implements those it needs.
+pub trait Visitor {
+    /// Called for every command
+    fn visit_command(&mut self, _command: Command, _span: Span) {}
+
+    /// Called for every comment
+    fn visit_comment(&mut self, _text: &str, _span: Span) {}
+
+    /// Called before the body of a loop, with the span of the whole loop
+    fn enter_loop(&mut self, _span: Span) {}
+
+    /// Called after the body of a loop, with the span of the whole loop
+    fn exit_loop(&mut self, _span: Span) {}
+}
+
+impl Ast {
+    /// Call the methods of `visitor` for every node, in source order
+    pub fn walk(&self, visitor: &mut impl Visitor) {
+        walk_nodes(&self.nodes, visitor);
+    }
+
+    /// Iterate over every node depth first, a loop before its body
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            stack: vec![self.nodes.iter()],
+        }
+    }
+}
+
+fn walk_nodes(nodes: &[Node], visitor: &mut impl Visitor) {
+    for node in nodes {
+        match node {
+            Node::Command { command, span } => visitor.visit_command(*command, *span),
+            Node::Comment { text, span } => visitor.visit_comment(text, *span),
+            Node::Loop { body, span } => {
+                visitor.enter_loop(*span);
+                walk_nodes(body, visitor);
+                visitor.exit_loop(*span);
+            }
+        }
+    }
+}
+
+/// Iterator over the nodes of an [`Ast`], returned by [`Ast::iter`]
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    stack: Vec<std::slice::Iter<'a, Node>>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(node) = self.stack.last_mut()?.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if let Node::Loop { body, .. } = node {
+                self.stack.push(body.iter());
+            }
+            return Some(node);
+        }
+    }
+}
+
+/// Parse Brainfuck source into a syntax tree, matching its brackets.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::ast::{parse, Node, Span};
+///
+/// let ast = parse("+[-] clear").unwrap();
+/// assert_eq!(ast.nodes.len(), 3);
+/// assert_eq!(ast.nodes[1].span(), Span { start: 1, end: 4 });
+/// let loops = ast.iter().filter(|node| matches!(node, Node::Loop { .. })).count();
+/// assert_eq!(loops, 1);
+/// ```
+pub fn parse(code: &str) -> Result<Ast, RunError> {
+    let mut stack: Vec<(usize, Vec<Node>)> = Vec::new();
+    let mut current = Vec::new();
+
+    for (i, ch) in code.chars().enumerate() {
+        let span = Span {
+            start: i,
+            end: i + 1,
+        };
+        match ch {
+            '[' => stack.push((i, std::mem::take(&mut current))),
+            ']' => {
+                let (start, parent) = stack.pop().ok_or(RunError::UnmatchedCloseBracket(i))?;
+                let body = std::mem::replace(&mut current, parent);
+                current.push(Node::Loop {
+                    body,
+                    span: Span { start, end: i + 1 },
+                });
+            }
+            _ => match Command::from_char(ch) {
+                Some(command) => current.push(Node::Command { command, span }),
+                None => match current.last_mut() {
+                    Some(Node::Comment { text, span }) if span.end == i => {
+                        text.push(ch);
+                        span.end = i + 1;
+                    }
+                    _ => current.push(Node::Comment {
+                        text: ch.to_string(),
+                        span,
+                    }),
+                },
+            },
+        }
+    }
+
+    if let Some((open_pos, _)) = stack.pop() {
+        return Err(RunError::UnmatchedOpenBracket(open_pos));
+    }
+
+    Ok(Ast { nodes: current })
+}
+
+/// How [`emit_bf`] lays out the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Style {
+    /// The commands only, without comments or whitespace
+    Compact,
+    /// The commands and comments as they are in the tree, which reproduces the parsed
+    /// source exactly
+    Original,
+    /// The commands laid out by [`crate::format::format`], without comments
+    Formatted(FormatOptions),
+}
+
+/// Emit the source of a syntax tree in the given style.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::ast::{emit_bf, parse, Node, Style};
+///
+/// let mut ast = parse("+++ three, [-] clear").unwrap();
+/// assert_eq!(emit_bf(&ast, &Style::Original), "+++ three, [-] clear");
+/// // Drop the clear loop
+/// ast.nodes.retain(|node| !matches!(node, Node::Loop { .. }));
+/// assert_eq!(emit_bf(&ast, &Style::Compact), "+++,");
+/// ```
+pub fn emit_bf(ast: &Ast, style: &Style) -> String {
+    let mut out = String::new();
+    emit_nodes(&mut out, &ast.nodes, *style == Style::Original);
+    match style {
+        Style::Formatted(options) => format(&out, options).expect("the brackets of a tree match"),
+        _ => out,
+    }
+}
+
+fn emit_nodes(out: &mut String, nodes: &[Node], comments: bool) {
+    for node in nodes {
+        match node {
+            Node::Command { command, .. } => out.push(command.to_char()),
+            Node::Comment { text, .. } if comments => out.push_str(text),
+            Node::Comment { .. } => {}
+            Node::Loop { body, .. } => {
+                out.push('[');
+                emit_nodes(out, body, comments);
+                out.push(']');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let ast = parse("a+[>b]").unwrap();
+        let expected = vec![
+            Node::Comment {
+                text: "a".to_string(),
+                span: Span { start: 0, end: 1 },
+            },
+            Node::Command {
+                command: Command::Increment,
+                span: Span { start: 1, end: 2 },
+            },
+            Node::Loop {
+                body: vec![
+                    Node::Command {
+                        command: Command::Right,
+                        span: Span { start: 3, end: 4 },
+                    },
+                    Node::Comment {
+                        text: "b".to_string(),
+                        span: Span { start: 4, end: 5 },
+                    },
+                ],
+                span: Span { start: 2, end: 6 },
+            },
+        ];
+        assert_eq!(ast.nodes, expected);
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        assert_eq!(parse("[[]"), Err(RunError::UnmatchedOpenBracket(0)));
+        assert_eq!(parse("[]]"), Err(RunError::UnmatchedCloseBracket(2)));
+    }
+
+    #[test]
+    fn test_emit_bf() {
+        let code = "Add two: ++\n[> move\n+<-]";
+        let ast = parse(code).unwrap();
+        assert_eq!(emit_bf(&ast, &Style::Original), code);
+        assert_eq!(emit_bf(&ast, &Style::Compact), "++[>+<-]");
+        let options = FormatOptions {
+            indent: 2,
+            width: 4,
+        };
+        assert_eq!(
+            emit_bf(&ast, &Style::Formatted(options)),
+            "++\n[\n  >+\n  <-\n]"
+        );
+    }
+
+    #[derive(Default)]
+    struct Depths(Vec<(char, usize)>, usize);
+
+    impl Visitor for Depths {
+        fn visit_command(&mut self, command: Command, _span: Span) {
+            self.0.push((command.to_char(), self.1));
+        }
+
+        fn enter_loop(&mut self, _span: Span) {
+            self.1 += 1;
+        }
+
+        fn exit_loop(&mut self, _span: Span) {
+            self.1 -= 1;
+        }
+    }
+
+    #[test]
+    fn test_walk_and_iter() {
+        let ast = parse("+[>[-]<]. ok").unwrap();
+        let mut depths = Depths::default();
+        ast.walk(&mut depths);
+        assert_eq!(depths.0, [('+', 0), ('>', 1), ('-', 2), ('<', 1), ('.', 0)]);
+
+        let starts: Vec<usize> = ast.iter().map(|node| node.span().start).collect();
+        assert_eq!(starts, [0, 1, 2, 3, 4, 6, 8, 9]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Helpers for build scripts, enabled by the `build` feature.
+//!
+//! These run programs and generate Rust source with the same interpreter limits, IR and
+//! code generation as the macros, so a `build.rs` turning `.bf` assets into source files
+//! gets the same results as `brainfuck!` or `brainfuck_transpile!` would.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use brainfuck_core::build::{compile_bf_to_rust, Options};
+//!
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! let source = compile_bf_to_rust("programs/hello.b", &Options::default()).unwrap();
+//! std::fs::write(format!("{}/hello.rs", out_dir), source).unwrap();
+//! println!("cargo:rerun-if-changed=programs/hello.b");
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::interpret::{self, RunError};
+use crate::optimize::PassManager;
+use crate::{ir, transpile};
+
+/// Options for [`compile_bf_to_rust`] and [`eval_bf`]
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Name of the generated function (default `run`)
+    pub name: String,
+    /// Generate a `fn main()` using stdin and stdout instead of a function taking the
+    /// input as bytes
+    pub main: bool,
+    /// Generate a function running on a tape provided by the caller and passing output
+    /// to a callback, which needs neither `std` nor an allocator, like
+    /// `brainfuck_transpile!(..., no_std = true)`
+    pub no_std: bool,
+    /// Export the function to JavaScript with `#[wasm_bindgen]`, like
+    /// `brainfuck_transpile!(..., wasm_bindgen = true)`
+    pub wasm_bindgen: bool,
+    /// Index the tape of a plain function without bounds checks when the pointer provably
+    /// stays on it, with [`transpile::to_rust_unchecked`], like
+    /// `brainfuck_transpile!(..., unchecked = true)`. The source then contains `unsafe`
+    /// code
+    pub unchecked: bool,
+    /// The optimization passes run before generating code (default
+    /// [`PassManager::optimize`], the passes of the macros)
+    pub passes: PassManager,
+    /// The input read by `,` when evaluating
+    pub input: Vec<u8>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            name: "run".to_string(),
+            main: false,
+            no_std: false,
+            wasm_bindgen: false,
+            unchecked: false,
+            passes: PassManager::optimize(),
+            input: Vec::new(),
+        }
+    }
+}
+
+/// The output of a program run by [`eval_bf`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// The bytes written by `.`
+    pub bytes: Vec<u8>,
+}
+
+impl Output {
+    /// The output as text, with every byte as the character of the same code point, as
+    /// `brainfuck!` produces it
+    pub fn text(&self) -> String {
+        self.bytes.iter().map(|&byte| byte as char).collect()
+    }
+}
+
+/// Error returned when a program file cannot be compiled
+#[derive(Debug)]
+pub enum BuildError {
+    /// The file could not be read
+    Io(PathBuf, std::io::Error),
+    /// The program has unmatched brackets
+    Syntax(PathBuf, RunError),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::Io(path, e) => write!(f, "couldn't read '{}': {}", path.display(), e),
+            BuildError::Syntax(path, e) => {
+                write!(f, "Brainfuck syntax error in '{}': {}", path.display(), e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Read a program and generate the source of a Rust function running it, optimized like
+/// the output of `brainfuck_transpile!`, or of a `fn main()` like `brainfuck_main!` if
+/// [`Options::main`] is set, or of a `no_std` function if [`Options::no_std`] is set, or
+/// of a function exported to JavaScript if [`Options::wasm_bindgen`] is set, or of one
+/// without bounds checks if [`Options::unchecked`] is set
+pub fn compile_bf_to_rust(path: impl AsRef<Path>, options: &Options) -> Result<String, BuildError> {
+    let path = path.as_ref();
+    let code = std::fs::read_to_string(path).map_err(|e| BuildError::Io(path.to_path_buf(), e))?;
+    let ops = ir::parse(&code).map_err(|e| BuildError::Syntax(path.to_path_buf(), e))?;
+    let ops = options.passes.run(ops);
+    Ok(if options.main {
+        transpile::to_rust_main(&ops)
+    } else if options.no_std {
+        transpile::to_rust_no_std(&options.name, &ops)
+    } else if options.wasm_bindgen {
+        transpile::to_rust_wasm_bindgen(&options.name, &ops)
+    } else if options.unchecked {
+        transpile::to_rust_unchecked(&options.name, &ops)
+    } else {
+        transpile::to_rust(&options.name, &ops)
+    })
+}
+
+/// Run a program over [`Options::input`] with the limits of the macros
+pub fn eval_bf(code: &str, options: &Options) -> Result<Output, RunError> {
+    interpret::run(code, &options.input).map(|bytes| Output { bytes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_bf() {
+        let output = eval_bf("+++++[>+++++++++++++<-]>.", &Options::default()).unwrap();
+        assert_eq!(output.text(), "A");
+        let options = Options {
+            input: vec![0xe9],
+            ..Options::default()
+        };
+        assert_eq!(eval_bf(",.", &options).unwrap().text(), "\u{e9}");
+        assert_eq!(
+            eval_bf("<", &Options::default()),
+            Err(RunError::PointerUnderflow)
+        );
+    }
+
+    #[test]
+    fn test_compile_bf_to_rust() {
+        let path = std::env::temp_dir().join("brainfuck_core_build_test.b");
+        std::fs::write(&path, "Echo: ,[.,]").unwrap();
+        let options = Options {
+            name: "echo".to_string(),
+            ..Options::default()
+        };
+        let source = compile_bf_to_rust(&path, &options).unwrap();
+        assert!(source.starts_with("#[allow(unused_mut)]\npub fn echo(input: &[u8]) -> Vec<u8> {"));
+        let main = Options {
+            main: true,
+            ..Options::default()
+        };
+        assert!(compile_bf_to_rust(&path, &main)
+            .unwrap()
+            .contains("fn main() {"));
+        let wasm = Options {
+            wasm_bindgen: true,
+            ..Options::default()
+        };
+        assert!(compile_bf_to_rust(&path, &wasm)
+            .unwrap()
+            .starts_with("#[wasm_bindgen::prelude::wasm_bindgen]\n"));
+
+        std::fs::write(&path, "+[-]").unwrap();
+        let unoptimized = Options {
+            passes: PassManager::new(),
+            ..Options::default()
+        };
+        let checked = compile_bf_to_rust(&path, &unoptimized).unwrap();
+        assert!(checked.contains("while tape[ptr] != 0 {"));
+        assert!(!checked.contains("unsafe"));
+        assert!(compile_bf_to_rust(&path, &Options::default())
+            .unwrap()
+            .contains("    tape[ptr] = 0;\n"));
+        let unchecked = Options {
+            unchecked: true,
+            ..unoptimized
+        };
+        let source = compile_bf_to_rust(&path, &unchecked).unwrap();
+        assert!(source.contains("while unsafe { *tape.get_unchecked(ptr) } != 0 {"));
+        assert!(!source.contains("tape[ptr]"));
+        // A drifting pointer keeps the checks even when asked to leave them out
+        std::fs::write(&path, "+[>+]").unwrap();
+        let source = compile_bf_to_rust(&path, &unchecked).unwrap();
+        assert!(source.contains("while tape[ptr] != 0 {"));
+        assert!(!source.contains("unsafe"));
+
+        std::fs::write(&path, "[").unwrap();
+        let error = compile_bf_to_rust(&path, &options).unwrap_err();
+        assert!(error.to_string().ends_with(": Unmatched '[' at position 0"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            compile_bf_to_rust(&path, &options),
+            Err(BuildError::Io(..))
+        ));
+    }
+}
@@ -0,0 +1,431 @@
+//! Step-by-step execution for debuggers, REPLs and visualizations.
+//!
+//! [`Debugger`] runs a program like [`crate::interpret::run`], one instruction at a
+//! time, and exposes the machine state in between. Execution stops at breakpoints, set
+//! on instruction positions or on every `#`, the conventional debug command of
+//! Brainfuck interpreters. Outside of breakpoints, `#` is a comment as usual.
+//!
+//! [`Debugger::snapshot`] saves the state of the machine, and [`Debugger::restore`]
+//! brings it back, to step backwards or to resume a program later. With the `serde`
+//! feature, snapshots can be serialized, for save states of web playgrounds or
+//! long-running programs.
+//!
+//! # Example
+//!
+//! ```rust
+//! use brainfuck_core::debug::{Breakpoint, Debugger, Stop};
+//!
+//! let mut debugger = Debugger::new("+++#>++#.", b"").unwrap();
+//! assert_eq!(debugger.run_until(Breakpoint::Hash), Ok(Stop::Breakpoint(3)));
+//! assert_eq!((debugger.pointer(), debugger.tape()[0]), (0, 3));
+//! debugger.step().unwrap();
+//! assert_eq!(debugger.run_until(Breakpoint::Hash), Ok(Stop::Breakpoint(7)));
+//! assert_eq!(&debugger.tape()[..2], &[3, 2]);
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::interpret::{Outputs, RunError, MAX_STEPS, TAPE_SIZE};
+
+/// Where execution stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before executing the character at this position
+    Instruction(usize),
+    /// Before executing any `#`
+    Hash,
+}
+
+impl Breakpoint {
+    /// Whether the breakpoint is hit before executing the character at `ip`
+    fn hit(self, ip: usize, instruction: char) -> bool {
+        match self {
+            Breakpoint::Instruction(position) => ip == position,
+            Breakpoint::Hash => instruction == '#',
+        }
+    }
+}
+
+/// Why execution stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// A breakpoint was hit before the character at this position
+    Breakpoint(usize),
+    /// The program ended
+    Finished,
+}
+
+/// The state of a [`Debugger`] at one point of the execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// The tape up to the last nonzero cell; the cells after it are zero
+    pub tape: Vec<u8>,
+    /// The position of the pointer on the tape
+    pub pointer: usize,
+    /// The position of the next instruction in the code, in characters
+    pub ip: usize,
+    /// The number of instructions executed
+    pub steps: usize,
+    /// The number of input bytes read
+    pub input_pos: usize,
+    /// The bytes written by `.`
+    pub output: Vec<u8>,
+}
+
+/// Error returned when a snapshot cannot be restored into a debugger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The tape or the pointer extends beyond [`TAPE_SIZE`]
+    Tape,
+    /// The position is beyond the end of the program
+    Position,
+}
+
+impl core::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SnapshotError::Tape => write!(f, "the snapshot's tape exceeds {} cells", TAPE_SIZE),
+            SnapshotError::Position => {
+                write!(
+                    f,
+                    "the snapshot's position is beyond the end of the program"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+/// Where execution stands after [`Debugger::run_for`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunStatus {
+    /// The fuel ran out; calling [`Debugger::run_for`] again resumes the program
+    Paused,
+    /// A breakpoint was hit before the character at this position
+    Breakpoint(usize),
+    /// The program ended
+    Finished,
+    /// The program failed
+    Failed(RunError),
+}
+
+/// A program executed one instruction at a time.
+///
+/// The tape, input handling and limits are those of [`crate::interpret::run`]. The step
+/// limit applies to every call of [`Debugger::run_until`] and [`Debugger::run`]
+/// separately, so a session can go on for as long as the user keeps resuming it.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    program: Outputs,
+    output: Vec<u8>,
+    breakpoints: Vec<Breakpoint>,
+    /// Whether execution stopped at a breakpoint before the next instruction, so
+    /// resuming does not stop there again
+    at_breakpoint: bool,
+}
+
+impl Debugger {
+    /// Prepare a program for debugging over `input`, checking its brackets
+    pub fn new(code: &str, input: &[u8]) -> Result<Self, RunError> {
+        Ok(Self {
+            program: Outputs::new(code, input)?,
+            output: Vec::new(),
+            breakpoints: Vec::new(),
+            at_breakpoint: false,
+        })
+    }
+
+    /// Stop [`Debugger::run`] at the breakpoint
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    /// Stop no longer at the breakpoint, returning whether it was set
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|&set| set != breakpoint);
+        self.breakpoints.len() < count
+    }
+
+    /// The breakpoints set with [`Debugger::add_breakpoint`]
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Execute the next instruction, returning whether the program was still running
+    pub fn step(&mut self) -> Result<bool, RunError> {
+        if self.program.is_finished() {
+            return Ok(false);
+        }
+        if let Some(byte) = self.program.step()? {
+            self.output.push(byte);
+        }
+        self.at_breakpoint = false;
+        Ok(true)
+    }
+
+    /// Execute until `breakpoint` is hit or the program ends. A breakpoint that stopped
+    /// execution does not stop it again right away when resuming.
+    pub fn run_until(&mut self, breakpoint: Breakpoint) -> Result<Stop, RunError> {
+        self.run_to(|ip, instruction| breakpoint.hit(ip, instruction))
+    }
+
+    /// Execute until any of the breakpoints set is hit or the program ends
+    pub fn run(&mut self) -> Result<Stop, RunError> {
+        let breakpoints = self.breakpoints.clone();
+        self.run_to(|ip, instruction| {
+            breakpoints
+                .iter()
+                .any(|breakpoint| breakpoint.hit(ip, instruction))
+        })
+    }
+
+    /// Execute at most `fuel` instructions, stopping early at the breakpoints set or at
+    /// the end of the program, and report where execution stands. Hosts like games, GUIs
+    /// or async executors can call this repeatedly to run a program in bounded slices
+    /// between other work. There is no step limit beyond the fuel.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use brainfuck_core::debug::{Debugger, RunStatus};
+    ///
+    /// let mut debugger = Debugger::new("+++++[>+++++++++++++<-]>.", b"").unwrap();
+    /// let mut slices = 1;
+    /// while debugger.run_for(10) == RunStatus::Paused {
+    ///     slices += 1;
+    /// }
+    /// assert_eq!((slices, debugger.output()), (10, &b"A"[..]));
+    /// ```
+    pub fn run_for(&mut self, fuel: u64) -> RunStatus {
+        let breakpoints = self.breakpoints.clone();
+        let limit = usize::try_from(fuel).unwrap_or(usize::MAX);
+        let hit = |ip, instruction| {
+            breakpoints
+                .iter()
+                .any(|breakpoint: &Breakpoint| breakpoint.hit(ip, instruction))
+        };
+        match self.advance(limit, hit) {
+            Ok(Some(Stop::Finished)) => RunStatus::Finished,
+            Ok(Some(Stop::Breakpoint(ip))) => RunStatus::Breakpoint(ip),
+            Ok(None) => RunStatus::Paused,
+            Err(e) => RunStatus::Failed(e),
+        }
+    }
+
+    fn run_to(&mut self, hit: impl FnMut(usize, char) -> bool) -> Result<Stop, RunError> {
+        self.advance(MAX_STEPS, hit)?
+            .ok_or(RunError::MaxStepsExceeded)
+    }
+
+    /// Execute at most `limit` instructions until `hit` or the end of the program,
+    /// returning `None` if the limit was reached first
+    fn advance(
+        &mut self,
+        limit: usize,
+        mut hit: impl FnMut(usize, char) -> bool,
+    ) -> Result<Option<Stop>, RunError> {
+        let mut steps = 0;
+        loop {
+            if self.program.is_finished() {
+                return Ok(Some(Stop::Finished));
+            }
+            let ip = self.program.ip();
+            // The breakpoint execution stopped at last time is passed
+            if !self.at_breakpoint && hit(ip, self.program.chars()[ip]) {
+                self.at_breakpoint = true;
+                return Ok(Some(Stop::Breakpoint(ip)));
+            }
+            if steps >= limit {
+                return Ok(None);
+            }
+            self.step()?;
+            steps += 1;
+        }
+    }
+
+    /// The tape
+    pub fn tape(&self) -> &[u8] {
+        self.program.tape()
+    }
+
+    /// The position of the pointer on the tape
+    pub fn pointer(&self) -> usize {
+        self.program.pointer()
+    }
+
+    /// The position of the next instruction in the code, in characters
+    pub fn position(&self) -> usize {
+        self.program.ip()
+    }
+
+    /// The next instruction, or `None` once the program ended
+    pub fn instruction(&self) -> Option<char> {
+        self.program.chars().get(self.program.ip()).copied()
+    }
+
+    /// The bytes written by `.` so far
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// The number of instructions executed so far, comments included
+    pub fn steps(&self) -> usize {
+        self.program.steps()
+    }
+
+    /// Whether the program ended
+    pub fn is_finished(&self) -> bool {
+        self.program.is_finished()
+    }
+
+    /// Save the state of the machine. Breakpoints are not part of it.
+    pub fn snapshot(&self) -> Snapshot {
+        let tape = self.tape();
+        let used = tape
+            .iter()
+            .rposition(|&cell| cell != 0)
+            .map_or(0, |last| last + 1);
+        Snapshot {
+            tape: tape[..used].to_vec(),
+            pointer: self.pointer(),
+            ip: self.position(),
+            steps: self.steps(),
+            input_pos: self.program.input_pos(),
+            output: self.output.clone(),
+        }
+    }
+
+    /// Continue from a snapshot of this program, taken by this debugger or another one
+    /// debugging the same code and input
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        if snapshot.tape.len() > TAPE_SIZE || snapshot.pointer >= TAPE_SIZE {
+            return Err(SnapshotError::Tape);
+        }
+        if snapshot.ip > self.program.chars().len() {
+            return Err(SnapshotError::Position);
+        }
+        self.program.set_state(
+            &snapshot.tape,
+            snapshot.pointer,
+            snapshot.ip,
+            snapshot.steps,
+            snapshot.input_pos,
+        );
+        self.output = snapshot.output.clone();
+        self.at_breakpoint = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step() {
+        let mut debugger = Debugger::new(",+.", b"a").unwrap();
+        assert_eq!(debugger.instruction(), Some(','));
+        assert_eq!(debugger.step(), Ok(true));
+        assert_eq!(debugger.tape()[0], b'a');
+        assert_eq!(debugger.step(), Ok(true));
+        assert_eq!(debugger.step(), Ok(true));
+        assert_eq!(debugger.output(), b"b");
+        assert_eq!(debugger.step(), Ok(false));
+        assert!(debugger.is_finished());
+        assert_eq!(debugger.steps(), 3);
+    }
+
+    #[test]
+    fn test_instruction_breakpoints() {
+        // The breakpoint inside the loop is hit on every iteration
+        let mut debugger = Debugger::new("+++[>+<-]", b"").unwrap();
+        debugger.add_breakpoint(Breakpoint::Instruction(5));
+        let mut cells = Vec::new();
+        while debugger.run() == Ok(Stop::Breakpoint(5)) {
+            cells.push(debugger.tape()[1]);
+        }
+        assert_eq!(cells, [0, 1, 2]);
+        assert!(debugger.is_finished());
+        assert!(debugger.remove_breakpoint(Breakpoint::Instruction(5)));
+        assert!(debugger.breakpoints().is_empty());
+    }
+
+    #[test]
+    fn test_run_for() {
+        let mut debugger = Debugger::new("+++#+.<", b"").unwrap();
+        debugger.add_breakpoint(Breakpoint::Hash);
+        assert_eq!(debugger.run_for(2), RunStatus::Paused);
+        assert_eq!(debugger.steps(), 2);
+        // A breakpoint reached with the last of the fuel is still hit, and only once
+        assert_eq!(debugger.run_for(1), RunStatus::Breakpoint(3));
+        assert_eq!(debugger.run_for(0), RunStatus::Paused);
+        assert_eq!(debugger.run_for(3), RunStatus::Paused);
+        assert_eq!(debugger.output(), [4]);
+        assert_eq!(
+            debugger.run_for(u64::MAX),
+            RunStatus::Failed(RunError::PointerUnderflow)
+        );
+        let mut debugger = Debugger::new("+", b"").unwrap();
+        assert_eq!(debugger.run_for(5), RunStatus::Finished);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut debugger = Debugger::new("+<", b"").unwrap();
+        assert_eq!(
+            debugger.run_until(Breakpoint::Hash),
+            Err(RunError::PointerUnderflow)
+        );
+        // The failing instruction is not skipped
+        assert_eq!(debugger.instruction(), Some('<'));
+        let mut debugger = Debugger::new("+[]", b"").unwrap();
+        assert_eq!(
+            debugger.run_until(Breakpoint::Hash),
+            Err(RunError::MaxStepsExceeded)
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut debugger = Debugger::new(",+.#,+.", b"ab").unwrap();
+        debugger.run_until(Breakpoint::Hash).unwrap();
+        let snapshot = debugger.snapshot();
+        assert_eq!(snapshot.tape, [b'b']);
+        assert_eq!((snapshot.ip, snapshot.steps, snapshot.input_pos), (3, 3, 1));
+        assert_eq!(debugger.run(), Ok(Stop::Finished));
+        assert_eq!(debugger.output(), b"bc");
+
+        // Going back and running again gives the same result
+        debugger.restore(&snapshot).unwrap();
+        assert_eq!(debugger.output(), b"b");
+        assert_eq!(debugger.run(), Ok(Stop::Finished));
+        assert_eq!(debugger.output(), b"bc");
+
+        let mut other = Debugger::new("+", b"").unwrap();
+        assert_eq!(other.restore(&snapshot), Err(SnapshotError::Position));
+        let huge = Snapshot {
+            pointer: TAPE_SIZE,
+            ..snapshot
+        };
+        assert_eq!(debugger.restore(&huge), Err(SnapshotError::Tape));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_serde() {
+        let mut debugger = Debugger::new("++>+++#", b"").unwrap();
+        debugger.run().unwrap();
+        let json = serde_json::to_string(&debugger.snapshot()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"tape":[2,3],"pointer":1,"ip":7,"steps":7,"input_pos":0,"output":[]}"#
+        );
+        let snapshot: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, debugger.snapshot());
+    }
+}
@@ -0,0 +1,245 @@
+//! Panic-free evaluation with bounded resources.
+//!
+//! [`eval`] takes arbitrary bytes as a program and never panics, allocates no more than
+//! its [`Limits`] allow and stops after a bounded number of steps, whatever it is given.
+//! That makes it the surface for fuzzing the interpreter without the proc-macro
+//! plumbing, for example with a `cargo fuzz` target like:
+//!
+//! ```rust,ignore
+//! #![no_main]
+//! use brainfuck_core::eval::{eval, Limits};
+//!
+//! libfuzzer_sys::fuzz_target!(|data: (&[u8], &[u8])| {
+//!     let _ = eval(data.0, data.1, Limits::default());
+//! });
+//! ```
+
+use alloc::{vec, vec::Vec};
+
+use crate::interpret::{MAX_STEPS, TAPE_SIZE};
+
+/// Resource limits of [`eval`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum length of the program in bytes (default 64 KiB)
+    pub max_code_len: usize,
+    /// The maximum number of executed commands (default [`MAX_STEPS`])
+    pub max_steps: usize,
+    /// The number of cells of the tape, at least 1 (default [`TAPE_SIZE`])
+    pub tape_size: usize,
+    /// The maximum length of the output in bytes (default 64 KiB)
+    pub max_output: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_code_len: 64 * 1024,
+            max_steps: MAX_STEPS,
+            tape_size: TAPE_SIZE,
+            max_output: 64 * 1024,
+        }
+    }
+}
+
+/// The result of a successful [`eval`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// The bytes written by `.`
+    pub bytes: Vec<u8>,
+    /// The number of commands executed
+    pub steps: usize,
+}
+
+/// Error returned by [`eval`], with positions in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The program is longer than [`Limits::max_code_len`]
+    CodeTooLong(usize),
+    /// Unmatched opening bracket at the given byte position
+    UnmatchedOpenBracket(usize),
+    /// Unmatched closing bracket at the given byte position
+    UnmatchedCloseBracket(usize),
+    /// The pointer moved below cell 0 at the given byte position
+    PointerUnderflow(usize),
+    /// The pointer moved beyond the last cell at the given byte position
+    PointerOverflow(usize),
+    /// Execution exceeded [`Limits::max_steps`]
+    StepLimitExceeded,
+    /// The output exceeded [`Limits::max_output`]
+    OutputLimitExceeded,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::CodeTooLong(len) => write!(f, "Program of {} bytes is too long", len),
+            Error::UnmatchedOpenBracket(pos) => write!(f, "Unmatched '[' at byte {}", pos),
+            Error::UnmatchedCloseBracket(pos) => write!(f, "Unmatched ']' at byte {}", pos),
+            Error::PointerUnderflow(pos) => write!(f, "Pointer moved below zero at byte {}", pos),
+            Error::PointerOverflow(pos) => {
+                write!(
+                    f,
+                    "Pointer moved beyond the end of the tape at byte {}",
+                    pos
+                )
+            }
+            Error::StepLimitExceeded => write!(f, "Execution exceeded the step limit"),
+            Error::OutputLimitExceeded => write!(f, "Output exceeded the output limit"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Run a program given as bytes over `input` within `limits`.
+///
+/// Every byte other than the eight commands is a comment, so any byte string is a
+/// program, and `,` stores 0 once the input is exhausted. Cells are bytes wrapping
+/// around like in [`crate::interpret::run`], but steps count executed commands only.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::eval::{eval, Error, Limits};
+///
+/// let output = eval(b",[.,]", b"fuzz", Limits::default()).unwrap();
+/// assert_eq!((output.bytes, output.steps), (b"fuzz".to_vec(), 14));
+///
+/// let limits = Limits {
+///     max_steps: 100,
+///     ..Limits::default()
+/// };
+/// assert_eq!(eval(b"+[]", b"", limits), Err(Error::StepLimitExceeded));
+/// assert_eq!(eval(b"\xff]", b"", limits), Err(Error::UnmatchedCloseBracket(1)));
+/// ```
+pub fn eval(code: &[u8], input: &[u8], limits: Limits) -> Result<Output, Error> {
+    if code.len() > limits.max_code_len {
+        return Err(Error::CodeTooLong(code.len()));
+    }
+    let jumps = jump_table(code)?;
+    let mut tape = vec![0u8; limits.tape_size.max(1)];
+    let mut pointer = 0;
+    let mut input = input.iter().copied();
+    let mut bytes = Vec::new();
+    let mut steps = 0;
+    let mut ip = 0;
+    while let Some(&command) = code.get(ip) {
+        if !b"<>+-.,[]".contains(&command) {
+            ip += 1;
+            continue;
+        }
+        if steps >= limits.max_steps {
+            return Err(Error::StepLimitExceeded);
+        }
+        steps += 1;
+        match command {
+            b'>' => {
+                if pointer + 1 >= tape.len() {
+                    return Err(Error::PointerOverflow(ip));
+                }
+                pointer += 1;
+            }
+            b'<' => {
+                if pointer == 0 {
+                    return Err(Error::PointerUnderflow(ip));
+                }
+                pointer -= 1;
+            }
+            b'+' => tape[pointer] = tape[pointer].wrapping_add(1),
+            b'-' => tape[pointer] = tape[pointer].wrapping_sub(1),
+            b'.' => {
+                if bytes.len() >= limits.max_output {
+                    return Err(Error::OutputLimitExceeded);
+                }
+                bytes.push(tape[pointer]);
+            }
+            b',' => tape[pointer] = input.next().unwrap_or(0),
+            b'[' if tape[pointer] == 0 => ip = jumps[ip],
+            b']' if tape[pointer] != 0 => ip = jumps[ip],
+            _ => {}
+        }
+        ip += 1;
+    }
+    Ok(Output { bytes, steps })
+}
+
+/// The position of the matching bracket of every bracket, and 0 for other bytes
+fn jump_table(code: &[u8]) -> Result<Vec<usize>, Error> {
+    let mut jumps = vec![0; code.len()];
+    let mut stack = Vec::new();
+    for (i, &byte) in code.iter().enumerate() {
+        match byte {
+            b'[' => stack.push(i),
+            b']' => {
+                let open = stack.pop().ok_or(Error::UnmatchedCloseBracket(i))?;
+                jumps[open] = i;
+                jumps[i] = open;
+            }
+            _ => {}
+        }
+    }
+    match stack.pop() {
+        Some(open) => Err(Error::UnmatchedOpenBracket(open)),
+        None => Ok(jumps),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits {
+            max_code_len: 8,
+            tape_size: 0,
+            max_output: 2,
+            ..Limits::default()
+        };
+        assert_eq!(eval(b"+++++++++", b"", limits), Err(Error::CodeTooLong(9)));
+        assert_eq!(eval(b"+.", b"", limits).unwrap().bytes, [1]);
+        assert_eq!(eval(b"x>", b"", limits), Err(Error::PointerOverflow(1)));
+        assert_eq!(eval(b"<", b"", limits), Err(Error::PointerUnderflow(0)));
+        assert_eq!(eval(b"+[.]", b"", limits), Err(Error::OutputLimitExceeded));
+        assert_eq!(
+            eval(b"[[]", b"", limits),
+            Err(Error::UnmatchedOpenBracket(0))
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_bytes() {
+        // A fixed pseudo-random sequence of programs drawn mostly from the commands
+        let mut state = 0x2545_f491_u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        let limits = Limits {
+            max_steps: 10_000,
+            tape_size: 16,
+            max_output: 64,
+            ..Limits::default()
+        };
+        for _ in 0..1_000 {
+            let len = next() as usize % 32;
+            let code: Vec<u8> = (0..len)
+                .map(|_| match next() % 10 {
+                    0 => next() as u8,
+                    n => b"<>+-.,[]["[n as usize - 1],
+                })
+                .collect();
+            match eval(&code, b"in", limits) {
+                Ok(output) => {
+                    assert!(output.steps <= limits.max_steps);
+                    assert!(output.bytes.len() <= limits.max_output);
+                }
+                Err(error) => assert!(!error.to_string().is_empty()),
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Pretty-printer for Brainfuck source.
+//!
+//! Comments are dropped and the commands are laid out one bracket depth per indentation
+//! level. Loops without nested loops that fit within the line width stay on one line,
+//! all other loops put `[` and `]` on lines of their own.
+
+/// Layout options for [`format`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Number of spaces per bracket depth
+    pub indent: usize,
+    /// Maximum line width, including indentation
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: 4,
+            width: 80,
+        }
+    }
+}
+
+/// Error returned when the source cannot be formatted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// Unmatched opening bracket at the given character position
+    UnmatchedOpenBracket(usize),
+    /// Unmatched closing bracket at the given character position
+    UnmatchedCloseBracket(usize),
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::UnmatchedOpenBracket(pos) => {
+                write!(f, "Unmatched '[' at position {}", pos)
+            }
+            FormatError::UnmatchedCloseBracket(pos) => {
+                write!(f, "Unmatched ']' at position {}", pos)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// A run of non-bracket commands or a loop
+enum Node {
+    Commands(String),
+    Loop(Vec<Node>),
+}
+
+/// Format Brainfuck source according to `options`.
+///
+/// The result has no trailing newline. A line never exceeds `options.width` unless the
+/// indentation alone already does.
+pub fn format(code: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let nodes = parse(code)?;
+    let mut lines = Vec::new();
+    write_nodes(&nodes, 0, options, &mut lines);
+    Ok(lines.join("\n"))
+}
+
+/// Build the node tree, keeping every command (no folding)
+fn parse(code: &str) -> Result<Vec<Node>, FormatError> {
+    let mut stack: Vec<(usize, Vec<Node>)> = Vec::new();
+    let mut current = Vec::new();
+
+    for (i, ch) in code.chars().enumerate() {
+        match ch {
+            '+' | '-' | '<' | '>' | '.' | ',' => {
+                if let Some(Node::Commands(run)) = current.last_mut() {
+                    run.push(ch);
+                } else {
+                    current.push(Node::Commands(ch.to_string()));
+                }
+            }
+            '[' => stack.push((i, std::mem::take(&mut current))),
+            ']' => {
+                let (_, parent) = stack.pop().ok_or(FormatError::UnmatchedCloseBracket(i))?;
+                let body = std::mem::replace(&mut current, parent);
+                current.push(Node::Loop(body));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((open_pos, _)) = stack.pop() {
+        return Err(FormatError::UnmatchedOpenBracket(open_pos));
+    }
+    Ok(current)
+}
+
+/// The single-line source of a loop body, or `None` if it contains nested loops
+fn inline_body(nodes: &[Node]) -> Option<String> {
+    nodes
+        .iter()
+        .map(|node| match node {
+            Node::Commands(run) => Some(run.as_str()),
+            Node::Loop(_) => None,
+        })
+        .collect()
+}
+
+fn write_nodes(nodes: &[Node], depth: usize, options: &FormatOptions, lines: &mut Vec<String>) {
+    let indent = " ".repeat(depth * options.indent);
+    let room = options.width.saturating_sub(indent.len()).max(1);
+
+    for node in nodes {
+        match node {
+            Node::Commands(run) => {
+                let chars: Vec<char> = run.chars().collect();
+                for chunk in chars.chunks(room) {
+                    lines.push(format!("{}{}", indent, chunk.iter().collect::<String>()));
+                }
+            }
+            Node::Loop(body) => match inline_body(body) {
+                Some(inline) if inline.len() + 2 <= room => {
+                    lines.push(format!("{}[{}]", indent, inline));
+                }
+                _ => {
+                    lines.push(format!("{}[", indent));
+                    write_nodes(body, depth + 1, options, lines);
+                    lines.push(format!("{}]", indent));
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_layout() {
+        let code = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.";
+        let expected = "\
+++++++++
+[
+    >++++
+    [>++>+++>+++>+<<<<-]
+    >+>+>->>+
+    [<]
+    <-
+]
+>>.";
+        assert_eq!(format(code, &FormatOptions::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_comments_dropped() {
+        let code = "Set to three +++ and print .";
+        assert_eq!(format(code, &FormatOptions::default()).unwrap(), "+++.");
+    }
+
+    #[test]
+    fn test_width_wraps_runs_and_loops() {
+        let options = FormatOptions {
+            indent: 2,
+            width: 6,
+        };
+        let code = "++++++++++[>+++++<-]";
+        let expected = "\
+++++++
+++++
+[
+  >+++
+  ++<-
+]";
+        assert_eq!(format(code, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        let options = FormatOptions::default();
+        assert_eq!(
+            format("+[", &options),
+            Err(FormatError::UnmatchedOpenBracket(1))
+        );
+        assert_eq!(
+            format("]", &options),
+            Err(FormatError::UnmatchedCloseBracket(0))
+        );
+    }
+}
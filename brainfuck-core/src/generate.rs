@@ -0,0 +1,248 @@
+//! Generation of Brainfuck programs that print given text.
+
+/// Overhead of a multiplication loop `>[<>-]<`, excluding the two factors
+const LOOP_OVERHEAD: usize = 7;
+
+/// The most cells [`golf`] sets up
+const MAX_GOLF_CELLS: usize = 6;
+
+/// The largest loop counter [`golf`] tries for setting up cells
+const MAX_GOLF_COUNTER: usize = 20;
+
+/// Generate a Brainfuck program that prints `bytes`.
+///
+/// Cell 0 holds the character being printed and cell 1 serves as a loop counter. Each
+/// byte is reached from the previous one either with a plain run of `+`/`-` or with a
+/// multiplication loop, whichever is shorter.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::generate::print_bytes;
+///
+/// assert_eq!(print_bytes(b"AB"), ">++++++++[<++++++++>-]<+.+.");
+/// ```
+pub fn print_bytes(bytes: &[u8]) -> String {
+    let mut code = String::new();
+    let mut current = 0u8;
+    for &byte in bytes {
+        code.push_str(&adjust(current, byte));
+        code.push('.');
+        current = byte;
+    }
+    code
+}
+
+/// Generate a short Brainfuck program that prints `bytes`, searching harder than
+/// [`print_bytes`].
+///
+/// Besides the single-cell approach of [`print_bytes`], this tries setting up several
+/// cells at once with a shared multiplication loop (`++++++[>+++++++++++>++++++++<<-]`),
+/// with cell values near the bytes to print, and then prints every byte from the cell
+/// that is cheapest to reach and adjust. The shortest program found is returned, so the
+/// result is never longer than that of [`print_bytes`].
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::generate::{golf, print_bytes};
+///
+/// let text = b"Hello, World!";
+/// assert!(golf(text).len() < print_bytes(text).len());
+/// ```
+pub fn golf(bytes: &[u8]) -> String {
+    let mut best = print_bytes(bytes);
+    for cells in 1..=MAX_GOLF_CELLS {
+        let centers = centers(bytes, cells);
+        for counter in 2..=MAX_GOLF_COUNTER {
+            let candidate = print_from_cells(bytes, counter, &centers);
+            if candidate.len() < best.len() {
+                best = candidate;
+            }
+        }
+    }
+    best
+}
+
+/// Split the sorted bytes into `count` groups of equal size and take their medians
+fn centers(bytes: &[u8], count: usize) -> Vec<u8> {
+    let mut sorted = bytes.to_vec();
+    sorted.sort_unstable();
+    let mut centers: Vec<u8> = (0..count)
+        .filter_map(|group| {
+            let start = group * sorted.len() / count;
+            let end = (group + 1) * sorted.len() / count;
+            sorted[start..end].get((end - start) / 2).copied()
+        })
+        .collect();
+    centers.dedup();
+    centers
+}
+
+/// Set up one cell per center with a multiplication loop on cell 0, then print each
+/// byte from the cell that is cheapest to move to and adjust
+fn print_from_cells(bytes: &[u8], counter: usize, centers: &[u8]) -> String {
+    let counter_value = counter as i32;
+    let mut factors: Vec<i32> = centers
+        .iter()
+        .map(|&center| {
+            // Negative factors reach bytes above 127 by wrapping
+            let center = center as i8 as i32;
+            (center + center.signum() * counter_value / 2) / counter_value
+        })
+        .collect();
+    factors.dedup();
+
+    let mut code = repeat('+', counter);
+    code.push('[');
+    for &factor in &factors {
+        code.push('>');
+        code.push_str(&repeat(
+            if factor < 0 { '-' } else { '+' },
+            factor.unsigned_abs() as usize,
+        ));
+    }
+    code.push_str(&repeat('<', factors.len()));
+    code.push_str("-]");
+
+    // Cell 0 is the (now cleared) loop counter and can be used like the others
+    let mut values: Vec<u8> = std::iter::once(0)
+        .chain(factors.iter().map(|&factor| (counter_value * factor) as u8))
+        .collect();
+    let mut position = 0;
+    for &byte in bytes {
+        let cost = |cell: usize, value: u8| {
+            cell.abs_diff(position) + (byte.wrapping_sub(value) as i8).unsigned_abs() as usize
+        };
+        let (cell, &value) = values
+            .iter()
+            .enumerate()
+            .min_by_key(|&(cell, &value)| cost(cell, value))
+            .expect("cell 0 always exists");
+        let moves = if cell < position { '<' } else { '>' };
+        code.push_str(&repeat(moves, cell.abs_diff(position)));
+        let delta = byte.wrapping_sub(value) as i8;
+        code.push_str(&repeat(
+            if delta < 0 { '-' } else { '+' },
+            delta.unsigned_abs() as usize,
+        ));
+        code.push('.');
+        values[cell] = byte;
+        position = cell;
+    }
+    code
+}
+
+/// Shortest code (of the forms considered) turning cell 0 from `from` into `to`
+fn adjust(from: u8, to: u8) -> String {
+    // Take the shorter way around, since cells wrap
+    let delta = to.wrapping_sub(from) as i8 as i32;
+    let (up, down) = if delta >= 0 { ('+', '-') } else { ('-', '+') };
+    let distance = delta.unsigned_abs() as usize;
+
+    let mut best = repeat(up, distance);
+    for outer in 2..=distance {
+        for inner in [distance / outer, distance / outer + 1] {
+            if inner < 2 {
+                continue;
+            }
+            let product = outer * inner;
+            let (rest_char, rest) = if product <= distance {
+                (up, distance - product)
+            } else {
+                (down, product - distance)
+            };
+            if outer + inner + rest + LOOP_OVERHEAD < best.len() {
+                best = format!(
+                    ">{}[<{}>-]<{}",
+                    repeat('+', outer),
+                    repeat(up, inner),
+                    repeat(rest_char, rest)
+                );
+            }
+        }
+    }
+    best
+}
+
+fn repeat(ch: char, count: usize) -> String {
+    std::iter::repeat_n(ch, count).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal interpreter for checking generated programs
+    fn run(code: &str) -> Vec<u8> {
+        let code: Vec<char> = code.chars().collect();
+        let (mut tape, mut ptr, mut ip, mut out) = (vec![0u8; 16], 0usize, 0usize, Vec::new());
+        while ip < code.len() {
+            match code[ip] {
+                '>' => ptr += 1,
+                '<' => ptr -= 1,
+                '+' => tape[ptr] = tape[ptr].wrapping_add(1),
+                '-' => tape[ptr] = tape[ptr].wrapping_sub(1),
+                '.' => out.push(tape[ptr]),
+                '[' if tape[ptr] == 0 => {
+                    while code[ip] != ']' {
+                        ip += 1;
+                    }
+                }
+                ']' if tape[ptr] != 0 => {
+                    while code[ip] != '[' {
+                        ip -= 1;
+                    }
+                }
+                _ => {}
+            }
+            ip += 1;
+        }
+        out
+    }
+
+    #[test]
+    fn test_prints_text() {
+        let text = b"Hello, World!\n";
+        assert_eq!(run(&print_bytes(text)), text);
+    }
+
+    #[test]
+    fn test_wrapping_and_high_bytes() {
+        let bytes = [0xff, 0x00, 0x80, 0x7f, 0x20];
+        assert_eq!(run(&print_bytes(&bytes)), bytes);
+    }
+
+    #[test]
+    fn test_uses_loops_for_large_deltas() {
+        let code = print_bytes(b"H");
+        assert_eq!(code, ">++++++++[<+++++++++>-]<.");
+        assert!(code.len() < 72);
+    }
+
+    #[test]
+    fn test_golf_prints_text() {
+        for text in [
+            &b"Hello, World!\n"[..],
+            b"",
+            b"aaaa",
+            &[0xff, 0x00, 0x80, 0x7f, 0x20],
+        ] {
+            let code = golf(text);
+            assert_eq!(run(&code), text);
+            assert!(code.len() <= print_bytes(text).len());
+        }
+    }
+
+    #[test]
+    fn test_golf_shares_loop_constants() {
+        let text = b"The quick brown fox jumps over the lazy dog";
+        assert!(golf(text).len() * 3 < print_bytes(text).len() * 2);
+    }
+
+    #[test]
+    fn test_small_deltas_stay_plain() {
+        assert_eq!(adjust(72, 69), "---");
+        assert_eq!(adjust(10, 10), "");
+    }
+}
@@ -0,0 +1,658 @@
+//! Runtime interpreter.
+//!
+//! [`run`] uses the same limits as the compile-time interpreter of the macros, so a
+//! program behaves identically whether it is evaluated by `brainfuck!` or at runtime.
+//! Unlike the macros, input is always available: `,` reads the next byte of the given
+//! input and stores 0 once it is exhausted. [`stream`] runs a program over readers and
+//! writers instead of buffers.
+//!
+//! [`run_with_tape`] needs neither `std` nor an allocator: it runs on a tape provided by
+//! the caller and passes output bytes to a callback, for `no_std` targets. [`run`] and
+//! [`Outputs`] require the `alloc` feature and [`stream`] the `std` feature, both
+//! enabled by default.
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// The number of cells in the tape
+pub const TAPE_SIZE: usize = 30_000;
+
+/// The maximum number of execution steps, counting every character of the source
+pub const MAX_STEPS: usize = 1_000_000;
+
+/// Error returned when a program cannot be run to completion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    /// Unmatched opening bracket at the given character position
+    UnmatchedOpenBracket(usize),
+    /// Unmatched closing bracket at the given character position
+    UnmatchedCloseBracket(usize),
+    /// The pointer moved below cell 0
+    PointerUnderflow,
+    /// The pointer moved beyond the last cell of the tape
+    PointerOverflow,
+    /// Execution exceeded [`MAX_STEPS`]
+    MaxStepsExceeded,
+}
+
+impl core::fmt::Display for RunError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RunError::UnmatchedOpenBracket(pos) => {
+                write!(f, "Unmatched '[' at position {}", pos)
+            }
+            RunError::UnmatchedCloseBracket(pos) => {
+                write!(f, "Unmatched ']' at position {}", pos)
+            }
+            RunError::PointerUnderflow => {
+                write!(f, "Pointer moved below zero")
+            }
+            RunError::PointerOverflow => {
+                write!(f, "Pointer moved beyond the end of the tape")
+            }
+            RunError::MaxStepsExceeded => {
+                write!(f, "Execution exceeded maximum steps ({})", MAX_STEPS)
+            }
+        }
+    }
+}
+
+impl core::error::Error for RunError {}
+
+/// A cell of a tape passed to [`run_with_tape`], wrapping around at its width
+pub trait Cell: Copy + PartialEq {
+    /// The value of a cleared cell
+    const ZERO: Self;
+
+    /// The cell plus one, wrapping around
+    fn increment(self) -> Self;
+
+    /// The cell minus one, wrapping around
+    fn decrement(self) -> Self;
+
+    /// The cell holding a byte read by `,`
+    fn from_byte(byte: u8) -> Self;
+
+    /// The byte `.` outputs: the lowest 8 bits of the cell
+    fn to_byte(self) -> u8;
+}
+
+macro_rules! impl_cell {
+    ($($ty:ty),*) => {
+        $(
+            impl Cell for $ty {
+                const ZERO: Self = 0;
+
+                fn increment(self) -> Self {
+                    self.wrapping_add(1)
+                }
+
+                fn decrement(self) -> Self {
+                    self.wrapping_sub(1)
+                }
+
+                fn from_byte(byte: u8) -> Self {
+                    byte.into()
+                }
+
+                fn to_byte(self) -> u8 {
+                    self as u8
+                }
+            }
+        )*
+    };
+}
+
+impl_cell!(u8, u16, u32);
+
+/// Run a program over `input` on a tape provided by the caller, passing every output
+/// byte to `output`, without allocating.
+///
+/// Apart from the tape, this behaves like [`run`]: the step limit is [`MAX_STEPS`] and
+/// `,` stores 0 once the input is exhausted. The tape is used as it is, so it can be
+/// reused between runs, pre-filled to pass data to the program, placed in static memory
+/// on embedded targets, and inspected afterwards. Its cells can be `u8`, `u16` or `u32`;
+/// `.` outputs the lowest 8 bits of wider cells.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::interpret::run_with_tape;
+///
+/// let mut tape = [0u8; 16];
+/// let mut output = [0u8; 4];
+/// let mut len = 0;
+/// run_with_tape(",[+.,]", &mut tape, b"HAL", |byte| {
+///     output[len] = byte;
+///     len += 1;
+/// })
+/// .unwrap();
+/// assert_eq!(&output[..len], b"IBM");
+///
+/// // 16-bit cells don't wrap around at 256
+/// let mut tape = [0u16; 2];
+/// run_with_tape("++++++++++++++++[>++++++++++++++++<-]>+", &mut tape, b"", |_| {}).unwrap();
+/// assert_eq!(tape, [0, 257]);
+/// ```
+pub fn run_with_tape<C: Cell>(
+    code: &str,
+    tape: &mut [C],
+    input: &[u8],
+    mut output: impl FnMut(u8),
+) -> Result<(), RunError> {
+    check_brackets(code)?;
+    let code = code.as_bytes();
+    let mut pointer = 0;
+    let mut input = input.iter().copied();
+    let mut ip = 0;
+    let mut steps = 0;
+    while ip < code.len() {
+        // Steps count characters, as in `run`, so continuation bytes are free
+        if !is_continuation(code[ip]) {
+            if steps >= MAX_STEPS {
+                return Err(RunError::MaxStepsExceeded);
+            }
+            steps += 1;
+        }
+        match code[ip] {
+            b'>' => {
+                if pointer + 1 >= tape.len() {
+                    return Err(RunError::PointerOverflow);
+                }
+                pointer += 1;
+            }
+            b'<' => {
+                if pointer == 0 {
+                    return Err(RunError::PointerUnderflow);
+                }
+                pointer -= 1;
+            }
+            b'+' => tape[pointer] = tape[pointer].increment(),
+            b'-' => tape[pointer] = tape[pointer].decrement(),
+            b'.' => output(tape[pointer].to_byte()),
+            b',' => tape[pointer] = C::from_byte(input.next().unwrap_or(0)),
+            b'[' if tape[pointer] == C::ZERO => ip = matching_close(code, ip),
+            b']' if tape[pointer] != C::ZERO => ip = matching_open(code, ip),
+            _ => {}
+        }
+        ip += 1;
+    }
+    Ok(())
+}
+
+/// Whether a byte of UTF-8 continues a character rather than starting one
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xc0 == 0x80
+}
+
+/// Check that the brackets of a program match, reporting character positions
+fn check_brackets(code: &str) -> Result<(), RunError> {
+    // The positions of the unmatched `[`s are not stored, so the first one is found by
+    // scanning back from the end once the depth is known
+    let mut depth = 0usize;
+    for (i, ch) in code.chars().enumerate() {
+        match ch {
+            '[' => depth += 1,
+            ']' if depth == 0 => return Err(RunError::UnmatchedCloseBracket(i)),
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth == 0 {
+        return Ok(());
+    }
+    // The innermost unmatched `[` is the last `[` with no `]` after it at its level
+    let mut level = 0usize;
+    let count = code.chars().count();
+    for (i, ch) in code.chars().rev().enumerate() {
+        match ch {
+            ']' => level += 1,
+            '[' if level == 0 => return Err(RunError::UnmatchedOpenBracket(count - 1 - i)),
+            '[' => level -= 1,
+            _ => {}
+        }
+    }
+    unreachable!("a `[` is unmatched")
+}
+
+/// The position of the `]` matching the `[` at `open`, in bytes
+fn matching_close(code: &[u8], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, &byte) in code.iter().enumerate().skip(open) {
+        match byte {
+            b'[' => depth += 1,
+            b']' if depth == 1 => return i,
+            b']' => depth -= 1,
+            _ => {}
+        }
+    }
+    unreachable!("brackets are checked before running")
+}
+
+/// The position of the `[` matching the `]` at `close`, in bytes
+fn matching_open(code: &[u8], close: usize) -> usize {
+    let mut depth = 0;
+    for i in (0..=close).rev() {
+        match code[i] {
+            b']' => depth += 1,
+            b'[' if depth == 1 => return i,
+            b'[' => depth -= 1,
+            _ => {}
+        }
+    }
+    unreachable!("brackets are checked before running")
+}
+
+/// Run a program over `input` and return its output.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::interpret::run;
+///
+/// assert_eq!(run(",[.,]", b"echo").unwrap(), b"echo");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn run(code: &str, input: &[u8]) -> Result<Vec<u8>, RunError> {
+    let mut outputs = Outputs::new(code, input)?;
+    outputs.max_steps = Some(MAX_STEPS);
+    outputs.collect()
+}
+
+/// Error returned when a program run over streams by [`stream`] fails
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum StreamError {
+    /// The program failed
+    Run(RunError),
+    /// Reading the input or writing the output failed
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Run(e) => write!(f, "{}", e),
+            StreamError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Run(e) => Some(e),
+            StreamError::Io(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<RunError> for StreamError {
+    fn from(error: RunError) -> Self {
+        StreamError::Run(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for StreamError {
+    fn from(error: std::io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+/// Run a program reading `,` from `input` and writing `.` to `output`, for programs
+/// processing files, sockets or stdin.
+///
+/// Input is read one byte at a time as `,` needs it, and `,` stores 0 at the end of the
+/// input. Output is written as it is produced and flushed at every newline, before
+/// every read and at the end, so interactive programs show their prompts in time. Wrap
+/// unbuffered readers and writers like files in [`std::io::BufReader`] and
+/// [`std::io::BufWriter`]. Like [`Outputs`], there is no step limit.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::interpret::stream;
+///
+/// let mut output = Vec::new();
+/// stream(",[.,]", &b"echo"[..], &mut output).unwrap();
+/// assert_eq!(output, b"echo");
+/// ```
+#[cfg(feature = "std")]
+pub fn stream(
+    code: &str,
+    mut input: impl std::io::Read,
+    mut output: impl std::io::Write,
+) -> Result<(), StreamError> {
+    let mut program = Outputs::new(code, &[])?;
+    while !program.is_finished() {
+        let read = || -> Result<u8, StreamError> {
+            output.flush()?;
+            let mut byte = [0];
+            match input.read_exact(&mut byte) {
+                Ok(()) => Ok(byte[0]),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(0),
+                Err(e) => Err(e.into()),
+            }
+        };
+        if let Some(byte) = program.step_with(read)? {
+            output.write_all(&[byte])?;
+            if byte == b'\n' {
+                output.flush()?;
+            }
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// A program being executed lazily, yielding each output byte as soon as it is produced.
+///
+/// Unlike [`run`], there is no step limit, so programs producing unbounded output can be
+/// consumed incrementally. A program that runs forever without producing output blocks
+/// [`Iterator::next`] forever. After an error is yielded, the iterator is exhausted.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::interpret::Outputs;
+///
+/// // Prints the bytes 1, 2, 3, ... forever, wrapping around after 255
+/// let outputs = Outputs::new("+[>+.<]", b"").unwrap();
+/// let first: Vec<u8> = outputs.take(3).map(Result::unwrap).collect();
+/// assert_eq!(first, [1, 2, 3]);
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct Outputs {
+    chars: Vec<char>,
+    jumps: Vec<usize>,
+    tape: Vec<u8>,
+    pointer: usize,
+    input: Vec<u8>,
+    input_pos: usize,
+    ip: usize,
+    steps: usize,
+    max_steps: Option<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl Outputs {
+    /// Prepare a program for execution over `input`, checking its brackets
+    pub fn new(code: &str, input: &[u8]) -> Result<Self, RunError> {
+        let chars: Vec<char> = code.chars().collect();
+        let jumps = jump_table(&chars)?;
+        Ok(Self {
+            chars,
+            jumps,
+            tape: vec![0; TAPE_SIZE],
+            pointer: 0,
+            input: input.to_vec(),
+            input_pos: 0,
+            ip: 0,
+            steps: 0,
+            max_steps: None,
+        })
+    }
+
+    /// Execute until the next output byte or the end of the program
+    fn step_to_output(&mut self) -> Result<Option<u8>, RunError> {
+        while !self.is_finished() {
+            if self.max_steps.is_some_and(|max| self.steps >= max) {
+                return Err(RunError::MaxStepsExceeded);
+            }
+            if let Some(output) = self.step()? {
+                return Ok(Some(output));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Execute the instruction at the instruction pointer, returning its output
+    pub(crate) fn step(&mut self) -> Result<Option<u8>, RunError> {
+        let byte = self.input.get(self.input_pos).copied().unwrap_or(0);
+        self.step_with(|| Ok(byte))
+    }
+
+    /// Execute the instruction at the instruction pointer like [`Outputs::step`], with
+    /// `read` providing the byte of `,`
+    fn step_with<E: From<RunError>>(
+        &mut self,
+        read: impl FnOnce() -> Result<u8, E>,
+    ) -> Result<Option<u8>, E> {
+        self.steps += 1;
+        let mut output = None;
+        match self.chars[self.ip] {
+            '>' => {
+                if self.pointer >= TAPE_SIZE - 1 {
+                    return Err(RunError::PointerOverflow.into());
+                }
+                self.pointer += 1;
+            }
+            '<' => {
+                if self.pointer == 0 {
+                    return Err(RunError::PointerUnderflow.into());
+                }
+                self.pointer -= 1;
+            }
+            '+' => self.tape[self.pointer] = self.tape[self.pointer].wrapping_add(1),
+            '-' => self.tape[self.pointer] = self.tape[self.pointer].wrapping_sub(1),
+            '.' => output = Some(self.tape[self.pointer]),
+            ',' => {
+                self.tape[self.pointer] = read()?;
+                self.input_pos += 1;
+            }
+            '[' if self.tape[self.pointer] == 0 => self.ip = self.jumps[self.ip],
+            ']' if self.tape[self.pointer] != 0 => self.ip = self.jumps[self.ip],
+            _ => {}
+        }
+        self.ip += 1;
+        Ok(output)
+    }
+
+    /// Whether the instruction pointer is past the end of the program
+    pub(crate) fn is_finished(&self) -> bool {
+        self.ip >= self.chars.len()
+    }
+
+    /// The program as characters
+    pub(crate) fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// The position of the next instruction
+    pub(crate) fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// The tape
+    pub(crate) fn tape(&self) -> &[u8] {
+        &self.tape
+    }
+
+    /// The position of the pointer on the tape
+    pub(crate) fn pointer(&self) -> usize {
+        self.pointer
+    }
+
+    /// The number of instructions executed so far
+    pub(crate) fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// The number of input bytes read so far
+    pub(crate) fn input_pos(&self) -> usize {
+        self.input_pos
+    }
+
+    /// Continue from the given state, with `tape` as the start of the tape and the
+    /// remaining cells zero
+    pub(crate) fn set_state(
+        &mut self,
+        tape: &[u8],
+        pointer: usize,
+        ip: usize,
+        steps: usize,
+        input_pos: usize,
+    ) {
+        self.tape[..tape.len()].copy_from_slice(tape);
+        self.tape[tape.len()..].fill(0);
+        self.pointer = pointer;
+        self.ip = ip;
+        self.steps = steps;
+        self.input_pos = input_pos;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Iterator for Outputs {
+    type Item = Result<u8, RunError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step_to_output() {
+            Ok(output) => output.map(Ok),
+            Err(e) => {
+                // Stop for good, so the error is reported exactly once
+                self.ip = self.chars.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// For every bracket, the position of its partner (other entries are unused)
+#[cfg(feature = "alloc")]
+fn jump_table(chars: &[char]) -> Result<Vec<usize>, RunError> {
+    let mut jumps = vec![0; chars.len()];
+    let mut stack = Vec::new();
+    for (i, ch) in chars.iter().enumerate() {
+        match ch {
+            '[' => stack.push(i),
+            ']' => {
+                let open = stack.pop().ok_or(RunError::UnmatchedCloseBracket(i))?;
+                jumps[open] = i;
+                jumps[i] = open;
+            }
+            _ => {}
+        }
+    }
+    match stack.pop() {
+        Some(open) => Err(RunError::UnmatchedOpenBracket(open)),
+        None => Ok(jumps),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_output() {
+        assert_eq!(run("+++++[>+++++++++++++<-]>.+.", b"").unwrap(), b"AB");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_run_with_tape() {
+        let run_on = |code, tape: &mut [u8], input| {
+            let mut output = Vec::new();
+            run_with_tape(code, tape, input, |byte| output.push(byte)).map(|()| output)
+        };
+        let code = "++++++++[>++++++++<-]>+.é,[.,]";
+        let mut tape = [0; 2];
+        assert_eq!(run_on(code, &mut tape, b"yz"), run(code, b"yz"));
+        assert_eq!(tape, [0, 0]);
+        assert_eq!(run_on(">>", &mut tape, b""), Err(RunError::PointerOverflow));
+        // Errors and step counts are the same as those of `run`, in characters
+        for code in ["é+[", "[[]é]]", "[é[[]", "+[é]"] {
+            assert_eq!(run_on(code, &mut [0; 8], b""), run(code, b""));
+        }
+        // The tape is used as it is
+        assert_eq!(run_on("[.-]", &mut [2], b""), Ok(vec![2, 1]));
+    }
+
+    #[test]
+    fn test_wide_cells() {
+        let mut output = None;
+        let mut tape = [0u32; 1];
+        run_with_tape("-.,+", &mut tape, b"\xff", |byte| output = Some(byte)).unwrap();
+        // `.` outputs the lowest byte, `,` stores the byte without sign extension
+        assert_eq!(output, Some(0xff));
+        assert_eq!(tape, [0x100]);
+        let mut tape = [0u16; 1];
+        run_with_tape("-", &mut tape, b"", |_| {}).unwrap();
+        assert_eq!(tape, [u16::MAX]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_input_exhausted() {
+        assert_eq!(run(",.,.,.", b"x").unwrap(), b"x\0\0");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_errors() {
+        assert_eq!(run("+[", b""), Err(RunError::UnmatchedOpenBracket(1)));
+        assert_eq!(run("]", b""), Err(RunError::UnmatchedCloseBracket(0)));
+        assert_eq!(run("<", b""), Err(RunError::PointerUnderflow));
+        assert_eq!(run("+[]", b""), Err(RunError::MaxStepsExceeded));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_outputs_are_lazy() {
+        let mut outputs = Outputs::new("+[>+.<]", b"").unwrap();
+        assert_eq!(outputs.nth(299), Some(Ok(44)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_outputs_stop_after_error() {
+        let mut outputs = Outputs::new("+.<", b"").unwrap();
+        assert_eq!(outputs.next(), Some(Ok(1)));
+        assert_eq!(outputs.next(), Some(Err(RunError::PointerUnderflow)));
+        assert_eq!(outputs.next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    mod stream_tests {
+        use super::*;
+
+        #[test]
+        fn test_stream() {
+            let mut output = Vec::new();
+            stream(",[+.,]", &b"HAL"[..], &mut output).unwrap();
+            assert_eq!(output, b"IBM");
+            let error = stream("+.<", &b""[..], &mut output).unwrap_err();
+            assert!(matches!(
+                error,
+                StreamError::Run(RunError::PointerUnderflow)
+            ));
+            // The output up to the error was written
+            assert_eq!(output, b"IBM\x01");
+        }
+
+        #[test]
+        fn test_stream_io_error() {
+            struct Broken;
+
+            impl std::io::Write for Broken {
+                fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                    Err(std::io::ErrorKind::BrokenPipe.into())
+                }
+
+                fn flush(&mut self) -> std::io::Result<()> {
+                    Ok(())
+                }
+            }
+
+            let error = stream("+.", &b""[..], Broken).unwrap_err();
+            assert_eq!(error.to_string(), "I/O error: broken pipe");
+        }
+    }
+}
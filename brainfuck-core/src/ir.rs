@@ -0,0 +1,343 @@
+//! Intermediate representation used by the code generation backends.
+//!
+//! The IR is a tree: loops own their bodies, and runs of `+`/`-` and `<`/`>`
+//! are folded into single operations while parsing. The optimizer (see
+//! `optimize`) further replaces common loop idioms with dedicated operations.
+
+use crate::ast::{Ast, Command, Node};
+use crate::interpret::RunError;
+
+/// A single IR operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// Add a value to the current cell (wrapping)
+    Add(u8),
+    /// Move the pointer by a signed offset
+    Move(isize),
+    /// Output the current cell
+    Output,
+    /// Read one byte of input into the current cell
+    Input,
+    /// Repeat the body while the current cell is nonzero
+    Loop(Vec<Op>),
+    /// Set the current cell to zero (`[-]`)
+    Clear,
+    /// For each `(offset, factor)`, add the current cell times `factor` to the cell at
+    /// `offset`, then clear the current cell (`[->+++<]` and similar)
+    MulLoop(Vec<(isize, u8)>),
+}
+
+/// Parse Brainfuck source into IR, folding runs of arithmetic and pointer moves
+pub fn parse(code: &str) -> Result<Vec<Op>, RunError> {
+    let mut stack: Vec<(usize, Vec<Op>)> = Vec::new();
+    let mut current = Vec::new();
+
+    for (i, ch) in code.chars().enumerate() {
+        match ch {
+            '+' => push_add(&mut current, 1),
+            '-' => push_add(&mut current, u8::MAX),
+            '>' => push_move(&mut current, 1),
+            '<' => push_move(&mut current, -1),
+            '.' => current.push(Op::Output),
+            ',' => current.push(Op::Input),
+            '[' => {
+                stack.push((i, std::mem::take(&mut current)));
+            }
+            ']' => {
+                let (_, parent) = stack.pop().ok_or(RunError::UnmatchedCloseBracket(i))?;
+                let body = std::mem::replace(&mut current, parent);
+                current.push(Op::Loop(body));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((open_pos, _)) = stack.pop() {
+        return Err(RunError::UnmatchedOpenBracket(open_pos));
+    }
+
+    Ok(current)
+}
+
+/// Convert a syntax tree into IR with one operation per command, without folding runs
+/// like [`parse`] does. [`crate::optimize::rle`] folds them.
+pub fn from_ast(ast: &Ast) -> Vec<Op> {
+    from_nodes(&ast.nodes)
+}
+
+fn from_nodes(nodes: &[Node]) -> Vec<Op> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            Node::Command { command, .. } => Some(match command {
+                Command::Right => Op::Move(1),
+                Command::Left => Op::Move(-1),
+                Command::Increment => Op::Add(1),
+                Command::Decrement => Op::Add(u8::MAX),
+                Command::Output => Op::Output,
+                Command::Input => Op::Input,
+            }),
+            Node::Loop { body, .. } => Some(Op::Loop(from_nodes(body))),
+            Node::Comment { .. } => None,
+        })
+        .collect()
+}
+
+/// Append an addition, merging it into a preceding `Add` (dropping it if the run cancels out)
+fn push_add(ops: &mut Vec<Op>, delta: u8) {
+    if let Some(Op::Add(n)) = ops.last_mut() {
+        *n = n.wrapping_add(delta);
+        if *n == 0 {
+            ops.pop();
+        }
+    } else {
+        ops.push(Op::Add(delta));
+    }
+}
+
+/// Append a pointer move, merging it into a preceding `Move` (dropping it if the run cancels out)
+fn push_move(ops: &mut Vec<Op>, delta: isize) {
+    if let Some(Op::Move(n)) = ops.last_mut() {
+        *n += delta;
+        if *n == 0 {
+            ops.pop();
+        }
+    } else {
+        ops.push(Op::Move(delta));
+    }
+}
+
+/// Emit the shortest equivalent Brainfuck source for the operations
+pub fn to_source(ops: &[Op]) -> String {
+    let mut out = String::new();
+    emit_source(&mut out, ops);
+    out
+}
+
+fn emit_source(out: &mut String, ops: &[Op]) {
+    for op in ops {
+        match op {
+            Op::Add(n) if *n > 128 => out.extend(std::iter::repeat_n('-', n.wrapping_neg().into())),
+            Op::Add(n) => out.extend(std::iter::repeat_n('+', (*n).into())),
+            Op::Move(n) if *n < 0 => out.extend(std::iter::repeat_n('<', n.unsigned_abs())),
+            Op::Move(n) => out.extend(std::iter::repeat_n('>', n.unsigned_abs())),
+            Op::Output => out.push('.'),
+            Op::Input => out.push(','),
+            Op::Loop(body) => {
+                out.push('[');
+                emit_source(out, body);
+                out.push(']');
+            }
+            Op::Clear => out.push_str("[-]"),
+            Op::MulLoop(terms) => {
+                let mut body = vec![Op::Add(u8::MAX)];
+                let mut position = 0;
+                for &(offset, factor) in terms {
+                    body.push(Op::Move(offset - position));
+                    body.push(Op::Add(factor));
+                    position = offset;
+                }
+                body.push(Op::Move(-position));
+                body.retain(|op| *op != Op::Move(0));
+                emit_source(out, &[Op::Loop(body)]);
+            }
+        }
+    }
+}
+
+/// Render the operations as a human readable listing, one operation per line with loop
+/// bodies indented
+pub fn dump(ops: &[Op]) -> String {
+    let mut out = String::new();
+    dump_ops(&mut out, ops, 0);
+    out
+}
+
+fn dump_ops(out: &mut String, ops: &[Op], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for op in ops {
+        match op {
+            Op::Add(n) => out.push_str(&format!("{}add {}\n", indent, *n as i8)),
+            Op::Move(n) => out.push_str(&format!("{}move {:+}\n", indent, n)),
+            Op::Output => out.push_str(&format!("{}output\n", indent)),
+            Op::Input => out.push_str(&format!("{}input\n", indent)),
+            Op::Loop(body) => {
+                out.push_str(&format!("{}loop\n", indent));
+                dump_ops(out, body, depth + 1);
+                out.push_str(&format!("{}end\n", indent));
+            }
+            Op::Clear => out.push_str(&format!("{}clear\n", indent)),
+            Op::MulLoop(terms) => {
+                let terms: Vec<String> = terms
+                    .iter()
+                    .map(|(offset, factor)| format!("[{:+}] += {}", offset, *factor as i8))
+                    .collect();
+                out.push_str(&format!("{}mul {}\n", indent, terms.join(", ")));
+            }
+        }
+    }
+}
+
+/// Whether any operation (including inside loops) reads input
+pub fn uses_input(ops: &[Op]) -> bool {
+    ops.iter().any(|op| match op {
+        Op::Input => true,
+        Op::Loop(body) => uses_input(body),
+        _ => false,
+    })
+}
+
+/// Whether any operation (including inside loops) writes output
+pub fn uses_output(ops: &[Op]) -> bool {
+    ops.iter().any(|op| match op {
+        Op::Output => true,
+        Op::Loop(body) => uses_output(body),
+        _ => false,
+    })
+}
+
+/// The lowest and highest positions of the pointer and the cells accessed through it
+/// over any run, relative to the starting cell, or `None` if they cannot be bounded.
+///
+/// The positions are known exactly outside loops, and inside loops whose bodies move
+/// the pointer back to where they started, which is all the analysis accepts: a loop
+/// drifting the pointer has no bound on the cells it reaches.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::ir::{parse, pointer_range};
+///
+/// assert_eq!(pointer_range(&parse("+++[>++<-]>.").unwrap()), Some(0..=1));
+/// assert_eq!(pointer_range(&parse("<<").unwrap()), Some(-2..=0));
+/// assert_eq!(pointer_range(&parse("+[>+]").unwrap()), None);
+/// ```
+pub fn pointer_range(ops: &[Op]) -> Option<std::ops::RangeInclusive<isize>> {
+    let mut range = (0, 0);
+    visit_range(ops, 0, &mut range)?;
+    Some(range.0..=range.1)
+}
+
+/// Widen `range` by the positions the operations reach from `start`, returning the
+/// position they end at
+fn visit_range(ops: &[Op], start: isize, range: &mut (isize, isize)) -> Option<isize> {
+    let reach = |range: &mut (isize, isize), position: isize| {
+        *range = (range.0.min(position), range.1.max(position));
+    };
+    let mut position = start;
+    for op in ops {
+        match op {
+            Op::Move(n) => {
+                position += n;
+                reach(range, position);
+            }
+            Op::Loop(body) => {
+                if visit_range(body, position, range)? != position {
+                    return None;
+                }
+            }
+            Op::MulLoop(terms) => {
+                for &(offset, _) in terms {
+                    reach(range, position + offset);
+                }
+            }
+            Op::Add(_) | Op::Output | Op::Input | Op::Clear => {}
+        }
+    }
+    Some(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folds_runs() {
+        let ops = parse("+++>>--<.").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                Op::Add(3),
+                Op::Move(2),
+                Op::Add(254),
+                Op::Move(-1),
+                Op::Output
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_ast() {
+        let code = "++ comment [>-<] ,.";
+        let ops = from_ast(&crate::ast::parse(code).unwrap());
+        assert_eq!(
+            ops[..3],
+            [
+                Op::Add(1),
+                Op::Add(1),
+                Op::Loop(vec![Op::Move(1), Op::Add(255), Op::Move(-1)])
+            ]
+        );
+        assert_eq!(crate::optimize::rle(ops), parse(code).unwrap());
+    }
+
+    #[test]
+    fn test_cancelling_runs_removed() {
+        assert_eq!(parse("+-<>").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_nested_loops() {
+        let ops = parse("+[>[-]<-]").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                Op::Add(1),
+                Op::Loop(vec![
+                    Op::Move(1),
+                    Op::Loop(vec![Op::Add(255)]),
+                    Op::Move(-1),
+                    Op::Add(255),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_source() {
+        let ops = parse("Set +++--- then ++++ [ > +++ < - ] wrap ---------- > .").unwrap();
+        assert_eq!(to_source(&ops), "++++[>+++<-]---------->.");
+        assert_eq!(to_source(&parse(&"+".repeat(250)).unwrap()), "------");
+    }
+
+    #[test]
+    fn test_to_source_of_optimized_ops() {
+        let ops = vec![Op::Clear, Op::MulLoop(vec![(1, 2), (-1, 255)])];
+        assert_eq!(to_source(&ops), "[-][->++<<->]");
+    }
+
+    #[test]
+    fn test_dump() {
+        let ops = vec![
+            Op::Add(254),
+            Op::Loop(vec![Op::Move(1), Op::Output]),
+            Op::MulLoop(vec![(2, 3)]),
+        ];
+        assert_eq!(
+            dump(&ops),
+            "add -2\nloop\n  move +1\n  output\nend\nmul [+2] += 3\n"
+        );
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        assert!(matches!(
+            parse("[["),
+            Err(RunError::UnmatchedOpenBracket(1))
+        ));
+        assert!(matches!(
+            parse("+]"),
+            Err(RunError::UnmatchedCloseBracket(1))
+        ));
+    }
+}
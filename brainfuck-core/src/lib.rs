@@ -0,0 +1,82 @@
+//! # Brainfuck Core
+//!
+//! Reusable Brainfuck tooling shared by the `brainfuck-macro` crate. Procedural macro
+//! crates cannot export ordinary functions, so everything that editors, build scripts,
+//! or other tools may want to call directly lives here.
+//!
+//! [`machine::Interpreter`] is the interpreter of the macros, with all of their
+//! extensions and limits, for running programs at runtime exactly as `brainfuck!` does.
+//!
+//! [`sandbox::Sandbox`] runs untrusted programs within limits on their steps, memory,
+//! output and running time.
+//!
+//! [`program::Program`] compiles a program once, to run it over many inputs and share it
+//! between threads.
+//!
+//! [`debug::Debugger`] executes programs one instruction at a time, stopping at
+//! breakpoints, for teaching REPLs and other interactive tools.
+//!
+//! The crate is `no_std` without the default `std` feature. The interpreter in
+//! [`interpret`] then still runs programs on a tape provided by the caller, and with the
+//! `alloc` feature also on its own tape; everything else requires `std`.
+//!
+//! [`eval::eval`] runs arbitrary bytes within strict limits and never panics, as a
+//! target for fuzzing the interpreter.
+//!
+//! [`ast::parse`] turns source into a syntax tree with nested loops and spans, for
+//! formatters, analyzers and converters.
+//!
+//! Build scripts can enable the `build` feature for [`build`], which generates Rust
+//! source from program files and evaluates programs like the macros do.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # #[cfg(feature = "std")] {
+//! use brainfuck_core::format::{format, FormatOptions};
+//!
+//! let formatted = format("++[>+<-]>.", &FormatOptions::default()).unwrap();
+//! assert_eq!(formatted, "++\n[>+<-]\n>.");
+//! # }
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod ast;
+#[cfg(feature = "build")]
+pub mod build;
+#[cfg(feature = "alloc")]
+pub mod debug;
+#[cfg(feature = "alloc")]
+pub mod eval;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "std")]
+pub mod generate;
+pub mod interpret;
+#[cfg(feature = "std")]
+pub mod ir;
+#[cfg(feature = "std")]
+pub mod machine;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod optimize;
+#[cfg(feature = "std")]
+pub mod program;
+#[cfg(feature = "std")]
+pub mod random;
+#[cfg(feature = "std")]
+pub mod reduce;
+#[cfg(feature = "std")]
+pub mod sandbox;
+#[cfg(feature = "std")]
+pub mod termination;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod transpile;
@@ -0,0 +1,1409 @@
+//! The interpreter of the macros.
+//!
+//! [`Interpreter`] runs programs with the same limits and extensions as `brainfuck!`, so
+//! its results can be reused, fuzzed or benchmarked outside of a macro expansion.
+
+use std::collections::{HashMap, VecDeque};
+
+pub use crate::interpret::{MAX_STEPS, TAPE_SIZE};
+use crate::trace::{ExecutionHook, Trace, TraceStep};
+
+/// The names of all extensions, for error messages
+pub const EXTENSION_NAMES: &[&str] = &[
+    "extended",
+    "end",
+    "brainfork",
+    "bitwise",
+    "tapes",
+    "decimal",
+];
+
+/// The enabled extensions of the instruction set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Extensions {
+    /// Extended Brainfuck Type I: `@` ends the program, `$` and `!` store the current cell
+    /// into and retrieve it from a storage byte, `{` and `}` shift the current cell left
+    /// and right, `~` inverts it, and `^`, `&` and `|` combine it with the storage byte
+    pub extended: bool,
+    /// `@` ends the program, as in Extended Brainfuck but without its other commands
+    pub end: bool,
+    /// Brainfork: `Y` forks the running thread. Threads take turns executing one
+    /// instruction each, so the output does not depend on timing.
+    pub brainfork: bool,
+    /// `&`, `|` and `^` combine the current cell with the next one, `{` and `}` shift the
+    /// current cell left and right by the next one, and `~` inverts the current cell
+    pub bitwise: bool,
+    /// `%` switches to the next of several tapes, each with its own pointer
+    pub tapes: bool,
+    /// `:` outputs the current cell as decimal digits and `;` reads a decimal number from
+    /// the input into it
+    pub decimal: bool,
+}
+
+impl Extensions {
+    /// Parse a comma-separated list of extension names
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut extensions = Self::default();
+        for name in list.split(',').map(str::trim) {
+            let enabled = match name {
+                "extended" => &mut extensions.extended,
+                "end" => &mut extensions.end,
+                "brainfork" => &mut extensions.brainfork,
+                "bitwise" => &mut extensions.bitwise,
+                "tapes" => &mut extensions.tapes,
+                "decimal" => &mut extensions.decimal,
+                _ => {
+                    return Err(format!(
+                        "unknown extension `{}`; supported extensions are {}",
+                        name,
+                        EXTENSION_NAMES.join(", ")
+                    ))
+                }
+            };
+            if *enabled {
+                return Err(format!("duplicate extension `{}`", name));
+            }
+            *enabled = true;
+        }
+        if extensions.extended && extensions.bitwise {
+            return Err("extensions `extended` and `bitwise` cannot be combined, since both define `&`, `|`, `^`, `~`, `{` and `}`".to_string());
+        }
+        Ok(extensions)
+    }
+}
+
+/// The maximum number of nested pbrain procedure calls
+pub const MAX_CALL_DEPTH: usize = 1_000;
+
+/// The maximum number of concurrently running Brainfork threads
+pub const MAX_THREADS: usize = 64;
+
+/// The maximum number of tapes of the `tapes` extension
+pub const MAX_TAPES: usize = 16;
+
+/// Error types for Brainfuck execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrainfuckError {
+    /// Unmatched opening bracket
+    UnmatchedOpenBracket(usize),
+    /// Unmatched closing bracket
+    UnmatchedCloseBracket(usize),
+    /// Pointer moved out of bounds (left)
+    PointerUnderflow,
+    /// Pointer moved out of bounds (right) of a tape of the given size
+    PointerOverflow(usize),
+    /// Input operation not supported at compile time
+    InputNotSupported,
+    /// Execution exceeded the given maximum number of steps
+    MaxStepsExceeded(usize),
+    /// Unmatched opening parenthesis of a pbrain procedure
+    UnmatchedOpenParen(usize),
+    /// Unmatched closing parenthesis of a pbrain procedure
+    UnmatchedCloseParen(usize),
+    /// A pbrain procedure was called before being defined: the procedure number and the
+    /// position of the call
+    UndefinedProcedure(u32, usize),
+    /// pbrain procedure calls were nested too deeply
+    CallDepthExceeded,
+    /// Brainfork forked more threads than can run at once
+    TooManyThreads,
+}
+
+impl std::fmt::Display for BrainfuckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrainfuckError::UnmatchedOpenBracket(pos) => {
+                write!(f, "Unmatched '[' at position {}", pos)
+            }
+            BrainfuckError::UnmatchedCloseBracket(pos) => {
+                write!(f, "Unmatched ']' at position {}", pos)
+            }
+            BrainfuckError::PointerUnderflow => {
+                write!(f, "Pointer moved below zero")
+            }
+            BrainfuckError::PointerOverflow(size) => {
+                write!(f, "Pointer moved beyond tape size ({})", size)
+            }
+            BrainfuckError::InputNotSupported => {
+                write!(f, "Input operation ',' is not supported at compile time")
+            }
+            BrainfuckError::MaxStepsExceeded(max) => {
+                write!(f, "Execution exceeded maximum steps ({})", max)
+            }
+            BrainfuckError::UnmatchedOpenParen(pos) => {
+                write!(f, "Unmatched '(' at position {}", pos)
+            }
+            BrainfuckError::UnmatchedCloseParen(pos) => {
+                write!(f, "Unmatched ')' at position {}", pos)
+            }
+            BrainfuckError::UndefinedProcedure(procedure, pos) => {
+                write!(
+                    f,
+                    "Call of undefined procedure {} at position {}",
+                    procedure, pos
+                )
+            }
+            BrainfuckError::TooManyThreads => {
+                write!(f, "More than {} threads running at once", MAX_THREADS)
+            }
+            BrainfuckError::CallDepthExceeded => {
+                write!(
+                    f,
+                    "Procedure calls nested deeper than {} levels",
+                    MAX_CALL_DEPTH
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BrainfuckError {}
+
+/// A failed execution of [`Interpreter::execute`]: what went wrong, where, and how far
+/// the program got.
+///
+/// It displays as its [`kind`](Error::kind), the message of the macros' compile errors.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::{BrainfuckError, Interpreter};
+///
+/// let error = Interpreter::new().execute("++.\n>.<<").unwrap_err();
+/// assert_eq!(error.kind, BrainfuckError::PointerUnderflow);
+/// assert_eq!((error.position, error.line, error.column), (7, 2, 4));
+/// assert_eq!((error.steps, error.output_len), (8, 2));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// What went wrong
+    pub kind: BrainfuckError,
+    /// Position in the code (in characters) of the instruction that failed, or of the
+    /// unmatched bracket or parenthesis
+    pub position: usize,
+    /// Line of the position, starting at 1
+    pub line: usize,
+    /// Column of the position in characters, starting at 1
+    pub column: usize,
+    /// The number of steps taken, including the one that failed
+    pub steps: usize,
+    /// Length in bytes of the output produced before the error, kept in
+    /// [`Interpreter::output`]
+    pub output_len: usize,
+}
+
+impl Error {
+    fn new(
+        code: &str,
+        kind: BrainfuckError,
+        position: usize,
+        steps: usize,
+        output_len: usize,
+    ) -> Self {
+        let before: Vec<char> = code.chars().take(position).collect();
+        let line_start = before
+            .iter()
+            .rposition(|&ch| ch == '\n')
+            .map_or(0, |i| i + 1);
+        Self {
+            kind,
+            position,
+            line: before[..line_start]
+                .iter()
+                .filter(|&&ch| ch == '\n')
+                .count()
+                + 1,
+            column: position - line_start + 1,
+            steps,
+            output_len,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A Brainfork thread waiting for its turn
+#[derive(Debug)]
+struct Thread {
+    tape: Vec<u32>,
+    pointer: usize,
+    /// The other tapes with their pointers
+    tapes: VecDeque<(Vec<u32>, usize)>,
+    /// The position of the next instruction
+    ip: usize,
+    /// The positions of the active pbrain calls
+    calls: Vec<usize>,
+}
+
+/// The state of a running program, apart from the machine
+#[derive(Debug)]
+struct Execution {
+    chars: Vec<char>,
+    jump_table: Vec<Option<usize>>,
+    paren_table: Vec<Option<usize>>,
+    /// The position of the `(` of every defined procedure
+    procedures: HashMap<u32, usize>,
+    /// The positions of the active pbrain calls
+    calls: Vec<usize>,
+    /// Brainfork threads other than the running one, in the order they run next. The
+    /// threads take turns executing one instruction each.
+    threads: VecDeque<Thread>,
+    /// The position of the next instruction
+    ip: usize,
+    steps: usize,
+    /// Whether every thread finished or `@` ended the program
+    ended: bool,
+}
+
+/// The interpreter behind the macros, with every extension they support.
+///
+/// Unlike [`crate::interpret::run`], `,` is an error unless the interpreter was created
+/// with an input, as it is for the macros, and the output is a string with every cell
+/// value as the character of the same code point. [`Interpreter::builder`] configures
+/// the tape, cells and limits; [`Interpreter::new`] uses those of the macros.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::{Extensions, Interpreter};
+///
+/// let mut interpreter = Interpreter::new();
+/// interpreter.enable_extensions(Extensions {
+///     decimal: true,
+///     ..Extensions::default()
+/// });
+/// assert_eq!(interpreter.execute("+++++[>+++++++++++++<-]>:").unwrap(), "65");
+/// assert_eq!(interpreter.steps(), 93);
+/// ```
+#[derive(Debug)]
+pub struct Interpreter {
+    tape: Vec<u32>,
+    pointer: usize,
+    tape_size: usize,
+    /// The bits of a cell set, for wrapping
+    mask: u32,
+    max_steps: usize,
+    output: String,
+    input: Option<Vec<u8>>,
+    input_pos: usize,
+    trace: Option<Trace>,
+    /// Whether the pbrain commands `(`, `)` and `:` are executed
+    procedures: bool,
+    extensions: Extensions,
+    /// The storage cell of Extended Brainfuck Type I
+    storage: u32,
+    /// The tapes other than the current one with their pointers, in the order `%`
+    /// switches to them
+    tapes: VecDeque<(Vec<u32>, usize)>,
+    /// The number of steps taken by the last execution
+    steps: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Create a new Brainfuck interpreter with the tape, cells and limits of the macros
+    pub fn new() -> Self {
+        Builder::default().create()
+    }
+
+    /// Create a new Brainfuck interpreter that reads `,` from the given input.
+    ///
+    /// Once the input is exhausted, `,` stores 0 in the current cell.
+    pub fn with_input(input: &[u8]) -> Self {
+        Builder::default().input(input).create()
+    }
+
+    /// Configure an interpreter, starting from the settings of [`Interpreter::new`]
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Record the first `limit` executed instructions into a trace
+    pub fn enable_trace(&mut self, limit: usize) {
+        self.trace = Some(Trace::new(limit));
+    }
+
+    /// Execute the pbrain commands: `(` and `)` define a procedure numbered by the current
+    /// cell, and `:` calls the procedure numbered by the current cell
+    pub fn enable_procedures(&mut self) {
+        self.procedures = true;
+    }
+
+    /// Execute the commands of the given extensions
+    pub fn enable_extensions(&mut self, extensions: Extensions) {
+        self.extensions = extensions;
+    }
+
+    /// Use `count` tapes, switched between with `%`
+    pub fn enable_tapes(&mut self, count: usize) {
+        self.tapes = (1..count).map(|_| (vec![0; self.tape_size], 0)).collect();
+    }
+
+    /// Make `next` the running thread, returning the thread that was running
+    fn switch_thread(&mut self, next: Thread, ip: &mut usize, calls: &mut Vec<usize>) -> Thread {
+        Thread {
+            tape: std::mem::replace(&mut self.tape, next.tape),
+            pointer: std::mem::replace(&mut self.pointer, next.pointer),
+            tapes: std::mem::replace(&mut self.tapes, next.tapes),
+            ip: std::mem::replace(ip, next.ip),
+            calls: std::mem::replace(calls, next.calls),
+        }
+    }
+
+    /// The cell right of the current one, the second operand of bitwise operations
+    fn next_cell(&self) -> Result<u32, BrainfuckError> {
+        self.tape
+            .get(self.pointer + 1)
+            .copied()
+            .ok_or(BrainfuckError::PointerOverflow(self.tape_size))
+    }
+
+    /// Read a decimal number for `;`, skipping leading whitespace. Numbers wrap around like
+    /// cells do, and input without digits reads as 0.
+    fn read_decimal(&mut self) -> Result<u32, BrainfuckError> {
+        let Some(input) = &self.input else {
+            return Err(BrainfuckError::InputNotSupported);
+        };
+        while input
+            .get(self.input_pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.input_pos += 1;
+        }
+        let mut value = 0u32;
+        while let Some(digit) = input.get(self.input_pos).filter(|b| b.is_ascii_digit()) {
+            value = value.wrapping_mul(10).wrapping_add(u32::from(digit - b'0')) & self.mask;
+            self.input_pos += 1;
+        }
+        Ok(value)
+    }
+
+    /// Find matching bracket positions for jump operations
+    pub fn find_matching_brackets(code: &str) -> Result<Vec<Option<usize>>, BrainfuckError> {
+        let mut jump_table = vec![None; code.len()];
+        let mut stack = Vec::new();
+
+        for (i, ch) in code.chars().enumerate() {
+            match ch {
+                '[' => {
+                    stack.push(i);
+                }
+                ']' => {
+                    if let Some(open_pos) = stack.pop() {
+                        jump_table[open_pos] = Some(i);
+                        jump_table[i] = Some(open_pos);
+                    } else {
+                        return Err(BrainfuckError::UnmatchedCloseBracket(i));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(open_pos) = stack.pop() {
+            return Err(BrainfuckError::UnmatchedOpenBracket(open_pos));
+        }
+
+        Ok(jump_table)
+    }
+
+    /// Find matching parenthesis positions of pbrain procedure definitions
+    fn find_matching_parens(code: &str) -> Result<Vec<Option<usize>>, BrainfuckError> {
+        let mut paren_table = vec![None; code.len()];
+        let mut stack = Vec::new();
+
+        for (i, ch) in code.chars().enumerate() {
+            match ch {
+                '(' => stack.push(i),
+                ')' => {
+                    let open_pos = stack.pop().ok_or(BrainfuckError::UnmatchedCloseParen(i))?;
+                    paren_table[open_pos] = Some(i);
+                    paren_table[i] = Some(open_pos);
+                }
+                _ => {}
+            }
+        }
+
+        match stack.pop() {
+            Some(open_pos) => Err(BrainfuckError::UnmatchedOpenParen(open_pos)),
+            None => Ok(paren_table),
+        }
+    }
+
+    /// Execute Brainfuck code and return the output
+    pub fn execute(&mut self, code: &str) -> Result<String, Error> {
+        self.execute_with_hook(code, &mut ())
+    }
+
+    /// Execute Brainfuck code and return the output, notifying `hook` of every
+    /// instruction, output and loop. The trace of [`Interpreter::enable_trace`] is still
+    /// recorded.
+    pub fn execute_with_hook(
+        &mut self,
+        code: &str,
+        hook: &mut impl ExecutionHook,
+    ) -> Result<String, Error> {
+        self.execute_until(code, hook, |_, _| false)
+            .map(|output| output.expect("the execution is never stopped"))
+    }
+
+    /// Execute Brainfuck code like [`Interpreter::execute_with_hook`], calling `stop` with
+    /// the interpreter and the steps taken before every instruction. Returns `None` if
+    /// `stop` ended the execution early.
+    pub(crate) fn execute_until(
+        &mut self,
+        code: &str,
+        hook: &mut impl ExecutionHook,
+        mut stop: impl FnMut(&Self, usize) -> bool,
+    ) -> Result<Option<String>, Error> {
+        let mut exec = self.start(code).map_err(|kind| {
+            let position = match kind {
+                BrainfuckError::UnmatchedOpenBracket(position)
+                | BrainfuckError::UnmatchedCloseBracket(position)
+                | BrainfuckError::UnmatchedOpenParen(position)
+                | BrainfuckError::UnmatchedCloseParen(position) => position,
+                _ => 0,
+            };
+            Error::new(code, kind, position, 0, self.output.len())
+        })?;
+        loop {
+            if stop(self, exec.steps) {
+                self.steps = exec.steps;
+                return Ok(None);
+            }
+            match self.step(&mut exec, hook) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(kind) => {
+                    return Err(Error::new(
+                        code,
+                        kind,
+                        exec.ip,
+                        exec.steps,
+                        self.output.len(),
+                    ))
+                }
+            }
+        }
+        self.steps = exec.steps;
+        Ok(Some(self.output.clone()))
+    }
+
+    /// Run a program lazily over `input` without a step limit, yielding every output
+    /// byte as soon as it is produced, so programs with unbounded output can be
+    /// consumed incrementally. See [`Interpreter::into_outputs`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use brainfuck_core::machine::Interpreter;
+    ///
+    /// // Prints the bytes 1, 2, 3, ... forever
+    /// let first: Vec<u8> = Interpreter::outputs("+[>+.<]", b"")
+    ///     .take(3)
+    ///     .map(Result::unwrap)
+    ///     .collect();
+    /// assert_eq!(first, [1, 2, 3]);
+    /// ```
+    pub fn outputs(code: &str, input: &[u8]) -> Outputs {
+        Builder::default()
+            .max_steps(usize::MAX)
+            .input(input)
+            .create()
+            .into_outputs(code)
+    }
+
+    /// Run a program lazily with the settings of this interpreter, step limit included.
+    ///
+    /// `.` yields the value of the cell, truncated to 8 bits for wider cells, and `:` of
+    /// the `decimal` extension its ASCII digits. The output is not kept. After an error,
+    /// the iterator is exhausted.
+    pub fn into_outputs(self, code: &str) -> Outputs {
+        let exec = self.start(code);
+        Outputs {
+            interpreter: self,
+            exec: Some(exec),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Prepare the execution of a program, checking its brackets
+    fn start(&self, code: &str) -> Result<Execution, BrainfuckError> {
+        let jump_table = Self::find_matching_brackets(code)?;
+        let paren_table = if self.procedures {
+            Self::find_matching_parens(code)?
+        } else {
+            Vec::new()
+        };
+        Ok(Execution {
+            chars: code.chars().collect(),
+            jump_table,
+            paren_table,
+            procedures: HashMap::new(),
+            calls: Vec::new(),
+            threads: VecDeque::new(),
+            ip: 0,
+            steps: 0,
+            ended: false,
+        })
+    }
+
+    /// Execute one instruction, returning whether the program was still running
+    fn step(
+        &mut self,
+        exec: &mut Execution,
+        hook: &mut impl ExecutionHook,
+    ) -> Result<bool, BrainfuckError> {
+        if exec.ended {
+            return Ok(false);
+        }
+        while exec.ip >= exec.chars.len() {
+            // The running thread has finished
+            let Some(next) = exec.threads.pop_front() else {
+                exec.ended = true;
+                return Ok(false);
+            };
+            self.switch_thread(next, &mut exec.ip, &mut exec.calls);
+        }
+        if exec.steps >= self.max_steps {
+            return Err(BrainfuckError::MaxStepsExceeded(self.max_steps));
+        }
+        exec.steps += 1;
+
+        let position = exec.ip;
+        match exec.chars[exec.ip] {
+            '>' => {
+                if self.pointer >= self.tape_size - 1 {
+                    return Err(BrainfuckError::PointerOverflow(self.tape_size));
+                }
+                self.pointer += 1;
+            }
+            '<' => {
+                if self.pointer == 0 {
+                    return Err(BrainfuckError::PointerUnderflow);
+                }
+                self.pointer -= 1;
+            }
+            '+' => {
+                self.tape[self.pointer] = self.tape[self.pointer].wrapping_add(1) & self.mask;
+            }
+            '-' => {
+                self.tape[self.pointer] = self.tape[self.pointer].wrapping_sub(1) & self.mask;
+            }
+            '.' => {
+                let cell = self.tape[self.pointer];
+                let start = self.output.len();
+                self.output
+                    .push(char::from_u32(cell).unwrap_or(char::REPLACEMENT_CHARACTER));
+                hook.on_output(&self.output[start..]);
+            }
+            ',' => {
+                let Some(input) = &self.input else {
+                    return Err(BrainfuckError::InputNotSupported);
+                };
+                self.tape[self.pointer] =
+                    u32::from(input.get(self.input_pos).copied().unwrap_or(0));
+                self.input_pos += 1;
+            }
+            '[' => {
+                if self.tape[self.pointer] == 0 {
+                    if let Some(matching) = exec.jump_table[exec.ip] {
+                        exec.ip = matching;
+                    }
+                } else {
+                    hook.on_loop_enter(exec.ip);
+                }
+            }
+            ']' => {
+                if let Some(matching) = exec.jump_table[exec.ip] {
+                    if self.tape[self.pointer] != 0 {
+                        exec.ip = matching;
+                    } else {
+                        hook.on_loop_exit(matching);
+                    }
+                }
+            }
+            '(' if self.procedures => {
+                // Define the procedure and skip its body
+                exec.procedures.insert(self.tape[self.pointer], exec.ip);
+                if let Some(matching) = exec.paren_table[exec.ip] {
+                    exec.ip = matching;
+                }
+            }
+            ')' if self.procedures => {
+                if let Some(call) = exec.calls.pop() {
+                    exec.ip = call;
+                }
+            }
+            ':' if self.procedures => {
+                let procedure = self.tape[self.pointer];
+                let Some(&start) = exec.procedures.get(&procedure) else {
+                    return Err(BrainfuckError::UndefinedProcedure(procedure, exec.ip));
+                };
+                if exec.calls.len() >= MAX_CALL_DEPTH {
+                    return Err(BrainfuckError::CallDepthExceeded);
+                }
+                exec.calls.push(exec.ip);
+                exec.ip = start;
+            }
+            ':' if self.extensions.decimal => {
+                let number = self.tape[self.pointer].to_string();
+                self.output.push_str(&number);
+                hook.on_output(&number);
+            }
+            ';' if self.extensions.decimal => {
+                self.tape[self.pointer] = self.read_decimal()?;
+            }
+            '@' if self.extensions.extended || self.extensions.end => {
+                exec.ended = true;
+                return Ok(true);
+            }
+            '$' if self.extensions.extended => self.storage = self.tape[self.pointer],
+            '!' if self.extensions.extended => self.tape[self.pointer] = self.storage,
+            '{' if self.extensions.extended => {
+                self.tape[self.pointer] = (self.tape[self.pointer] << 1) & self.mask
+            }
+            '}' if self.extensions.extended => self.tape[self.pointer] >>= 1,
+            '~' if self.extensions.extended => {
+                self.tape[self.pointer] = !self.tape[self.pointer] & self.mask
+            }
+            '^' if self.extensions.extended => self.tape[self.pointer] ^= self.storage,
+            '&' if self.extensions.extended => self.tape[self.pointer] &= self.storage,
+            '|' if self.extensions.extended => self.tape[self.pointer] |= self.storage,
+            '&' if self.extensions.bitwise => self.tape[self.pointer] &= self.next_cell()?,
+            '|' if self.extensions.bitwise => self.tape[self.pointer] |= self.next_cell()?,
+            '^' if self.extensions.bitwise => self.tape[self.pointer] ^= self.next_cell()?,
+            '{' if self.extensions.bitwise => {
+                let shift = self.next_cell()?;
+                self.tape[self.pointer] =
+                    self.tape[self.pointer].checked_shl(shift).unwrap_or(0) & self.mask;
+            }
+            '}' if self.extensions.bitwise => {
+                let shift = self.next_cell()?;
+                self.tape[self.pointer] = self.tape[self.pointer].checked_shr(shift).unwrap_or(0);
+            }
+            '~' if self.extensions.bitwise => {
+                self.tape[self.pointer] = !self.tape[self.pointer] & self.mask
+            }
+            '%' if self.extensions.tapes => {
+                // Switch to the next tape, cycling back to the first after the last
+                if let Some((tape, pointer)) = self.tapes.pop_front() {
+                    let tape = std::mem::replace(&mut self.tape, tape);
+                    let pointer = std::mem::replace(&mut self.pointer, pointer);
+                    self.tapes.push_back((tape, pointer));
+                }
+            }
+            'Y' if self.extensions.brainfork => {
+                // The parent continues with the current cell cleared, the child with
+                // a copy of the tape and the pointer moved to the next cell, set to 1
+                if exec.threads.len() + 1 >= MAX_THREADS {
+                    return Err(BrainfuckError::TooManyThreads);
+                }
+                if self.pointer >= self.tape_size - 1 {
+                    return Err(BrainfuckError::PointerOverflow(self.tape_size));
+                }
+                let mut tape = self.tape.clone();
+                tape[self.pointer + 1] = 1;
+                exec.threads.push_back(Thread {
+                    tape,
+                    pointer: self.pointer + 1,
+                    tapes: self.tapes.clone(),
+                    ip: exec.ip + 1,
+                    calls: exec.calls.clone(),
+                });
+                self.tape[self.pointer] = 0;
+            }
+            _ => {
+                // Ignore non-Brainfuck characters (comments)
+            }
+        }
+
+        let step = TraceStep {
+            position,
+            instruction: exec.chars[position],
+            pointer: self.pointer,
+            cell: self.tape[self.pointer],
+            output_len: self.output.len(),
+        };
+        if let Some(trace) = &mut self.trace {
+            trace.on_instruction(&step);
+        }
+        hook.on_instruction(&step);
+
+        exec.ip += 1;
+
+        if let Some(next) = exec.threads.pop_front() {
+            let current = self.switch_thread(next, &mut exec.ip, &mut exec.calls);
+            exec.threads.push_back(current);
+        }
+        Ok(true)
+    }
+
+    /// The number of steps taken by the last execution
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// The current tape
+    pub fn tape(&self) -> &[u32] {
+        &self.tape
+    }
+
+    /// The output produced so far, kept after an error
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// The trace recorded since [`Interpreter::enable_trace`]
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+}
+
+/// A program being executed lazily by [`Interpreter::outputs`] or
+/// [`Interpreter::into_outputs`]
+#[derive(Debug)]
+pub struct Outputs {
+    interpreter: Interpreter,
+    /// `None` once the program ended or failed
+    exec: Option<Result<Execution, BrainfuckError>>,
+    pending: VecDeque<u8>,
+}
+
+impl Iterator for Outputs {
+    type Item = Result<u8, BrainfuckError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(byte) = self.pending.pop_front() {
+                return Some(Ok(byte));
+            }
+            let result = match self.exec.as_mut()? {
+                Ok(exec) => self.interpreter.step(exec, &mut ()),
+                Err(e) => Err(e.clone()),
+            };
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.exec = None;
+                    return None;
+                }
+                Err(e) => {
+                    // Stop for good, so the error is reported exactly once
+                    self.exec = None;
+                    return Some(Err(e));
+                }
+            }
+            let output = &mut self.interpreter.output;
+            self.pending
+                .extend(output.chars().map(|output| output as u32 as u8));
+            output.clear();
+        }
+    }
+}
+
+/// Error returned by [`Builder::build`] for settings no interpreter can run with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The tape has no cells
+    EmptyTape,
+    /// Cells can only be 8, 16 or 32 bits wide
+    CellWidth(u32),
+    /// More than one tape was requested without the `tapes` extension
+    TapesWithoutExtension,
+    /// The number of tapes is not between 1 and [`MAX_TAPES`]
+    TapeCount(usize),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::EmptyTape => write!(f, "`tape_size` must be at least 1"),
+            ConfigError::CellWidth(width) => {
+                write!(f, "`cell_width` must be 8, 16 or 32, not {}", width)
+            }
+            ConfigError::TapesWithoutExtension => {
+                write!(f, "`tapes` requires `extensions = \"tapes\"`")
+            }
+            ConfigError::TapeCount(_) => {
+                write!(f, "`tapes` must be between 1 and {}", MAX_TAPES)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Settings of an [`Interpreter`], created by [`Interpreter::builder`].
+///
+/// Every setting defaults to the behavior of the macros, and the macros configure their
+/// interpreter with it, so options are checked the same way at compile time and at
+/// runtime.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::{Extensions, Interpreter};
+///
+/// let mut interpreter = Interpreter::builder()
+///     .tape_size(8)
+///     .cell_width(16)
+///     .max_steps(10_000)
+///     .extensions(Extensions {
+///         decimal: true,
+///         ..Extensions::default()
+///     })
+///     .build()
+///     .unwrap();
+/// // 64 * 4 = 256 no longer wraps around to 0
+/// assert_eq!(interpreter.execute("++++++++[>++++++++<-]>[<++++>-]<:").unwrap(), "256");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Builder {
+    tape_size: usize,
+    cell_width: u32,
+    max_steps: usize,
+    extensions: Extensions,
+    tapes: Option<usize>,
+    procedures: bool,
+    input: Option<Vec<u8>>,
+    trace: Option<usize>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            tape_size: TAPE_SIZE,
+            cell_width: 8,
+            max_steps: MAX_STEPS,
+            extensions: Extensions::default(),
+            tapes: None,
+            procedures: false,
+            input: None,
+            trace: None,
+        }
+    }
+}
+
+impl Builder {
+    /// The number of cells of every tape (default [`TAPE_SIZE`])
+    pub fn tape_size(mut self, cells: usize) -> Self {
+        self.tape_size = cells;
+        self
+    }
+
+    /// The number of bits of a cell: 8, 16 or 32 (default 8). Values wrap around at the
+    /// width, and `.` outputs the character of the cell's code point.
+    pub fn cell_width(mut self, bits: u32) -> Self {
+        self.cell_width = bits;
+        self
+    }
+
+    /// The maximum number of steps of an execution (default [`MAX_STEPS`])
+    pub fn max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = steps;
+        self
+    }
+
+    /// The extensions of the instruction set, like the `extensions` option
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// The number of tapes of the `tapes` extension, like the `tapes` option (default 2)
+    pub fn tapes(mut self, count: usize) -> Self {
+        self.tapes = Some(count);
+        self
+    }
+
+    /// Execute the pbrain commands, as `brainfuck_pbrain!` does
+    pub fn procedures(mut self, enabled: bool) -> Self {
+        self.procedures = enabled;
+        self
+    }
+
+    /// Read `,` from the given input, like the `input` option
+    pub fn input(mut self, input: &[u8]) -> Self {
+        self.input = Some(input.to_vec());
+        self
+    }
+
+    /// Record the first `limit` executed instructions, like `brainfuck_trace!`
+    pub fn trace(mut self, limit: usize) -> Self {
+        self.trace = Some(limit);
+        self
+    }
+
+    /// Check the settings and create the interpreter
+    pub fn build(self) -> Result<Interpreter, ConfigError> {
+        if self.tape_size == 0 {
+            return Err(ConfigError::EmptyTape);
+        }
+        if ![8, 16, 32].contains(&self.cell_width) {
+            return Err(ConfigError::CellWidth(self.cell_width));
+        }
+        match self.tapes {
+            Some(_) if !self.extensions.tapes => return Err(ConfigError::TapesWithoutExtension),
+            Some(count) if !(1..=MAX_TAPES).contains(&count) => {
+                return Err(ConfigError::TapeCount(count))
+            }
+            _ => {}
+        }
+        Ok(self.create())
+    }
+
+    /// Create the interpreter from settings known to be valid
+    fn create(self) -> Interpreter {
+        let mut interpreter = Interpreter {
+            tape: vec![0; self.tape_size],
+            pointer: 0,
+            tape_size: self.tape_size,
+            mask: u32::MAX >> (32 - self.cell_width),
+            max_steps: self.max_steps,
+            output: String::new(),
+            input: self.input,
+            input_pos: 0,
+            trace: self.trace.map(Trace::new),
+            procedures: self.procedures,
+            extensions: self.extensions,
+            storage: 0,
+            tapes: VecDeque::new(),
+            steps: 0,
+        };
+        if self.extensions.tapes {
+            interpreter.enable_tapes(self.tapes.unwrap_or(2));
+        }
+        interpreter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hello_world() {
+        let code = "++++++++++[>+++++++>++++++++++>+++>+<<<<-]>++.>+.+++++++..+++.>++.<<+++++++++++++++.>.+++.------.--------.>+.>.";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "Hello World!\n");
+    }
+
+    #[test]
+    fn test_simple_output() {
+        // 5 * 13 = 65 = 'A'
+        let code = "+++++[>+++++++++++++<-]>.";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "A");
+    }
+
+    #[test]
+    fn test_loop() {
+        let code = "+++++[>++++<-]>."; // 5 * 4 = 20
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "\u{14}"); // ASCII 20
+    }
+
+    #[test]
+    fn test_unmatched_open_bracket() {
+        let code = "[++";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).map_err(|e| e.kind);
+        assert!(matches!(
+            result,
+            Err(BrainfuckError::UnmatchedOpenBracket(_))
+        ));
+    }
+
+    #[test]
+    fn test_unmatched_close_bracket() {
+        let code = "++]";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).map_err(|e| e.kind);
+        assert!(matches!(
+            result,
+            Err(BrainfuckError::UnmatchedCloseBracket(_))
+        ));
+    }
+
+    #[test]
+    fn test_input_not_supported() {
+        let code = ",";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).map_err(|e| e.kind);
+        assert!(matches!(result, Err(BrainfuckError::InputNotSupported)));
+    }
+
+    #[test]
+    fn test_procedures() {
+        // Procedure 0 prints the cell after the current one, doubled
+        let code = "(>[->++<]>.<<):>+++<:";
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_procedures();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "\u{0}\u{6}");
+    }
+
+    #[test]
+    fn test_procedure_errors() {
+        let run = |code| {
+            let mut interpreter = Interpreter::new();
+            interpreter.enable_procedures();
+            interpreter.execute(code).map_err(|e| e.kind)
+        };
+        assert!(matches!(
+            run("+(:"),
+            Err(BrainfuckError::UnmatchedOpenParen(1))
+        ));
+        assert!(matches!(
+            run("+:"),
+            Err(BrainfuckError::UndefinedProcedure(1, 1))
+        ));
+        assert!(matches!(
+            run("(:):"),
+            Err(BrainfuckError::CallDepthExceeded)
+        ));
+        // Without the extension, the pbrain commands are comments
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.execute("+(:)").unwrap(), "");
+    }
+
+    #[test]
+    fn test_extended_type_one() {
+        let run = |code| {
+            let mut interpreter = Interpreter::new();
+            interpreter.enable_extensions(Extensions {
+                extended: true,
+                ..Default::default()
+            });
+            interpreter.execute(code).unwrap()
+        };
+        // 6 = 0b110 and 3 = 0b011
+        assert_eq!(
+            run("++++++$>+++^.>+++&.>+++|.>!}.>!{.>!~."),
+            "\u{5}\u{2}\u{7}\u{3}\u{c}\u{f9}"
+        );
+        assert_eq!(run("+.@+."), "\u{1}");
+        // Without the extension, the commands are comments
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.execute("+$>!.@+.").unwrap(), "\u{0}\u{1}");
+    }
+
+    #[test]
+    fn test_end() {
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_extensions(Extensions {
+            end: true,
+            ..Default::default()
+        });
+        // Only `@` is enabled, and it also ends loops
+        assert_eq!(interpreter.execute("+$.[@]+.").unwrap(), "\u{1}");
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let run = |code| {
+            let mut interpreter = Interpreter::new();
+            interpreter.enable_extensions(Extensions {
+                bitwise: true,
+                ..Default::default()
+            });
+            interpreter.execute(code).unwrap()
+        };
+        // 6 = 0b110 and 3 = 0b011, with the operand right of the current cell
+        assert_eq!(run("++++++>+++<^."), "\u{5}");
+        assert_eq!(run("++++++>+++<&."), "\u{2}");
+        assert_eq!(run("++++++>+++<|."), "\u{7}");
+        assert_eq!(run("++++++>+<{."), "\u{c}");
+        assert_eq!(run("++++++>++<}."), "\u{1}");
+        assert_eq!(run("++++++>++++++++<{."), "\u{0}");
+        assert_eq!(run("++++++~."), "\u{f9}");
+    }
+
+    #[test]
+    fn test_tapes() {
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_extensions(Extensions {
+            tapes: true,
+            ..Default::default()
+        });
+        interpreter.enable_tapes(3);
+        // Every tape keeps its own pointer; the third `%` returns to the first tape
+        let code = "+>++%+++>%+++++%.<.";
+        assert_eq!(interpreter.execute(code).unwrap(), "\u{2}\u{1}");
+    }
+
+    #[test]
+    fn test_brainfork() {
+        let run = |code| {
+            let mut interpreter = Interpreter::new();
+            interpreter.enable_extensions(Extensions {
+                brainfork: true,
+                ..Default::default()
+            });
+            interpreter.execute(code).map_err(|e| e.kind)
+        };
+        // The parent skips the loop and prints cell 0 before the child, which increments
+        // its own copy of cell 1 and prints it, is done
+        let code = "+++++[>+++++++++++++<-]>Y[<+>-]<.";
+        assert_eq!(run(code).unwrap(), "\u{0}B");
+        assert!(matches!(run("+[Y+]"), Err(BrainfuckError::TooManyThreads)));
+    }
+
+    #[test]
+    fn test_decimal() {
+        let run = |code, input: &[u8]| {
+            let mut interpreter = Interpreter::with_input(input);
+            interpreter.enable_extensions(Extensions {
+                decimal: true,
+                ..Default::default()
+            });
+            interpreter.execute(code).unwrap()
+        };
+        assert_eq!(run("+++++[>+++++++++++++<-]>:", b""), "65");
+        // Whitespace before a number is skipped; numbers wrap around and missing ones read
+        // as 0
+        assert_eq!(run(";+:;:;:;:", b" 41\n300 x"), "424400");
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_extensions(Extensions {
+            decimal: true,
+            ..Default::default()
+        });
+        assert!(matches!(
+            interpreter.execute(";").map_err(|e| e.kind),
+            Err(BrainfuckError::InputNotSupported)
+        ));
+    }
+
+    #[test]
+    fn test_input() {
+        let code = ",+.,+.,.";
+        let mut interpreter = Interpreter::with_input(b"ab");
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "bc\u{00}");
+    }
+
+    #[test]
+    fn test_pointer_underflow() {
+        let code = "<";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).map_err(|e| e.kind);
+        assert!(matches!(result, Err(BrainfuckError::PointerUnderflow)));
+    }
+
+    #[test]
+    fn test_nested_loops() {
+        // 2 outer * 2 inner * 2 innermost = 8 in cell 2
+        let code = "++[>++[>++<-]<-]>>.";
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "\u{08}"); // ASCII 8
+    }
+
+    #[test]
+    fn test_comments() {
+        let code = "This is a comment +++ with text . interspersed"; // Should output ASCII 3
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "\u{03}");
+    }
+
+    #[test]
+    fn test_wrapping() {
+        // Test that cells wrap at 256
+        let code = "--------."; // 0 - 8 = 248 (wrapping)
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.execute(code).unwrap();
+        assert_eq!(result, "\u{f8}");
+    }
+
+    #[test]
+    fn test_builder_limits() {
+        let mut interpreter = Interpreter::builder().tape_size(2).build().unwrap();
+        assert_eq!(
+            interpreter.execute(">>").map_err(|e| e.kind),
+            Err(BrainfuckError::PointerOverflow(2))
+        );
+        let mut interpreter = Interpreter::builder().max_steps(10).build().unwrap();
+        assert_eq!(
+            interpreter.execute("+[]").map_err(|e| e.kind),
+            Err(BrainfuckError::MaxStepsExceeded(10))
+        );
+        assert_eq!(
+            interpreter.execute("+[]").unwrap_err().to_string(),
+            "Execution exceeded maximum steps (10)"
+        );
+    }
+
+    #[test]
+    fn test_builder_cell_width() {
+        let run = |bits, code| {
+            let mut interpreter = Interpreter::builder().cell_width(bits).build().unwrap();
+            interpreter.execute(code).unwrap()
+        };
+        assert_eq!(run(8, "-."), "\u{ff}");
+        assert_eq!(run(16, "-."), "\u{ffff}");
+        // 2^32 - 1 is no character
+        assert_eq!(run(32, "-."), "\u{fffd}");
+        let mut interpreter = Interpreter::builder()
+            .cell_width(16)
+            .extensions(Extensions {
+                bitwise: true,
+                ..Extensions::default()
+            })
+            .build()
+            .unwrap();
+        // Shifting wraps at the width of a cell
+        assert_eq!(interpreter.execute("->+<{.~.").unwrap(), "\u{fffe}\u{1}");
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let tapes = Extensions {
+            tapes: true,
+            ..Extensions::default()
+        };
+        let error = |builder: Builder| builder.build().unwrap_err();
+        assert_eq!(
+            error(Interpreter::builder().tape_size(0)),
+            ConfigError::EmptyTape
+        );
+        assert_eq!(
+            error(Interpreter::builder().cell_width(12)),
+            ConfigError::CellWidth(12)
+        );
+        assert_eq!(
+            error(Interpreter::builder().tapes(3)),
+            ConfigError::TapesWithoutExtension
+        );
+        assert_eq!(
+            error(Interpreter::builder().extensions(tapes).tapes(17)),
+            ConfigError::TapeCount(17)
+        );
+        let mut interpreter = Interpreter::builder()
+            .extensions(tapes)
+            .tapes(3)
+            .input(b"x")
+            .build()
+            .unwrap();
+        assert_eq!(interpreter.execute(",%%%.").unwrap(), "x");
+    }
+
+    #[test]
+    fn test_outputs() {
+        let mut outputs = Interpreter::outputs("+[>+.<]", b"");
+        // Unbounded output, without a step limit
+        assert_eq!(outputs.nth(299), Some(Ok(44)));
+
+        let interpreter = Interpreter::builder()
+            .extensions(Extensions {
+                brainfork: true,
+                decimal: true,
+                ..Extensions::default()
+            })
+            .build()
+            .unwrap();
+        let outputs: Vec<_> = interpreter.into_outputs("++++++++++:Y.").collect();
+        // The child runs first after the fork
+        assert_eq!(outputs, [Ok(b'1'), Ok(b'0'), Ok(1), Ok(0)]);
+
+        let mut outputs = Interpreter::outputs("+.<", b"");
+        assert_eq!(outputs.next(), Some(Ok(1)));
+        assert_eq!(outputs.next(), Some(Err(BrainfuckError::PointerUnderflow)));
+        assert_eq!(outputs.next(), None);
+        let mut outputs = Interpreter::outputs("]", b"");
+        assert_eq!(
+            outputs.next(),
+            Some(Err(BrainfuckError::UnmatchedCloseBracket(0)))
+        );
+        assert_eq!(outputs.next(), None);
+    }
+
+    #[test]
+    fn test_error_details() {
+        let error = Interpreter::new().execute("+\n+[\n\n  ]]").unwrap_err();
+        assert_eq!(error.kind, BrainfuckError::UnmatchedCloseBracket(9));
+        assert_eq!((error.position, error.line, error.column), (9, 4, 4));
+        assert_eq!((error.steps, error.output_len), (0, 0));
+
+        let mut interpreter = Interpreter::builder().max_steps(5).build().unwrap();
+        let error = interpreter.execute("+.+.+[]").unwrap_err();
+        assert_eq!(error.kind, BrainfuckError::MaxStepsExceeded(5));
+        assert_eq!((error.position, error.line, error.column), (5, 1, 6));
+        assert_eq!((error.steps, error.output_len), (5, 2));
+        assert_eq!(interpreter.output().len(), error.output_len);
+        let source: &dyn std::error::Error = &error;
+        assert_eq!(source.to_string(), "Execution exceeded maximum steps (5)");
+    }
+
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl ExecutionHook for Events {
+        fn on_output(&mut self, output: &str) {
+            self.0.push(format!("output {:?}", output));
+        }
+
+        fn on_loop_enter(&mut self, position: usize) {
+            self.0.push(format!("enter {}", position));
+        }
+
+        fn on_loop_exit(&mut self, position: usize) {
+            self.0.push(format!("exit {}", position));
+        }
+    }
+
+    #[test]
+    fn test_hook() {
+        let mut interpreter = Interpreter::builder()
+            .extensions(Extensions {
+                decimal: true,
+                ..Extensions::default()
+            })
+            .trace(2)
+            .build()
+            .unwrap();
+        let mut events = Events::default();
+        let output = interpreter
+            .execute_with_hook("[-]++[>+[-]<-]>+++:.", &mut events)
+            .unwrap();
+        assert_eq!(output, "3\u{3}");
+        // The skipped loop is never entered, the inner one once per outer iteration
+        assert_eq!(
+            events.0,
+            [
+                "enter 5",
+                "enter 8",
+                "exit 8",
+                "enter 8",
+                "exit 8",
+                "exit 5",
+                "output \"3\"",
+                "output \"\\u{3}\"",
+            ]
+        );
+        assert_eq!(
+            interpreter
+                .trace()
+                .unwrap()
+                .render("", None)
+                .lines()
+                .count(),
+            4
+        );
+    }
+}
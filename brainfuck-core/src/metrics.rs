@@ -0,0 +1,140 @@
+//! Static metrics of programs.
+//!
+//! [`Metrics`] describes a program without running it: how often every command occurs,
+//! how its loops nest and how much of it is comments or redundant commands. Leaderboards
+//! and code-review tools compare programs by it, and `brainfuck_metrics!` computes it at
+//! compile time into a constant.
+
+use crate::ast::{self, Command, Span, Visitor};
+use crate::interpret::RunError;
+use crate::ir;
+
+/// The number of occurrences of every command other than the brackets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Histogram {
+    /// `>`
+    pub right: usize,
+    /// `<`
+    pub left: usize,
+    /// `+`
+    pub increment: usize,
+    /// `-`
+    pub decrement: usize,
+    /// `.`
+    pub output: usize,
+    /// `,`
+    pub input: usize,
+}
+
+/// Static metrics of a program.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::metrics::Metrics;
+///
+/// let metrics = Metrics::of("Print A: +++++ [>+++++++++++++<-] +- >.").unwrap();
+/// assert_eq!(metrics.histogram.increment, 19);
+/// assert_eq!((metrics.loops, metrics.max_depth), (1, 1));
+/// assert_eq!(metrics.instructions(), 27);
+/// assert_eq!((metrics.length, metrics.minified_length), (39, 25));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Metrics {
+    /// How often every command other than the brackets occurs
+    pub histogram: Histogram,
+    /// The number of loops, that is of `[`
+    pub loops: usize,
+    /// The deepest nesting of loops, 0 without loops
+    pub max_depth: usize,
+    /// The length of the program in characters, comments included
+    pub length: usize,
+    /// The length of the program minified like `brainfuck_minify!`: without comments and
+    /// with runs of commands folded to their net effect
+    pub minified_length: usize,
+}
+
+impl Metrics {
+    /// Compute the metrics of a program, checking its brackets
+    pub fn of(code: &str) -> Result<Self, RunError> {
+        let mut counter = Counter::default();
+        ast::parse(code)?.walk(&mut counter);
+        let minified = ir::to_source(&ir::parse(code)?);
+        Ok(Self {
+            histogram: counter.histogram,
+            loops: counter.loops,
+            max_depth: counter.max_depth,
+            length: code.chars().count(),
+            minified_length: minified.chars().count(),
+        })
+    }
+
+    /// The number of commands, brackets included
+    pub fn instructions(&self) -> usize {
+        let h = &self.histogram;
+        h.right + h.left + h.increment + h.decrement + h.output + h.input + 2 * self.loops
+    }
+}
+
+/// Counts the commands and loops of a syntax tree
+#[derive(Default)]
+struct Counter {
+    histogram: Histogram,
+    loops: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Visitor for Counter {
+    fn visit_command(&mut self, command: Command, _span: Span) {
+        let count = match command {
+            Command::Right => &mut self.histogram.right,
+            Command::Left => &mut self.histogram.left,
+            Command::Increment => &mut self.histogram.increment,
+            Command::Decrement => &mut self.histogram.decrement,
+            Command::Output => &mut self.histogram.output,
+            Command::Input => &mut self.histogram.input,
+        };
+        *count += 1;
+    }
+
+    fn enter_loop(&mut self, _span: Span) {
+        self.loops += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+
+    fn exit_loop(&mut self, _span: Span) {
+        self.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::of("+[>[-]<[>,.<-]]").unwrap();
+        let expected = Metrics {
+            histogram: Histogram {
+                right: 2,
+                left: 2,
+                increment: 1,
+                decrement: 2,
+                output: 1,
+                input: 1,
+            },
+            loops: 3,
+            max_depth: 2,
+            length: 15,
+            minified_length: 15,
+        };
+        assert_eq!(metrics, expected);
+        assert_eq!(metrics.instructions(), 15);
+        assert_eq!(
+            Metrics::of("[").unwrap_err(),
+            RunError::UnmatchedOpenBracket(0)
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Optimization passes over the IR.
+//!
+//! Every pass is a function from IR to IR: [`rle`] folds runs of additions and moves,
+//! [`clear_loops`] and [`mul_loops`] replace loop idioms, and [`dce`] drops code that can
+//! never run. A [`PassManager`] chains them, or passes of its own, into a pipeline.
+//!
+//! `optimize` runs the loop passes and is what the code generation backends use.
+//! `remove_dead_code` (the [`dce`] pass) additionally drops code that can never run,
+//! which only matters when re-emitting the program (an unreachable loop costs nothing at
+//! runtime).
+
+use crate::ir::Op;
+
+/// A pass over the IR
+pub type Pass = fn(Vec<Op>) -> Vec<Op>;
+
+/// A pipeline of passes run in order.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::ir::{from_ast, Op};
+/// use brainfuck_core::optimize::{clear_loops, dce, rle, PassManager};
+///
+/// let ast = brainfuck_core::ast::parse("[.] ++ [-]").unwrap();
+/// let passes = PassManager::new().pass(rle).pass(clear_loops).pass(dce);
+/// assert_eq!(passes.run(from_ast(&ast)), [Op::Add(2), Op::Clear]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PassManager {
+    passes: Vec<Pass>,
+}
+
+impl PassManager {
+    /// Create a pipeline without passes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The passes of [`optimize`]: [`clear_loops`], then [`mul_loops`]
+    pub fn optimize() -> Self {
+        Self::new().pass(clear_loops).pass(mul_loops)
+    }
+
+    /// Append a pass to the pipeline
+    pub fn pass(mut self, pass: Pass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// The passes of the pipeline, in order
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    /// Run every pass in order
+    pub fn run(&self, ops: Vec<Op>) -> Vec<Op> {
+        self.passes.iter().fold(ops, |ops, pass| pass(ops))
+    }
+}
+
+/// Apply all optimization passes
+pub fn optimize(ops: Vec<Op>) -> Vec<Op> {
+    PassManager::optimize().run(ops)
+}
+
+/// Fold adjacent additions and adjacent moves into one, dropping those that cancel out.
+///
+/// [`crate::ir::parse`] already folds the runs of the source, so this only matters after
+/// other passes brought operations together, or for IR built otherwise.
+pub fn rle(ops: Vec<Op>) -> Vec<Op> {
+    let mut folded: Vec<Op> = Vec::new();
+    for op in ops {
+        match (folded.last_mut(), op) {
+            (Some(Op::Add(n)), Op::Add(delta)) => {
+                *n = n.wrapping_add(delta);
+                if *n == 0 {
+                    folded.pop();
+                }
+            }
+            (Some(Op::Move(n)), Op::Move(delta)) => {
+                *n += delta;
+                if *n == 0 {
+                    folded.pop();
+                }
+            }
+            (_, Op::Add(0) | Op::Move(0)) => {}
+            (_, Op::Loop(body)) => folded.push(Op::Loop(rle(body))),
+            (_, op) => folded.push(op),
+        }
+    }
+    folded
+}
+
+/// Replace the loops `[-]` and `[+]` with [`Op::Clear`]
+pub fn clear_loops(ops: Vec<Op>) -> Vec<Op> {
+    map_loops(ops, clear_loops, clear_loop)
+}
+
+/// Replace loops that only add and move, return to where they started and change the
+/// current cell by one per iteration with [`Op::MulLoop`]
+pub fn mul_loops(ops: Vec<Op>) -> Vec<Op> {
+    map_loops(ops, mul_loops, mul_loop)
+}
+
+/// Apply `pass` to the body of every loop, then replace the loop with the result of
+/// `replace` for its new body, if any
+fn map_loops(ops: Vec<Op>, pass: Pass, replace: fn(&[Op]) -> Option<Op>) -> Vec<Op> {
+    ops.into_iter()
+        .map(|op| match op {
+            Op::Loop(body) => {
+                let body = pass(body);
+                replace(&body).unwrap_or(Op::Loop(body))
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Remove loops that are never entered because the current cell is known to be zero:
+/// loops at the start of the program, where the whole tape is zero, and loops directly
+/// following another loop or clear, which only finish once the cell is zero
+pub fn dce(ops: Vec<Op>) -> Vec<Op> {
+    remove_dead(ops, true)
+}
+
+/// Remove loops that are never entered, like [`dce`]
+pub fn remove_dead_code(ops: Vec<Op>) -> Vec<Op> {
+    dce(ops)
+}
+
+fn remove_dead(ops: Vec<Op>, mut zero: bool) -> Vec<Op> {
+    let mut live = Vec::new();
+    for op in ops {
+        let clears = matches!(op, Op::Loop(_) | Op::Clear | Op::MulLoop(_));
+        if clears && zero {
+            continue;
+        }
+        zero = clears || (zero && op == Op::Output);
+        live.push(match op {
+            Op::Loop(body) => Op::Loop(remove_dead(body, false)),
+            other => other,
+        });
+    }
+    live
+}
+
+/// `[-]` and `[+]` set the current cell to zero
+fn clear_loop(body: &[Op]) -> Option<Op> {
+    match body {
+        [Op::Add(1)] | [Op::Add(u8::MAX)] => Some(Op::Clear),
+        _ => None,
+    }
+}
+
+/// A loop that only adds and moves, returns to where it started, and changes the
+/// current cell by exactly one per iteration runs `cell` (or `256 - cell`) times, so
+/// its effect is a set of multiplications followed by clearing the current cell
+fn mul_loop(body: &[Op]) -> Option<Op> {
+    let mut offset = 0;
+    let mut deltas: Vec<(isize, u8)> = Vec::new();
+    for op in body {
+        match op {
+            Op::Move(n) => offset += n,
+            Op::Add(n) => match deltas.iter_mut().find(|(o, _)| *o == offset) {
+                Some((_, delta)) => *delta = delta.wrapping_add(*n),
+                None => deltas.push((offset, *n)),
+            },
+            _ => return None,
+        }
+    }
+    if offset != 0 {
+        return None;
+    }
+
+    let counter = deltas
+        .iter()
+        .find(|(o, _)| *o == 0)
+        .map_or(0, |(_, delta)| *delta);
+    let negate = match counter {
+        u8::MAX => false,
+        1 => true,
+        _ => return None,
+    };
+
+    let terms = deltas
+        .into_iter()
+        .filter(|&(o, delta)| o != 0 && delta != 0)
+        .map(|(o, delta)| (o, if negate { delta.wrapping_neg() } else { delta }))
+        .collect();
+    Some(Op::MulLoop(terms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::parse;
+
+    #[test]
+    fn test_clear_loops() {
+        let ops = optimize(parse("+[-]>[+]").unwrap());
+        assert_eq!(ops, vec![Op::Add(1), Op::Clear, Op::Move(1), Op::Clear]);
+    }
+
+    #[test]
+    fn test_mul_loops() {
+        let ops = optimize(parse("[->+++>--<<]").unwrap());
+        assert_eq!(ops, vec![Op::MulLoop(vec![(1, 3), (2, 254)])]);
+    }
+
+    #[test]
+    fn test_incrementing_mul_loop() {
+        let ops = optimize(parse("[<++>+]").unwrap());
+        assert_eq!(ops, vec![Op::MulLoop(vec![(-1, 254)])]);
+    }
+
+    #[test]
+    fn test_non_mul_loops_kept() {
+        for code in ["[->+<<]", "[-->+<]", "[->.<]", "[>]"] {
+            let ops = optimize(parse(code).unwrap());
+            assert!(matches!(ops[..], [Op::Loop(_)]), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_rle() {
+        let ops = vec![
+            Op::Add(1),
+            Op::Add(2),
+            Op::Move(1),
+            Op::Move(-1),
+            Op::Add(253),
+            Op::Loop(vec![Op::Move(0), Op::Output]),
+        ];
+        assert_eq!(rle(ops), vec![Op::Loop(vec![Op::Output])]);
+    }
+
+    #[test]
+    fn test_pass_manager() {
+        let ops = parse("+[-]>[->++<]").unwrap();
+        assert_eq!(
+            PassManager::optimize().run(ops.clone()),
+            optimize(ops.clone())
+        );
+        assert_eq!(PassManager::new().run(ops.clone()), ops);
+        // Without clear_loops, `[-]` is a multiplication without terms
+        let passes = PassManager::new().pass(mul_loops);
+        assert_eq!(passes.passes().len(), 1);
+        assert_eq!(
+            passes.run(ops),
+            vec![
+                Op::Add(1),
+                Op::MulLoop(vec![]),
+                Op::Move(1),
+                Op::MulLoop(vec![(1, 2)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dead_loops_removed() {
+        let ops = remove_dead_code(optimize(parse("[.]+[->+<][-][>]>.[-]").unwrap()));
+        assert_eq!(
+            ops,
+            vec![
+                Op::Add(1),
+                Op::MulLoop(vec![(1, 1)]),
+                Op::Move(1),
+                Op::Output,
+                Op::Clear,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dead_code_in_loop_bodies() {
+        let ops = remove_dead_code(parse("+[[-][.]>]").unwrap());
+        assert_eq!(
+            ops,
+            vec![
+                Op::Add(1),
+                Op::Loop(vec![Op::Loop(vec![Op::Add(255)]), Op::Move(1)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_loops_optimized() {
+        let ops = optimize(parse("+[>[-]<-]").unwrap());
+        assert_eq!(
+            ops,
+            vec![
+                Op::Add(1),
+                Op::Loop(vec![Op::Move(1), Op::Clear, Op::Move(-1), Op::Add(255)]),
+            ]
+        );
+    }
+}
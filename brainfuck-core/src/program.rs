@@ -0,0 +1,177 @@
+//! Programs compiled once and run many times.
+//!
+//! [`Program`] holds the optimized IR of a program, so servers and other hosts running
+//! the same program over many inputs parse and optimize it only once. Programs are
+//! `Send + Sync` and cloning one shares the compiled operations.
+
+use std::sync::Arc;
+
+use crate::interpret::{RunError, MAX_STEPS, TAPE_SIZE};
+use crate::ir::{self, Op};
+use crate::optimize::optimize;
+
+/// A parsed and optimized Brainfuck program.
+///
+/// [`Program::run`] behaves like [`crate::interpret::run`], on a tape of [`TAPE_SIZE`]
+/// cells with `,` storing 0 once the input is exhausted, except for the step limit: it
+/// counts executed IR operations rather than characters, and an optimized loop like
+/// `[-]` is a single operation, so a program may finish here that `run` stops.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::program::Program;
+///
+/// let upper = Program::compile(",[--------------------------------.,]").unwrap();
+/// let handles: Vec<_> = ["abc", "xyz"]
+///     .into_iter()
+///     .map(|word| {
+///         let upper = upper.clone();
+///         std::thread::spawn(move || upper.run(word.as_bytes()).unwrap())
+///     })
+///     .collect();
+/// let words: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+/// assert_eq!(words, [b"ABC", b"XYZ"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    ops: Arc<[Op]>,
+}
+
+impl Program {
+    /// Parse and optimize Brainfuck source, checking its brackets
+    pub fn compile(code: &str) -> Result<Self, RunError> {
+        let ops = optimize(ir::parse(code)?);
+        Ok(Self { ops: ops.into() })
+    }
+
+    /// The optimized operations of the program
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Run the program over `input` on a fresh tape and return its output
+    pub fn run(&self, input: &[u8]) -> Result<Vec<u8>, RunError> {
+        let mut machine = Machine {
+            tape: vec![0; TAPE_SIZE],
+            pointer: 0,
+            input,
+            input_pos: 0,
+            output: Vec::new(),
+            steps: 0,
+        };
+        machine.execute(&self.ops)?;
+        Ok(machine.output)
+    }
+}
+
+/// The state of one run of a [`Program`]
+struct Machine<'a> {
+    tape: Vec<u8>,
+    pointer: usize,
+    input: &'a [u8],
+    input_pos: usize,
+    output: Vec<u8>,
+    steps: usize,
+}
+
+impl Machine<'_> {
+    fn execute(&mut self, ops: &[Op]) -> Result<(), RunError> {
+        for op in ops {
+            self.count_step()?;
+            match op {
+                Op::Add(n) => self.tape[self.pointer] = self.tape[self.pointer].wrapping_add(*n),
+                Op::Move(n) => self.pointer = self.offset(*n)?,
+                Op::Output => self.output.push(self.tape[self.pointer]),
+                Op::Input => {
+                    self.tape[self.pointer] = self.input.get(self.input_pos).copied().unwrap_or(0);
+                    self.input_pos += 1;
+                }
+                Op::Loop(body) => {
+                    while self.tape[self.pointer] != 0 {
+                        self.execute(body)?;
+                        self.count_step()?;
+                    }
+                }
+                Op::Clear => self.tape[self.pointer] = 0,
+                Op::MulLoop(terms) => {
+                    let value = self.tape[self.pointer];
+                    if value != 0 {
+                        for &(offset, factor) in terms {
+                            let cell = self.offset(offset)?;
+                            self.tape[cell] =
+                                self.tape[cell].wrapping_add(value.wrapping_mul(factor));
+                        }
+                        self.tape[self.pointer] = 0;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn count_step(&mut self) -> Result<(), RunError> {
+        if self.steps >= MAX_STEPS {
+            return Err(RunError::MaxStepsExceeded);
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// The position of the cell `offset` cells from the pointer
+    fn offset(&self, offset: isize) -> Result<usize, RunError> {
+        let cell = self
+            .pointer
+            .checked_add_signed(offset)
+            .ok_or(RunError::PointerUnderflow)?;
+        if cell >= self.tape.len() {
+            return Err(RunError::PointerOverflow);
+        }
+        Ok(cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpret::run;
+
+    #[test]
+    fn test_matches_interpreter() {
+        let programs = [
+            "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.",
+            ",[.,]",
+            ",>,[<+>-]<.",
+            "+++[>+++++<-]>[>++<-]>.",
+        ];
+        for code in programs {
+            let program = Program::compile(code).unwrap();
+            for input in [&b""[..], b"ab", b"\x05\x07"] {
+                assert_eq!(program.run(input), run(code, input), "{}", code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Program::compile("+[").unwrap_err(),
+            RunError::UnmatchedOpenBracket(1)
+        );
+        let program = Program::compile("+[->+<]<").unwrap();
+        assert_eq!(program.run(b""), Err(RunError::PointerUnderflow));
+        let program = Program::compile("+[>+]").unwrap();
+        assert_eq!(program.run(b""), Err(RunError::PointerOverflow));
+        let program = Program::compile("+[]").unwrap();
+        assert_eq!(program.run(b""), Err(RunError::MaxStepsExceeded));
+    }
+
+    #[test]
+    fn test_shared() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Program>();
+
+        let program = Program::compile("+[-]").unwrap();
+        assert!(Arc::ptr_eq(&program.ops, &program.clone().ops));
+    }
+}
@@ -0,0 +1,205 @@
+//! Seeded random programs for property-based testing.
+//!
+//! [`program`] generates a syntactically valid program from a seed, and the same seed
+//! always gives the same program. Generated programs always terminate within
+//! [`Options::max_steps`] and never move the pointer off the first [`Options::cells`]
+//! cells, so every implementation of Brainfuck must agree on their output: running one
+//! with the interpreter, after optimizing and transpiled must give the same bytes.
+//!
+//! Termination holds by construction. Every loop decrements its counter cell once per
+//! iteration, right before its `]`, and nothing inside the loop touches that cell
+//! otherwise or moves the pointer on balance, so a loop runs at most 255 times. Loop
+//! bodies are kept small enough that the worst case fits the step budget.
+
+use crate::interpret::MAX_STEPS;
+
+/// The shape of the programs [`program`] generates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// The number of commands to generate, not counting the ones closing loops (default 64)
+    pub length: usize,
+    /// The deepest nesting of loops (default 2)
+    pub max_depth: usize,
+    /// The number of cells the pointer stays within, at least 1 (default 8)
+    pub cells: usize,
+    /// Whether to generate `,` (default `true`)
+    pub input: bool,
+    /// The most steps a run of the program can take (default [`MAX_STEPS`])
+    pub max_steps: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            length: 64,
+            max_depth: 2,
+            cells: 8,
+            input: true,
+            max_steps: MAX_STEPS,
+        }
+    }
+}
+
+/// Generate the program of a seed.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::interpret;
+/// use brainfuck_core::random::{program, Options};
+///
+/// for seed in 0..100 {
+///     let code = program(seed, &Options::default());
+///     assert_eq!(code, program(seed, &Options::default()));
+///     assert!(interpret::run(&code, b"input").is_ok());
+/// }
+/// ```
+pub fn program(seed: u64, options: &Options) -> String {
+    let mut generator = Generator {
+        state: seed,
+        options,
+        budget: options.length,
+        pointer: 0,
+        counters: Vec::new(),
+        code: String::new(),
+    };
+    generator.sequence(options.max_steps, None);
+    generator.code
+}
+
+/// The state of generating one program
+struct Generator<'a> {
+    /// The state of the SplitMix64 generator
+    state: u64,
+    options: &'a Options,
+    /// The number of commands left to generate
+    budget: usize,
+    /// The cell the pointer is at when the generated code has run
+    pointer: usize,
+    /// The counter cells of the loops being generated, which their bodies leave alone
+    counters: Vec<usize>,
+    code: String,
+}
+
+impl Generator<'_> {
+    /// The next number of the SplitMix64 sequence, uniform in `0..bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) % bound
+    }
+
+    /// Generate commands taking at most `limit` steps, returning the steps they can take.
+    /// Inside a loop, `home` is its counter cell, which the commands end at.
+    fn sequence(&mut self, limit: usize, home: Option<usize>) -> usize {
+        let mut steps = 0;
+        // Loop bodies stop early at random, the program itself uses up the budget
+        while self.budget > 0 && !(home.is_some() && self.below(6) == 0) {
+            let back = |pointer: usize| home.map_or(0, |home| pointer.abs_diff(home));
+            let free = !self.counters.contains(&self.pointer);
+            // The steps left with the moves back home still to come
+            let left = limit - steps - back(self.pointer);
+            let choice = self.below(8);
+            let body_limit = (left.saturating_sub(1) / 255).saturating_sub(2);
+            if choice == 0 && free && self.counters.len() < self.options.max_depth && body_limit > 0
+            {
+                self.budget -= 1;
+                self.code.push('[');
+                self.counters.push(self.pointer);
+                let body = self.sequence(body_limit, Some(self.pointer));
+                self.counters.pop();
+                self.code.push_str("-]");
+                steps += 1 + 255 * (body + 2);
+                continue;
+            }
+            if left == 0 {
+                break;
+            }
+            let cells = self.options.cells.max(1);
+            let command = match choice {
+                1 if self.pointer + 1 < cells
+                    && back(self.pointer + 1) < back(self.pointer) + left =>
+                {
+                    self.pointer += 1;
+                    '>'
+                }
+                2 if self.pointer > 0 && back(self.pointer - 1) < back(self.pointer) + left => {
+                    self.pointer -= 1;
+                    '<'
+                }
+                3 | 4 if free => '+',
+                5 if free => '-',
+                6 => '.',
+                7 if free && self.options.input => ',',
+                _ => continue,
+            };
+            self.budget -= 1;
+            self.code.push(command);
+            steps += 1;
+        }
+        if let Some(home) = home {
+            let (command, distance) = if self.pointer < home {
+                ('>', home - self.pointer)
+            } else {
+                ('<', self.pointer - home)
+            };
+            self.code.extend(std::iter::repeat_n(command, distance));
+            self.pointer = home;
+            steps += distance;
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, Limits};
+    use crate::{interpret, ir, optimize};
+
+    #[test]
+    fn test_programs_terminate() {
+        let options = Options {
+            length: 200,
+            max_depth: 3,
+            cells: 4,
+            max_steps: 20_000,
+            ..Options::default()
+        };
+        let limits = Limits {
+            max_steps: options.max_steps,
+            tape_size: options.cells,
+            ..Limits::default()
+        };
+        let mut loops = 0;
+        for seed in 0..500 {
+            let code = program(seed, &options);
+            loops += code.matches('[').count();
+            let output = eval(code.as_bytes(), b"abc", limits).unwrap();
+            assert_eq!(interpret::run(&code, b"abc").unwrap(), output.bytes);
+            let optimized = ir::to_source(&optimize::optimize(ir::parse(&code).unwrap()));
+            assert_eq!(interpret::run(&optimized, b"abc").unwrap(), output.bytes);
+        }
+        assert!(loops > 500);
+    }
+
+    #[test]
+    fn test_options() {
+        let options = Options {
+            length: 30,
+            input: false,
+            max_depth: 0,
+            cells: 1,
+            ..Options::default()
+        };
+        let code = program(7, &options);
+        assert_eq!(code.len(), 30);
+        assert!(code.chars().all(|c| "+-.".contains(c)));
+        assert_ne!(
+            program(7, &Options::default()),
+            program(8, &Options::default())
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Reduction of failing programs to minimal reproductions.
+//!
+//! [`minimize`] runs delta debugging over the characters of a program: it keeps
+//! removing chunks of the program as long as what is left still fails, halving the
+//! chunks whenever no chunk can go. A failure in thousands of characters typically ends
+//! up as a program of a few commands that fails the same way.
+
+use crate::machine::{Error, Interpreter};
+
+/// Reduce `code` to a smaller program for which `fails` still holds, assuming it holds
+/// for `code` itself.
+///
+/// The result is 1-minimal: removing any single character of it makes `fails` false.
+/// `fails` is called once per candidate, so its cost, typically running the program,
+/// dominates.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::reduce::minimize;
+///
+/// // The smallest program that still prints a 3
+/// let reduced = minimize("++[>+<-]+.>-.", |code| code.contains("+++") && code.ends_with('.'));
+/// assert_eq!(reduced, "+++.");
+/// ```
+pub fn minimize(code: &str, mut fails: impl FnMut(&str) -> bool) -> String {
+    let mut chars: Vec<char> = code.chars().collect();
+    let mut chunks = 2;
+    while chars.len() >= 2 {
+        let size = chars.len().div_ceil(chunks);
+        let reduced = (0..chars.len()).step_by(size).find_map(|start| {
+            let end = (start + size).min(chars.len());
+            let candidate: String = chars[..start].iter().chain(&chars[end..]).collect();
+            fails(&candidate).then_some(candidate)
+        });
+        match reduced {
+            Some(candidate) => {
+                chars = candidate.chars().collect();
+                chunks = (chunks - 1).max(2);
+            }
+            None if chunks >= chars.len() => break,
+            None => chunks = (chunks * 2).min(chars.len()),
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Reduce a program that failed with `error` to a minimal program failing with the same
+/// kind of error, on interpreters created by `interpreter`.
+///
+/// On top of [`minimize`], pairs of brackets are removed together, so loops that play no
+/// part in the error go away.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::Interpreter;
+/// use brainfuck_core::reduce::minimize_error;
+///
+/// let code = "Print 3: +++. then go back twice: <<.";
+/// let error = Interpreter::new().execute(code).unwrap_err();
+/// assert_eq!(minimize_error(code, &error, Interpreter::new), "<");
+/// ```
+pub fn minimize_error(
+    code: &str,
+    error: &Error,
+    mut interpreter: impl FnMut() -> Interpreter,
+) -> String {
+    let kind = std::mem::discriminant(&error.kind);
+    let mut fails = |candidate: &str| {
+        interpreter()
+            .execute(candidate)
+            .is_err_and(|e| std::mem::discriminant(&e.kind) == kind)
+    };
+    let mut reduced = minimize(code, &mut fails);
+    // Removing a single bracket unbalances the program, so loops that do not matter
+    // survive as pairs: drop them pair by pair
+    while let Some(candidate) = without_bracket_pairs(&reduced)
+        .into_iter()
+        .find(|c| fails(c))
+    {
+        reduced = minimize(&candidate, &mut fails);
+    }
+    reduced
+}
+
+/// Every program left by removing one `[` and one later `]` from `code`
+fn without_bracket_pairs(code: &str) -> Vec<String> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut candidates = Vec::new();
+    for open in (0..chars.len()).filter(|&i| chars[i] == '[') {
+        for close in (open + 1..chars.len()).filter(|&i| chars[i] == ']') {
+            let candidate = chars
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != open && i != close)
+                .map(|(_, &c)| c)
+                .collect();
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::BrainfuckError;
+
+    #[test]
+    fn test_minimize_error() {
+        let code = "Loop forever: +[ comment >+<-+ ] never reached: <";
+        let error = Interpreter::new().execute(code).unwrap_err();
+        assert!(matches!(error.kind, BrainfuckError::MaxStepsExceeded(_)));
+        assert_eq!(minimize_error(code, &error, Interpreter::new), "+[]");
+
+        let code = "Cell 1 gets 3: +++[>+<-] then back twice: <<";
+        let error = Interpreter::new().execute(code).unwrap_err();
+        assert_eq!(minimize_error(code, &error, Interpreter::new), "<");
+
+        let code = "+[>+<-]]";
+        let error = Interpreter::new().execute(code).unwrap_err();
+        assert_eq!(minimize_error(code, &error, Interpreter::new), "]");
+    }
+}
@@ -0,0 +1,239 @@
+//! Limits for running untrusted programs.
+//!
+//! [`Sandbox`] runs programs on the [interpreter](crate::machine::Interpreter) of the
+//! macros with caps on the steps, the tape, the output and the wall-clock time, for
+//! online judges, games and other hosts executing code submitted by users. Exceeding a
+//! cap ends the run with a [`Violation`] saying which one.
+
+use std::time::{Duration, Instant};
+
+use crate::machine::{BrainfuckError, Error, Interpreter, MAX_STEPS, TAPE_SIZE};
+
+/// The number of steps between two checks of the deadline
+const DEADLINE_INTERVAL: usize = 1024;
+
+/// Limits for running untrusted programs.
+///
+/// Every limit defaults to that of the macros, with at most 64 KiB of output and no
+/// deadline. A sandbox can be reused for any number of runs.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use brainfuck_core::sandbox::{Sandbox, Violation};
+///
+/// let sandbox = Sandbox::new()
+///     .fuel(10_000)
+///     .memory(1_000)
+///     .max_output(16)
+///     .deadline(Duration::from_millis(100));
+/// assert_eq!(sandbox.run(",[.,]", b"hello").unwrap(), "hello");
+/// assert_eq!(
+///     sandbox.run("+[]", b""),
+///     Err(Violation::FuelExhausted { fuel: 10_000 })
+/// );
+/// assert_eq!(
+///     sandbox.run("+[.]", b""),
+///     Err(Violation::OutputExceeded { max_output: 16 })
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    fuel: usize,
+    memory: usize,
+    max_output: usize,
+    deadline: Option<Duration>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sandbox {
+    /// Create a sandbox with the default limits
+    pub fn new() -> Self {
+        Self {
+            fuel: MAX_STEPS,
+            memory: TAPE_SIZE,
+            max_output: 64 * 1024,
+            deadline: None,
+        }
+    }
+
+    /// The maximum number of steps of a run (default [`MAX_STEPS`])
+    pub fn fuel(mut self, steps: usize) -> Self {
+        self.fuel = steps;
+        self
+    }
+
+    /// The number of cells of the tape, at least 1 (default [`TAPE_SIZE`])
+    pub fn memory(mut self, cells: usize) -> Self {
+        self.memory = cells.max(1);
+        self
+    }
+
+    /// The maximum length of the output in bytes (default 64 KiB)
+    pub fn max_output(mut self, bytes: usize) -> Self {
+        self.max_output = bytes;
+        self
+    }
+
+    /// The maximum wall-clock time of a run (default none), checked every 1024 steps
+    pub fn deadline(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(timeout);
+        self
+    }
+
+    /// Run a program over `input` within the limits and return its output, with every
+    /// cell value as the character of the same code point like `brainfuck!`
+    pub fn run(&self, code: &str, input: &[u8]) -> Result<String, Violation> {
+        let mut interpreter = Interpreter::builder()
+            .tape_size(self.memory)
+            .max_steps(self.fuel)
+            .input(input)
+            .build()
+            .expect("the tape has at least one cell");
+        let start = Instant::now();
+        let mut violation = None;
+        // Every output byte is one character, which may take several bytes in UTF-8
+        let (mut seen, mut written) = (0, 0);
+        let result = interpreter.execute_until(code, &mut (), |interpreter, steps| {
+            let output = interpreter.output();
+            written += output[seen..].chars().count();
+            seen = output.len();
+            if written > self.max_output {
+                violation = Some(Violation::OutputExceeded {
+                    max_output: self.max_output,
+                });
+            } else if let Some(deadline) = self.deadline {
+                if steps % DEADLINE_INTERVAL == 0 && start.elapsed() > deadline {
+                    violation = Some(Violation::DeadlineExceeded { deadline, steps });
+                }
+            }
+            violation.is_some()
+        });
+        match result {
+            Ok(Some(output)) if output.chars().count() > self.max_output => {
+                Err(Violation::OutputExceeded {
+                    max_output: self.max_output,
+                })
+            }
+            Ok(Some(output)) => Ok(output),
+            Ok(None) => Err(violation.expect("the run was stopped by a violation")),
+            Err(error) => Err(match error.kind {
+                BrainfuckError::MaxStepsExceeded(fuel) => Violation::FuelExhausted { fuel },
+                BrainfuckError::PointerOverflow(memory) => Violation::MemoryExceeded {
+                    memory,
+                    position: error.position,
+                },
+                _ => Violation::Error(error),
+            }),
+        }
+    }
+}
+
+/// The reason a [`Sandbox`] ended a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The program took more steps than its fuel
+    FuelExhausted {
+        /// The fuel of the sandbox
+        fuel: usize,
+    },
+    /// The program moved the pointer beyond the last cell of the tape
+    MemoryExceeded {
+        /// The number of cells of the tape
+        memory: usize,
+        /// Position in the code (in characters) of the instruction that moved the pointer
+        position: usize,
+    },
+    /// The program produced more output than allowed
+    OutputExceeded {
+        /// The maximum length of the output in bytes
+        max_output: usize,
+    },
+    /// The program ran longer than the deadline
+    DeadlineExceeded {
+        /// The deadline of the sandbox
+        deadline: Duration,
+        /// The steps taken until the deadline was noticed
+        steps: usize,
+    },
+    /// The program failed on its own, like moving the pointer below zero
+    Error(Error),
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::FuelExhausted { fuel } => write!(f, "Ran out of fuel after {} steps", fuel),
+            Violation::MemoryExceeded { memory, position } => write!(
+                f,
+                "Pointer moved beyond the {} cells of the tape at position {}",
+                memory, position
+            ),
+            Violation::OutputExceeded { max_output } => {
+                write!(f, "Output exceeded {} bytes", max_output)
+            }
+            Violation::DeadlineExceeded { deadline, steps } => write!(
+                f,
+                "Exceeded the deadline of {:?} after {} steps",
+                deadline, steps
+            ),
+            Violation::Error(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let sandbox = Sandbox::new().memory(3).max_output(2);
+        assert_eq!(sandbox.run("+.>+.", b"").unwrap(), "\u{1}\u{1}");
+        // Bytes of 128 and up count once, not by their length in UTF-8
+        assert_eq!(sandbox.run("-..", b"").unwrap(), "\u{ff}\u{ff}");
+        assert_eq!(
+            sandbox.run("-...>", b""),
+            Err(Violation::OutputExceeded { max_output: 2 })
+        );
+        assert_eq!(
+            sandbox.run(">>>", b""),
+            Err(Violation::MemoryExceeded {
+                memory: 3,
+                position: 2
+            })
+        );
+        // Caught at the end of the program too
+        assert_eq!(
+            sandbox.run("+...", b""),
+            Err(Violation::OutputExceeded { max_output: 2 })
+        );
+        let error = sandbox.run("<", b"").unwrap_err();
+        assert!(matches!(
+            &error,
+            Violation::Error(error) if error.kind == BrainfuckError::PointerUnderflow
+        ));
+        assert_eq!(error.to_string(), "Pointer moved below zero");
+    }
+
+    #[test]
+    fn test_deadline() {
+        let sandbox = Sandbox::new()
+            .fuel(usize::MAX)
+            .deadline(Duration::from_millis(10));
+        match sandbox.run("+[]", b"") {
+            Err(Violation::DeadlineExceeded { steps, .. }) => {
+                assert_eq!(steps % DEADLINE_INTERVAL, 0)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Proofs that programs terminate.
+//!
+//! [`step_bound`] proves that a program stops and bounds the steps it can take, for
+//! programs whose loops are all simple counted loops: loops that leave the pointer where
+//! they found it and change their counter cell by the same odd amount every iteration,
+//! without reading input into it. Cells wrap around, so such a counter reaches 0 after
+//! fewer iterations than a cell has values. Loop-free programs always stop.
+//!
+//! `brainfuck!` raises its step limit to the bound when it finds one that is not too
+//! large, so large programs that obviously stop, like the output of generators, do not
+//! fail on the limit.
+
+use std::collections::BTreeMap;
+
+use crate::machine::Interpreter;
+
+/// The most steps a program can take on an interpreter with cells of `cell_width` bits,
+/// or `None` without a proof that it stops.
+///
+/// Comments count as steps, like they do for the interpreter, and every command is taken
+/// to be one of the eight Brainfuck commands. Errors like moving the pointer off the
+/// tape end a run earlier than the bound.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::termination::step_bound;
+///
+/// assert_eq!(step_bound("+++.", 8), Some(4));
+/// // At most 255 iterations of `-`, and `[` and `]` at most 256 times each
+/// assert_eq!(step_bound("[-]", 8), Some(255 + 256 * 2));
+/// assert_eq!(step_bound("+[]", 8), None);
+/// assert_eq!(step_bound("+[>+<]", 8), None);
+/// ```
+pub fn step_bound(code: &str, cell_width: u32) -> Option<usize> {
+    let jumps = Interpreter::find_matching_brackets(code).ok()?;
+    let chars: Vec<char> = code.chars().collect();
+    let iterations = (1u128 << cell_width.min(64)) - 1;
+    let summary = summarize(&chars, &jumps, 0..chars.len(), iterations)?;
+    Some(usize::try_from(summary.steps).unwrap_or(usize::MAX))
+}
+
+/// What running a part of a program does to the cells, relative to where it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Effect {
+    /// The cell changes by this amount
+    Add(i64),
+    /// The cell changes in a way the proof does not follow
+    Unknown,
+}
+
+/// The proven behavior of a part of a program
+struct Summary {
+    /// The most steps it takes
+    steps: u128,
+    /// How far it moves the pointer
+    shift: i64,
+    /// What it does to the cells it changes, by offset from the starting cell
+    cells: BTreeMap<i64, Effect>,
+}
+
+/// Summarize the characters in `range`, in which brackets are matched, or `None` if one
+/// of its loops is not a simple counted loop
+fn summarize(
+    chars: &[char],
+    jumps: &[Option<usize>],
+    range: std::ops::Range<usize>,
+    iterations: u128,
+) -> Option<Summary> {
+    let mut summary = Summary {
+        steps: 0,
+        shift: 0,
+        cells: BTreeMap::new(),
+    };
+    let mut ip = range.start;
+    while ip < range.end {
+        let offset = summary.shift;
+        let mut add = |amount: i64| {
+            let effect = summary.cells.entry(offset).or_insert(Effect::Add(0));
+            if let Effect::Add(total) = effect {
+                *total += amount;
+            }
+        };
+        match chars[ip] {
+            '>' => summary.shift += 1,
+            '<' => summary.shift -= 1,
+            '+' => add(1),
+            '-' => add(-1),
+            ',' => {
+                summary.cells.insert(offset, Effect::Unknown);
+            }
+            '[' => {
+                let close = jumps[ip]?;
+                let body = summarize(chars, jumps, ip + 1..close, iterations)?;
+                match body.cells.get(&0) {
+                    Some(Effect::Add(amount)) if body.shift == 0 && amount % 2 != 0 => {}
+                    _ => return None,
+                }
+                // `[` and `]` once more than the iterations, the body once per iteration
+                let bound = iterations
+                    .saturating_mul(body.steps)
+                    .saturating_add((iterations + 1) * 2);
+                summary.steps = summary.steps.saturating_add(bound);
+                for cell in body.cells.keys() {
+                    summary.cells.insert(offset + cell, Effect::Unknown);
+                }
+                ip = close + 1;
+                continue;
+            }
+            _ => {}
+        }
+        summary.steps = summary.steps.saturating_add(1);
+        ip += 1;
+    }
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_bound() {
+        let nested = "-[>-[>++++++++++++++++<-]<-]>>.";
+        let bound = step_bound(nested, 8).unwrap();
+        let mut interpreter = Interpreter::builder().max_steps(bound).build().unwrap();
+        assert_eq!(interpreter.execute(nested).unwrap(), "\u{10}");
+        assert!(interpreter.steps() > crate::interpret::MAX_STEPS);
+        assert!(interpreter.steps() <= bound);
+
+        // The inner loop may change the counter of the outer loop
+        assert_eq!(step_bound("+[>+[<+>-]<-]", 8), None);
+        // Reading input into the counter
+        assert_eq!(step_bound("+[,-]", 8), None);
+        // An even step may skip 0 forever
+        assert_eq!(step_bound("+[--]", 8), None);
+        assert_eq!(
+            step_bound("+[>-<-]x,.", 16),
+            Some(65536 * 2 + 65535 * 4 + 4)
+        );
+        assert_eq!(step_bound("[", 8), None);
+    }
+}
@@ -0,0 +1,856 @@
+//! Recording and rendering of execution traces.
+//!
+//! [`ExecutionHook`] is notified of every instruction the
+//! [interpreter](crate::machine::Interpreter) executes, for profilers, visualizers and
+//! coverage tools. [`Trace`] is the hook behind `brainfuck_trace!`, [`CellStats`]
+//! counts the accesses to every cell, and [`Coverage`] counts the executions of every
+//! instruction. [`TapeTrace`] draws the tape after every step, for teaching, and
+//! [`Frames`] records the steps as JSON for visualizers.
+
+use std::collections::BTreeMap;
+
+/// One executed instruction
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+    /// Position of the instruction in the code (in characters)
+    pub position: usize,
+    /// The instruction itself
+    pub instruction: char,
+    /// Pointer after executing the instruction
+    pub pointer: usize,
+    /// Value of the cell under the pointer after executing the instruction
+    pub cell: u32,
+    /// Length in bytes of the output produced so far
+    pub output_len: usize,
+}
+
+/// Callbacks on the execution of a program by
+/// [`Interpreter::execute_with_hook`](crate::machine::Interpreter::execute_with_hook).
+///
+/// Every method does nothing by default, so a hook only implements the events it needs.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::Interpreter;
+/// use brainfuck_core::trace::{ExecutionHook, TraceStep};
+///
+/// /// Counts how often every instruction is executed
+/// struct Coverage(Vec<usize>);
+///
+/// impl ExecutionHook for Coverage {
+///     fn on_instruction(&mut self, step: &TraceStep) {
+///         self.0[step.position] += 1;
+///     }
+/// }
+///
+/// let code = "+++[>+<-]";
+/// let mut coverage = Coverage(vec![0; code.len()]);
+/// Interpreter::new().execute_with_hook(code, &mut coverage).unwrap();
+/// assert_eq!(coverage.0, [1, 1, 1, 1, 3, 3, 3, 3, 3]);
+/// ```
+pub trait ExecutionHook {
+    /// Called after every executed instruction, comments included
+    fn on_instruction(&mut self, _step: &TraceStep) {}
+
+    /// Called with the text `.` (or `:` of the `decimal` extension) appended to the
+    /// output, before [`ExecutionHook::on_instruction`] for the same instruction
+    fn on_output(&mut self, _output: &str) {}
+
+    /// Called when execution enters the loop whose `[` is at `position`, once per entry
+    /// rather than once per iteration
+    fn on_loop_enter(&mut self, _position: usize) {}
+
+    /// Called when execution leaves the loop whose `[` is at `position` through its `]`
+    fn on_loop_exit(&mut self, _position: usize) {}
+}
+
+/// The hook that ignores every event
+impl ExecutionHook for () {}
+
+/// A borrowed hook, so a hook can be lent to a combination and inspected afterwards
+impl<H: ExecutionHook + ?Sized> ExecutionHook for &mut H {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        (**self).on_instruction(step);
+    }
+
+    fn on_output(&mut self, output: &str) {
+        (**self).on_output(output);
+    }
+
+    fn on_loop_enter(&mut self, position: usize) {
+        (**self).on_loop_enter(position);
+    }
+
+    fn on_loop_exit(&mut self, position: usize) {
+        (**self).on_loop_exit(position);
+    }
+}
+
+/// Two hooks notified of every event in turn, to run several hooks at once
+impl<A: ExecutionHook, B: ExecutionHook> ExecutionHook for (A, B) {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        self.0.on_instruction(step);
+        self.1.on_instruction(step);
+    }
+
+    fn on_output(&mut self, output: &str) {
+        self.0.on_output(output);
+        self.1.on_output(output);
+    }
+
+    fn on_loop_enter(&mut self, position: usize) {
+        self.0.on_loop_enter(position);
+        self.1.on_loop_enter(position);
+    }
+
+    fn on_loop_exit(&mut self, position: usize) {
+        self.0.on_loop_exit(position);
+        self.1.on_loop_exit(position);
+    }
+}
+
+/// Trace of the first `limit` instructions of a run
+#[derive(Debug, Clone)]
+pub struct Trace {
+    limit: usize,
+    steps: Vec<TraceStep>,
+    executed: usize,
+}
+
+impl Trace {
+    /// Create a trace recording at most `limit` steps
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            steps: Vec::new(),
+            executed: 0,
+        }
+    }
+
+    /// Record an executed instruction, dropping it once the limit is reached
+    pub fn record(&mut self, step: TraceStep) {
+        self.executed += 1;
+        if self.steps.len() < self.limit {
+            self.steps.push(step);
+        }
+    }
+
+    /// Render the trace as a table. `output` is the complete output of the run and
+    /// `error` the error that ended it, if any.
+    pub fn render(&self, output: &str, error: Option<&str>) -> String {
+        let mut out = String::from(" step    ip  op    ptr  cell  output\n");
+        for (i, step) in self.steps.iter().enumerate() {
+            out.push_str(&format!(
+                "{:>5} {:>5}  {}  {:>5}  {:>4}  {:?}\n",
+                i + 1,
+                step.position,
+                step.instruction,
+                step.pointer,
+                step.cell,
+                &output[..step.output_len]
+            ));
+        }
+        if self.executed > self.steps.len() {
+            out.push_str(&format!(
+                "... {} more steps not shown\n",
+                self.executed - self.steps.len()
+            ));
+        }
+        if let Some(error) = error {
+            out.push_str(&format!("error: {}\n", error));
+        }
+        out
+    }
+}
+
+/// Records the Brainfuck instructions executed, skipping comments and the commands of
+/// extensions
+impl ExecutionHook for Trace {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        if "<>+-.,[]".contains(step.instruction) {
+            self.record(step.clone());
+        }
+    }
+}
+
+/// Trace of the first `limit` instructions of a run drawn as the tape after each step,
+/// a row of boxed cells with a caret under the pointer.
+///
+/// Only a window of [`TapeTrace::window`] cells is drawn, the one holding the pointer,
+/// and with [`TapeTrace::every`] only every n-th step. The tape is reconstructed from the
+/// values of the cells under the pointer, which is exact for the eight Brainfuck
+/// commands.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::Interpreter;
+/// use brainfuck_core::trace::TapeTrace;
+///
+/// let mut trace = TapeTrace::new(2).window(3);
+/// let mut interpreter = Interpreter::new();
+/// interpreter.execute_with_hook("+>++", &mut trace).unwrap();
+/// assert_eq!(
+///     trace.render(interpreter.output(), None),
+///     "step 1: + at 0
+///     0     1     2
+/// ┌─────┬─────┬─────┐
+/// │   1 │   0 │   0 │
+/// └─────┴─────┴─────┘
+///    ^
+/// step 2: > at 1
+///     0     1     2
+/// ┌─────┬─────┬─────┐
+/// │   1 │   0 │   0 │
+/// └─────┴─────┴─────┘
+///          ^
+/// ... 2 more steps not shown
+/// "
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TapeTrace {
+    limit: usize,
+    window: usize,
+    every: usize,
+    tape: Vec<u32>,
+    frames: Vec<TapeWindow>,
+    executed: usize,
+}
+
+/// The window of the tape after one step of a [`TapeTrace`]
+#[derive(Debug, Clone)]
+struct TapeWindow {
+    step: usize,
+    position: usize,
+    instruction: char,
+    /// The index of the first cell of the window
+    start: usize,
+    cells: Vec<u32>,
+    pointer: usize,
+}
+
+impl TapeTrace {
+    /// Create a tape trace of at most `limit` steps, drawing 8 cells of every step
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            window: 8,
+            every: 1,
+            tape: Vec::new(),
+            frames: Vec::new(),
+            executed: 0,
+        }
+    }
+
+    /// The number of cells drawn per step, at least 1 (default 8)
+    pub fn window(mut self, cells: usize) -> Self {
+        self.window = cells.max(1);
+        self
+    }
+
+    /// Draw only every n-th step, at least 1 (default 1, every step)
+    pub fn every(mut self, steps: usize) -> Self {
+        self.every = steps.max(1);
+        self
+    }
+
+    /// Render the drawn steps. `output` is the complete output of the run and `error`
+    /// the error that ended it, if any.
+    pub fn render(&self, output: &str, error: Option<&str>) -> String {
+        let mut out = String::new();
+        for frame in &self.frames {
+            let width = frame.cells.len();
+            out.push_str(&format!(
+                "step {}: {} at {}\n",
+                frame.step, frame.instruction, frame.position
+            ));
+            for cell in frame.start..frame.start + width {
+                out.push_str(&format!("{:>5} ", cell));
+            }
+            out.truncate(out.trim_end().len());
+            out.push('\n');
+            out.push_str(&format!("┌{}┐\n", vec!["─────"; width].join("┬")));
+            for value in &frame.cells {
+                out.push_str(&format!("│{:>4} ", value));
+            }
+            out.push_str("│\n");
+            out.push_str(&format!("└{}┘\n", vec!["─────"; width].join("┴")));
+            out.push_str(&format!(
+                "{}^\n",
+                " ".repeat(3 + 6 * (frame.pointer - frame.start))
+            ));
+        }
+        let shown = self.executed.min(self.limit);
+        if self.executed > shown {
+            out.push_str(&format!(
+                "... {} more steps not shown\n",
+                self.executed - shown
+            ));
+        }
+        if !output.is_empty() {
+            out.push_str(&format!("output: {:?}\n", output));
+        }
+        if let Some(error) = error {
+            out.push_str(&format!("error: {}\n", error));
+        }
+        out
+    }
+}
+
+/// Draws the Brainfuck instructions executed, skipping comments and the commands of
+/// extensions
+impl ExecutionHook for TapeTrace {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        if !is_command(step.instruction) {
+            return;
+        }
+        if step.pointer >= self.tape.len() {
+            self.tape.resize(step.pointer + 1, 0);
+        }
+        self.tape[step.pointer] = step.cell;
+        self.executed += 1;
+        if self.executed > self.limit || !(self.executed - 1).is_multiple_of(self.every) {
+            return;
+        }
+        let start = step.pointer - step.pointer % self.window;
+        let cells = (start..start + self.window)
+            .map(|cell| self.tape.get(cell).copied().unwrap_or(0))
+            .collect();
+        self.frames.push(TapeWindow {
+            step: self.executed,
+            position: step.position,
+            instruction: step.instruction,
+            start,
+            cells,
+            pointer: step.pointer,
+        });
+    }
+}
+
+/// A cell changed by a step of [`Frames`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellChange {
+    /// The position of the cell on the tape
+    pub cell: usize,
+    /// The value of the cell after the step
+    pub value: u32,
+}
+
+/// One step recorded by [`Frames`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frame {
+    /// The number of the step, starting at 1
+    pub step: usize,
+    /// Position of the instruction in the code (in characters)
+    pub ip: usize,
+    /// The instruction itself
+    pub instruction: char,
+    /// Pointer after executing the instruction
+    pub pointer: usize,
+    /// The cell the instruction changed, if any
+    pub changed: Option<CellChange>,
+    /// The text the instruction appended to the output, if any
+    pub output: Option<String>,
+}
+
+/// Frames of the first `limit` instructions of a run, for web-based visualizers that
+/// animate a run step by step without interpreting the program themselves.
+///
+/// Every frame holds the changes of one step rather than the whole tape, so a
+/// visualizer replays the frames onto a zeroed tape. With [`Frames::every`], only every
+/// n-th step is kept; the changes of the steps in between are then not recorded.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::Interpreter;
+/// use brainfuck_core::trace::Frames;
+///
+/// let mut frames = Frames::new(10);
+/// Interpreter::new().execute_with_hook("+>.", &mut frames).unwrap();
+/// assert_eq!(frames.frames()[0].changed.unwrap().value, 1);
+/// assert_eq!(
+///     frames.to_json(None),
+///     r#"{"steps":3,"frames":[
+/// {"step":1,"ip":0,"instruction":"+","pointer":0,"changed":{"cell":0,"value":1},"output":null},
+/// {"step":2,"ip":1,"instruction":">","pointer":1,"changed":null,"output":null},
+/// {"step":3,"ip":2,"instruction":".","pointer":1,"changed":null,"output":"\u0000"}
+/// ],"error":null}
+/// "#
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Frames {
+    limit: usize,
+    every: usize,
+    tape: Vec<u32>,
+    frames: Vec<Frame>,
+    output: Option<String>,
+    executed: usize,
+}
+
+impl Frames {
+    /// Create frames of at most `limit` steps
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            every: 1,
+            tape: Vec::new(),
+            frames: Vec::new(),
+            output: None,
+            executed: 0,
+        }
+    }
+
+    /// Keep only every n-th step, at least 1 (default 1, every step)
+    pub fn every(mut self, steps: usize) -> Self {
+        self.every = steps.max(1);
+        self
+    }
+
+    /// The recorded frames
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The frames as a JSON object, with the total number of steps of the run under
+    /// `steps`, one frame per line under `frames`, and `error`, the error that ended the
+    /// run or `null`
+    pub fn to_json(&self, error: Option<&str>) -> String {
+        let frames: Vec<String> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                let changed = match frame.changed {
+                    Some(change) => {
+                        format!(r#"{{"cell":{},"value":{}}}"#, change.cell, change.value)
+                    }
+                    None => "null".to_string(),
+                };
+                let output = match &frame.output {
+                    Some(output) => json_string(output),
+                    None => "null".to_string(),
+                };
+                format!(
+                    r#"{{"step":{},"ip":{},"instruction":{},"pointer":{},"changed":{},"output":{}}}"#,
+                    frame.step,
+                    frame.ip,
+                    json_string(&frame.instruction.to_string()),
+                    frame.pointer,
+                    changed,
+                    output
+                )
+            })
+            .collect();
+        format!(
+            "{{\"steps\":{},\"frames\":[\n{}\n],\"error\":{}}}\n",
+            self.executed,
+            frames.join(",\n"),
+            error.map_or_else(|| "null".to_string(), json_string)
+        )
+    }
+}
+
+/// Records the Brainfuck instructions executed, skipping comments and the commands of
+/// extensions
+impl ExecutionHook for Frames {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        if step.pointer >= self.tape.len() {
+            self.tape.resize(step.pointer + 1, 0);
+        }
+        let previous = std::mem::replace(&mut self.tape[step.pointer], step.cell);
+        let output = self.output.take();
+        if !is_command(step.instruction) {
+            return;
+        }
+        self.executed += 1;
+        if self.executed > self.limit || !(self.executed - 1).is_multiple_of(self.every) {
+            return;
+        }
+        self.frames.push(Frame {
+            step: self.executed,
+            ip: step.position,
+            instruction: step.instruction,
+            pointer: step.pointer,
+            changed: (previous != step.cell).then_some(CellChange {
+                cell: step.pointer,
+                value: step.cell,
+            }),
+            output,
+        });
+    }
+
+    fn on_output(&mut self, output: &str) {
+        self.output = Some(output.to_string());
+    }
+}
+
+/// Quote text as a JSON string
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if u32::from(ch) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(ch))),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Read and write counts of every cell of a run, for memory-layout visualizations and
+/// finding the cells a program works hardest on.
+///
+/// `.`, `[` and `]` read the cell under the pointer, while `+`, `-` and `,` write it.
+/// The commands of extensions are not counted.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::Interpreter;
+/// use brainfuck_core::trace::CellStats;
+///
+/// let mut stats = CellStats::new();
+/// Interpreter::new().execute_with_hook("++[>+++<-]>.", &mut stats).unwrap();
+/// assert_eq!(stats.reads(), [3, 1]);
+/// assert_eq!(stats.writes(), [4, 6]);
+/// assert_eq!(stats.cells_used(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellStats {
+    reads: Vec<u64>,
+    writes: Vec<u64>,
+}
+
+impl CellStats {
+    /// Create statistics without any access
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of reads of every cell, up to the last cell accessed
+    pub fn reads(&self) -> &[u64] {
+        &self.reads
+    }
+
+    /// The number of writes of every cell, up to the last cell accessed
+    pub fn writes(&self) -> &[u64] {
+        &self.writes
+    }
+
+    /// The number of cells up to the last cell accessed
+    pub fn cells_used(&self) -> usize {
+        self.reads.len()
+    }
+
+    /// Render the counts as a table, one row per cell accessed
+    pub fn render(&self) -> String {
+        let mut out = String::from(" cell  reads  writes\n");
+        for (cell, (reads, writes)) in self.reads.iter().zip(&self.writes).enumerate() {
+            if reads + writes > 0 {
+                out.push_str(&format!("{:>5}  {:>5}  {:>6}\n", cell, reads, writes));
+            }
+        }
+        out
+    }
+
+    fn count(&mut self, cell: usize, write: bool) {
+        if cell >= self.reads.len() {
+            self.reads.resize(cell + 1, 0);
+            self.writes.resize(cell + 1, 0);
+        }
+        let counts = if write {
+            &mut self.writes
+        } else {
+            &mut self.reads
+        };
+        counts[cell] += 1;
+    }
+}
+
+/// Counts the accesses of the eight Brainfuck commands; none of them move the pointer
+/// while accessing a cell, so the pointer after the step is the cell accessed
+impl ExecutionHook for CellStats {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        match step.instruction {
+            '.' | '[' | ']' => self.count(step.pointer, false),
+            '+' | '-' | ',' => self.count(step.pointer, true),
+            _ => {}
+        }
+    }
+}
+
+/// How often every instruction of a run was executed, rendered like a coverage report.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_core::machine::Interpreter;
+/// use brainfuck_core::trace::Coverage;
+///
+/// let code = "+[-]>[never]";
+/// let mut coverage = Coverage::new();
+/// Interpreter::new().execute_with_hook(code, &mut coverage).unwrap();
+/// assert_eq!(coverage.count(2), 1);
+/// assert_eq!(coverage.count(6), 0);
+/// assert_eq!(coverage.summary(code), (6, 7));
+/// let html = coverage.render_html(code, "example.b");
+/// assert!(html.contains(r#"<span class="miss">]</span>"#));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    counts: Vec<u64>,
+}
+
+impl Coverage {
+    /// Create a coverage without any execution
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of times the instruction at `position` (in characters) was executed
+    pub fn count(&self, position: usize) -> u64 {
+        self.counts.get(position).copied().unwrap_or(0)
+    }
+
+    /// The number of Brainfuck instructions of `code` executed at least once, and the
+    /// number of Brainfuck instructions in it
+    pub fn summary(&self, code: &str) -> (usize, usize) {
+        let instructions = code.chars().enumerate().filter(|(_, ch)| is_command(*ch));
+        instructions.fold((0, 0), |(covered, total), (position, _)| {
+            (covered + usize::from(self.count(position) > 0), total + 1)
+        })
+    }
+
+    /// Render `code` as a standalone HTML page titled `title`, with executed instructions
+    /// highlighted green, their counts as tooltips, and instructions never executed red.
+    /// Comments and the commands of extensions are left plain.
+    pub fn render_html(&self, code: &str, title: &str) -> String {
+        let (covered, total) = self.summary(code);
+        let percent = if total == 0 {
+            100.0
+        } else {
+            covered as f64 * 100.0 / total as f64
+        };
+        let mut out = format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Coverage of {title}</title>
+<style>
+body {{ font-family: sans-serif; }}
+pre {{ font-family: monospace; line-height: 1.4; }}
+.hit {{ background: #c8f0c8; }}
+.miss {{ background: #f8c8c8; }}
+.comment {{ color: #888; }}
+</style>
+</head>
+<body>
+<h1>Coverage of {title}</h1>
+<p>{covered} of {total} instructions executed ({percent:.1}%)</p>
+<pre>",
+            title = escape_html(title),
+        );
+        let mut comment = String::new();
+        for (position, ch) in code.chars().enumerate() {
+            if !is_command(ch) {
+                comment.push(ch);
+                continue;
+            }
+            if !comment.is_empty() {
+                let text = escape_html(&std::mem::take(&mut comment));
+                out.push_str(&format!("<span class=\"comment\">{}</span>", text));
+            }
+            match self.count(position) {
+                0 => out.push_str(&format!(
+                    "<span class=\"miss\">{}</span>",
+                    escape_html(&ch.to_string())
+                )),
+                count => out.push_str(&format!(
+                    "<span class=\"hit\" title=\"{} times\">{}</span>",
+                    count,
+                    escape_html(&ch.to_string())
+                )),
+            }
+        }
+        if !comment.is_empty() {
+            let text = escape_html(&comment);
+            out.push_str(&format!("<span class=\"comment\">{}</span>", text));
+        }
+        out.push_str("</pre>\n</body>\n</html>\n");
+        out
+    }
+
+    /// Render the counts as folded stacks for flame graph tools like `flamegraph.pl` or
+    /// `inferno-flamegraph`. Every line is a stack of loops, named `loop@<position>` by
+    /// their `[` from the outermost, under a root frame `program`, followed by the steps
+    /// executed in that loop but outside its inner loops. Brackets count towards their
+    /// own loop.
+    pub fn render_folded(&self, code: &str) -> String {
+        let mut weights: BTreeMap<Vec<usize>, u64> = BTreeMap::new();
+        let mut stack = Vec::new();
+        for (position, ch) in code.chars().enumerate() {
+            if ch == '[' {
+                stack.push(position);
+            }
+            let count = self.count(position);
+            if count > 0 {
+                *weights.entry(stack.clone()).or_default() += count;
+            }
+            if ch == ']' {
+                stack.pop();
+            }
+        }
+        let mut out = String::new();
+        for (stack, weight) in weights {
+            out.push_str("program");
+            for position in stack {
+                out.push_str(&format!(";loop@{}", position));
+            }
+            out.push_str(&format!(" {}\n", weight));
+        }
+        out
+    }
+}
+
+/// Counts every executed instruction, comments included
+impl ExecutionHook for Coverage {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        if step.position >= self.counts.len() {
+            self.counts.resize(step.position + 1, 0);
+        }
+        self.counts[step.position] += 1;
+    }
+}
+
+/// Whether a character is one of the eight Brainfuck commands
+fn is_command(ch: char) -> bool {
+    "<>+-.,[]".contains(ch)
+}
+
+/// Escape the characters of text that are special in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_truncated() {
+        let mut trace = Trace::new(2);
+        for (position, instruction, cell, output_len) in
+            [(0, '+', 1, 0), (1, '.', 1, 1), (2, '.', 1, 2)]
+        {
+            trace.record(TraceStep {
+                position,
+                instruction,
+                pointer: 0,
+                cell,
+                output_len,
+            });
+        }
+        let expected = " step    ip  op    ptr  cell  output
+    1     0  +      0     1  \"\"
+    2     1  .      0     1  \"\\u{1}\"
+... 1 more steps not shown
+";
+        assert_eq!(trace.render("\u{1}\u{1}", None), expected);
+    }
+
+    #[test]
+    fn test_render_error() {
+        let trace = Trace::new(10);
+        assert!(trace
+            .render("", Some("Pointer moved below zero"))
+            .ends_with("error: Pointer moved below zero\n"));
+    }
+
+    #[test]
+    fn test_cell_stats() {
+        let mut stats = CellStats::new();
+        for (instruction, pointer) in [('+', 0), ('>', 1), (',', 2), ('.', 2), ('#', 3)] {
+            stats.on_instruction(&TraceStep {
+                position: 0,
+                instruction,
+                pointer,
+                cell: 0,
+                output_len: 0,
+            });
+        }
+        assert_eq!(stats.reads(), [0, 0, 1]);
+        assert_eq!(stats.writes(), [1, 0, 1]);
+        assert_eq!(
+            stats.render(),
+            " cell  reads  writes\n    0      0       1\n    2      1       1\n"
+        );
+    }
+
+    #[test]
+    fn test_coverage_html() {
+        let code = "+>[<]x&";
+        let mut coverage = Coverage::new();
+        for position in [0, 1, 1, 2] {
+            coverage.on_instruction(&TraceStep {
+                position,
+                instruction: '+',
+                pointer: 0,
+                cell: 0,
+                output_len: 0,
+            });
+        }
+        assert_eq!(coverage.summary(code), (3, 5));
+        let html = coverage.render_html(code, "<test>");
+        assert!(html.contains("<title>Coverage of &lt;test&gt;</title>"));
+        assert!(html.contains("<p>3 of 5 instructions executed (60.0%)</p>"));
+        let expected = concat!(
+            r#"<pre><span class="hit" title="1 times">+</span>"#,
+            r#"<span class="hit" title="2 times">&gt;</span>"#,
+            r#"<span class="hit" title="1 times">[</span>"#,
+            r#"<span class="miss">&lt;</span>"#,
+            r#"<span class="miss">]</span><span class="comment">x&amp;</span></pre>"#
+        );
+        assert!(html.contains(expected), "{}", html);
+    }
+
+    #[test]
+    fn test_coverage_folded() {
+        let code = "++[>++[>+<-]<-]x.";
+        let mut coverage = Coverage::new();
+        crate::machine::Interpreter::new()
+            .execute_with_hook(code, &mut coverage)
+            .unwrap();
+        let expected = "program 4\nprogram;loop@2 13\nprogram;loop@2;loop@6 22\n";
+        assert_eq!(coverage.render_folded(code), expected);
+        assert_eq!(Coverage::new().render_folded(code), "");
+    }
+
+    #[test]
+    fn test_frames_sampled() {
+        let mut frames = Frames::new(5).every(2);
+        for (position, instruction, cell) in [(0, '+', 1), (1, 'x', 1), (2, '+', 2), (3, '+', 3)] {
+            frames.on_instruction(&TraceStep {
+                position,
+                instruction,
+                pointer: 0,
+                cell,
+                output_len: 0,
+            });
+        }
+        let steps: Vec<usize> = frames.frames().iter().map(|frame| frame.step).collect();
+        assert_eq!(steps, [1, 3]);
+        assert_eq!(json_string("a\"\\\n\u{1}"), r#""a\"\\\n\u0001""#);
+    }
+}
@@ -0,0 +1,517 @@
+//! Rust code generation backend.
+//!
+//! Turns IR into the source text of a standalone Rust function, either with the
+//! signature `fn(input: &[u8]) -> Vec<u8>`, as a `no_std` function running on a tape
+//! provided by the caller, as an `async fn` over an asynchronous reader and writer, or as
+//! a `fn main()` using stdin and stdout.
+//! Pointer moves are bounds checked by the tape indexing, so a misbehaving program
+//! panics instead of causing UB, and the generated source has no `unsafe` code for crates
+//! forbidding it. [`to_rust_unchecked`] opts into leaving the checks out for speed when
+//! [`pointer_range`] proves that the pointer stays on the tape.
+//!
+//! Apart from `fn main()` and the `async fn`, the generated functions use neither
+//! `std::io` nor a tape on the stack, so they compile for `wasm32-unknown-unknown`,
+//! where [`to_rust_wasm_bindgen`] also exports them to JavaScript.
+
+use crate::interpret::TAPE_SIZE;
+use crate::ir::{pointer_range, uses_input, uses_output, Op};
+
+/// Where the generated code reads input from and writes output to
+#[derive(Clone, Copy)]
+enum Io {
+    /// An `input` byte iterator and an `output` vector
+    Buffer,
+    /// Locked stdin bytes and a buffered stdout writer
+    Stdio,
+    /// An `input` byte iterator and an `output` callback
+    Callback,
+    /// An `AsyncRead` input and an `AsyncWrite` output, yielding to the executor after
+    /// the given number of steps
+    Async(usize),
+}
+
+/// How the generated code indexes the tape
+#[derive(Clone, Copy)]
+enum Access {
+    /// Indexing with bounds checks
+    Checked,
+    /// `get_unchecked`, for programs proven to keep the pointer on the tape
+    Unchecked,
+}
+
+impl Access {
+    /// The access for a program running on a tape of [`TAPE_SIZE`] cells
+    fn of(ops: &[Op]) -> Self {
+        match pointer_range(ops) {
+            Some(range) if *range.start() >= 0 && *range.end() < TAPE_SIZE as isize => {
+                Access::Unchecked
+            }
+            _ => Access::Checked,
+        }
+    }
+
+    /// An expression reading the cell at `offset` from the pointer
+    fn read(self, offset: isize) -> String {
+        match self {
+            Access::Checked => format!("tape[{}]", index(offset)),
+            Access::Unchecked => format!("unsafe {{ *tape.get_unchecked({}) }}", index(offset)),
+        }
+    }
+
+    /// A statement storing `value` in the cell at `offset` from the pointer, where `value`
+    /// is given an expression reading the cell
+    fn write(self, offset: isize, value: impl FnOnce(&str) -> String) -> String {
+        let index = index(offset);
+        match self {
+            Access::Checked => {
+                let cell = format!("tape[{}]", index);
+                format!("{} = {};", cell, value(&cell))
+            }
+            Access::Unchecked => format!(
+                "unsafe {{ *tape.get_unchecked_mut({}) = {} }};",
+                index,
+                value(&format!("(*tape.get_unchecked({}))", index))
+            ),
+        }
+    }
+}
+
+/// The index of the cell at `offset` from the pointer
+fn index(offset: isize) -> String {
+    match offset {
+        0 => "ptr".to_string(),
+        offset if offset < 0 => format!("ptr - {}", -offset),
+        offset => format!("ptr + {}", offset),
+    }
+}
+
+/// Generate the source of a Rust function called `name` that runs the program
+pub fn to_rust(name: &str, ops: &[Op]) -> String {
+    function(name, ops, Access::Checked)
+}
+
+/// Generate the source of a Rust function like [`to_rust`], indexing the tape without
+/// bounds checks in `unsafe` blocks if [`pointer_range`] proves the pointer stays on it.
+///
+/// Crates with `#![forbid(unsafe_code)]` cannot include the source of such programs.
+pub fn to_rust_unchecked(name: &str, ops: &[Op]) -> String {
+    function(name, ops, Access::of(ops))
+}
+
+/// The source of [`to_rust`] with the given tape access
+fn function(name: &str, ops: &[Op], access: Access) -> String {
+    let input = if uses_input(ops) { "input" } else { "_input" };
+
+    let mut out = String::new();
+    out.push_str("#[allow(unused_mut)]\n");
+    out.push_str(&format!(
+        "pub fn {}({}: &[u8]) -> Vec<u8> {{\n",
+        name, input
+    ));
+    out.push_str(&format!("    let mut tape = vec![0u8; {}];\n", TAPE_SIZE));
+    out.push_str("    let mut ptr: usize = 0;\n");
+    if input == "input" {
+        out.push_str("    let mut input = input.iter().copied();\n");
+    }
+    out.push_str("    let mut output = Vec::new();\n");
+    emit_ops(&mut out, ops, 1, Io::Buffer, access);
+    out.push_str("    output\n");
+    out.push_str("}\n");
+    out
+}
+
+/// Generate the source of a Rust function called `name` that runs the program without
+/// `std` or an allocator, with the signature
+/// `fn(tape: &mut [u8], input: &[u8], output: impl FnMut(u8))`.
+///
+/// The program runs on the tape as it is passed, and every output byte is passed to
+/// `output`. Moving the pointer off the tape panics.
+pub fn to_rust_no_std(name: &str, ops: &[Op]) -> String {
+    let input = if uses_input(ops) { "input" } else { "_input" };
+    let output = if uses_output(ops) {
+        "output"
+    } else {
+        "_output"
+    };
+
+    let mut out = String::new();
+    out.push_str("#[allow(unused_mut)]\n");
+    out.push_str(&format!(
+        "pub fn {}(tape: &mut [u8], {}: &[u8], mut {}: impl FnMut(u8)) {{\n",
+        name, input, output
+    ));
+    out.push_str("    let mut ptr: usize = 0;\n");
+    if input == "input" {
+        out.push_str("    let mut input = input.iter().copied();\n");
+    }
+    emit_ops(&mut out, ops, 1, Io::Callback, Access::Checked);
+    out.push_str("}\n");
+    out
+}
+
+/// Generate the source of a Rust function like [`to_rust`], exported to JavaScript with
+/// `#[wasm_bindgen]`, for web playgrounds. The crate including it needs `wasm-bindgen` as a
+/// dependency; in JavaScript, the function takes and returns a `Uint8Array`.
+pub fn to_rust_wasm_bindgen(name: &str, ops: &[Op]) -> String {
+    format!(
+        "#[wasm_bindgen::prelude::wasm_bindgen]\n{}",
+        to_rust(name, ops)
+    )
+}
+
+/// Generate the source of an `async fn` called `name` that runs the program over an
+/// `input` implementing `futures_io::AsyncRead` and an `output` implementing
+/// `futures_io::AsyncWrite`, returning `std::io::Result<()>`. The crate including it needs
+/// `futures-io` as a dependency.
+///
+/// Every loop iteration counts the operations of the loop body, plus one for the loop
+/// test, as steps, and the function yields to the executor whenever `yield_every` steps
+/// have passed, so a long-running program does not block other tasks. Output is flushed
+/// before every read and at the end. End of input stores 0.
+pub fn to_rust_async(name: &str, ops: &[Op], yield_every: usize) -> String {
+    let mut out = String::new();
+    out.push_str("#[allow(unused_mut, unused_imports, unused_variables)]\n");
+    out.push_str(&format!(
+        "pub async fn {}(\n    mut input: impl futures_io::AsyncRead + Unpin,\n    mut output: impl futures_io::AsyncWrite + Unpin,\n) -> std::io::Result<()> {{\n",
+        name
+    ));
+    out.push_str(ASYNC_HELPERS);
+    out.push_str(&format!("    let mut tape = vec![0u8; {}];\n", TAPE_SIZE));
+    out.push_str("    let mut ptr: usize = 0;\n");
+    out.push_str("    let mut steps: usize = 0;\n");
+    emit_ops(&mut out, ops, 1, Io::Async(yield_every), Access::Checked);
+    out.push_str("    flush(&mut output).await\n");
+    out.push_str("}\n");
+    out
+}
+
+/// The helper functions of [`to_rust_async`], needing nothing but `futures-io`
+const ASYNC_HELPERS: &str = "    use futures_io::{AsyncRead, AsyncWrite};
+    use std::future::poll_fn;
+    use std::pin::Pin;
+    use std::task::Poll;
+    async fn read_byte(input: &mut (impl AsyncRead + Unpin)) -> std::io::Result<u8> {
+        let mut byte = [0u8];
+        loop {
+            match poll_fn(|cx| Pin::new(&mut *input).poll_read(cx, &mut byte)).await {
+                Ok(0) => return Ok(0),
+                Ok(_) => return Ok(byte[0]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    async fn write_byte(output: &mut (impl AsyncWrite + Unpin), byte: u8) -> std::io::Result<()> {
+        loop {
+            match poll_fn(|cx| Pin::new(&mut *output).poll_write(cx, &[byte])).await {
+                Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    async fn flush(output: &mut (impl AsyncWrite + Unpin)) -> std::io::Result<()> {
+        poll_fn(|cx| Pin::new(&mut *output).poll_flush(cx)).await
+    }
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+";
+
+/// Generate the source of a `fn main()` that runs the program against stdin and stdout.
+///
+/// Output is buffered and flushed before every read, so interactive programs show their
+/// prompts. End of input stores 0.
+pub fn to_rust_main(ops: &[Op]) -> String {
+    let mut out = String::new();
+    out.push_str("#[allow(unused_mut, unused_imports)]\n");
+    out.push_str("fn main() {\n");
+    out.push_str("    use std::io::{Read, Write};\n");
+    out.push_str(&format!("    let mut tape = vec![0u8; {}];\n", TAPE_SIZE));
+    out.push_str("    let mut ptr: usize = 0;\n");
+    if uses_input(ops) {
+        out.push_str("    let mut input = std::io::stdin().lock().bytes();\n");
+    }
+    out.push_str("    let mut output = std::io::BufWriter::new(std::io::stdout().lock());\n");
+    emit_ops(&mut out, ops, 1, Io::Stdio, Access::Checked);
+    out.push_str("    output.flush().expect(\"failed to write to stdout\");\n");
+    out.push_str("}\n");
+    out
+}
+
+/// Emit a sequence of operations at the given indentation depth
+fn emit_ops(out: &mut String, ops: &[Op], depth: usize, io: Io, access: Access) {
+    for op in ops {
+        let indent = "    ".repeat(depth);
+        match op {
+            Op::Add(n) if *n > 128 => {
+                let n = n.wrapping_neg();
+                let statement = access.write(0, |cell| format!("{}.wrapping_sub({})", cell, n));
+                out.push_str(&format!("{}{}\n", indent, statement));
+            }
+            Op::Add(n) => {
+                let statement = access.write(0, |cell| format!("{}.wrapping_add({})", cell, n));
+                out.push_str(&format!("{}{}\n", indent, statement));
+            }
+            Op::Move(n) if *n < 0 => {
+                out.push_str(&format!("{}ptr -= {};\n", indent, -n));
+            }
+            Op::Move(n) => {
+                out.push_str(&format!("{}ptr += {};\n", indent, n));
+            }
+            Op::Output => match io {
+                Io::Callback => {
+                    out.push_str(&format!("{}output({});\n", indent, access.read(0)));
+                }
+                Io::Buffer => {
+                    out.push_str(&format!("{}output.push({});\n", indent, access.read(0)));
+                }
+                Io::Stdio => {
+                    out.push_str(&format!(
+                        "{}output.write_all(&[{}]).expect(\"failed to write to stdout\");\n",
+                        indent,
+                        access.read(0)
+                    ));
+                }
+                Io::Async(_) => {
+                    out.push_str(&format!(
+                        "{}write_byte(&mut output, {}).await?;\n",
+                        indent,
+                        access.read(0)
+                    ));
+                }
+            },
+            Op::Input => match io {
+                Io::Buffer | Io::Callback => {
+                    let statement = access.write(0, |_| "input.next().unwrap_or(0)".to_string());
+                    out.push_str(&format!("{}{}\n", indent, statement));
+                }
+                Io::Stdio => {
+                    out.push_str(&format!(
+                        "{}output.flush().expect(\"failed to write to stdout\");\n",
+                        indent
+                    ));
+                    let statement = access.write(0, |_| {
+                        "input.next().and_then(Result::ok).unwrap_or(0)".to_string()
+                    });
+                    out.push_str(&format!("{}{}\n", indent, statement));
+                }
+                Io::Async(_) => {
+                    out.push_str(&format!("{}flush(&mut output).await?;\n", indent));
+                    let statement = access.write(0, |_| "read_byte(&mut input).await?".to_string());
+                    out.push_str(&format!("{}{}\n", indent, statement));
+                }
+            },
+            Op::Loop(body) => {
+                out.push_str(&format!("{}while {} != 0 {{\n", indent, access.read(0)));
+                if let Io::Async(yield_every) = io {
+                    out.push_str(&format!("{}    steps += {};\n", indent, body.len() + 1));
+                    out.push_str(&format!(
+                        "{}    if steps >= {} {{\n{}        steps = 0;\n{}        yield_now().await;\n{}    }}\n",
+                        indent, yield_every, indent, indent, indent
+                    ));
+                }
+                emit_ops(out, body, depth + 1, io, access);
+                out.push_str(&format!("{}}}\n", indent));
+            }
+            Op::Clear => {
+                out.push_str(&format!(
+                    "{}{}\n",
+                    indent,
+                    access.write(0, |_| "0".to_string())
+                ));
+            }
+            Op::MulLoop(terms) => {
+                out.push_str(&format!("{}if {} != 0 {{\n", indent, access.read(0)));
+                out.push_str(&format!("{}    let value = {};\n", indent, access.read(0)));
+                for &(offset, factor) in terms {
+                    let statement = access.write(offset, |cell| {
+                        format!("{}.wrapping_add(value.wrapping_mul({}))", cell, factor)
+                    });
+                    out.push_str(&format!("{}    {}\n", indent, statement));
+                }
+                out.push_str(&format!(
+                    "{}    {}\n",
+                    indent,
+                    access.write(0, |_| "0".to_string())
+                ));
+                out.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::parse;
+    use crate::optimize::optimize;
+
+    #[test]
+    fn test_simple_program() {
+        let ops = parse("+++[>+<-]>.").unwrap();
+        let expected = "\
+#[allow(unused_mut)]
+pub fn run(_input: &[u8]) -> Vec<u8> {
+    let mut tape = vec![0u8; 30000];
+    let mut ptr: usize = 0;
+    let mut output = Vec::new();
+    tape[ptr] = tape[ptr].wrapping_add(3);
+    while tape[ptr] != 0 {
+        ptr += 1;
+        tape[ptr] = tape[ptr].wrapping_add(1);
+        ptr -= 1;
+        tape[ptr] = tape[ptr].wrapping_sub(1);
+    }
+    ptr += 1;
+    output.push(tape[ptr]);
+    output
+}
+";
+        assert_eq!(to_rust("run", &ops), expected);
+    }
+
+    #[test]
+    fn test_main_program() {
+        let ops = parse(",[.,]").unwrap();
+        let expected = "\
+#[allow(unused_mut, unused_imports)]
+fn main() {
+    use std::io::{Read, Write};
+    let mut tape = vec![0u8; 30000];
+    let mut ptr: usize = 0;
+    let mut input = std::io::stdin().lock().bytes();
+    let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+    output.flush().expect(\"failed to write to stdout\");
+    tape[ptr] = input.next().and_then(Result::ok).unwrap_or(0);
+    while tape[ptr] != 0 {
+        output.write_all(&[tape[ptr]]).expect(\"failed to write to stdout\");
+        output.flush().expect(\"failed to write to stdout\");
+        tape[ptr] = input.next().and_then(Result::ok).unwrap_or(0);
+    }
+    output.flush().expect(\"failed to write to stdout\");
+}
+";
+        assert_eq!(to_rust_main(&ops), expected);
+    }
+
+    #[test]
+    fn test_optimized_loops() {
+        let ops = optimize(parse("[-]+++[->++<<+>]").unwrap());
+        let source = to_rust("run", &ops);
+        let expected = "    tape[ptr] = 0;
+    tape[ptr] = tape[ptr].wrapping_add(3);
+    if tape[ptr] != 0 {
+        let value = tape[ptr];
+        tape[ptr + 1] = tape[ptr + 1].wrapping_add(value.wrapping_mul(2));
+        tape[ptr - 1] = tape[ptr - 1].wrapping_add(value.wrapping_mul(1));
+        tape[ptr] = 0;
+    }
+";
+        assert!(source.contains(expected), "{}", source);
+    }
+
+    #[test]
+    fn test_unchecked_program() {
+        let ops = parse("+++[>+<-]>.").unwrap();
+        let source = to_rust_unchecked("run", &ops);
+        let expected = "    unsafe { *tape.get_unchecked_mut(ptr) = (*tape.get_unchecked(ptr)).wrapping_add(3) };
+    while unsafe { *tape.get_unchecked(ptr) } != 0 {
+        ptr += 1;
+        unsafe { *tape.get_unchecked_mut(ptr) = (*tape.get_unchecked(ptr)).wrapping_add(1) };
+        ptr -= 1;
+        unsafe { *tape.get_unchecked_mut(ptr) = (*tape.get_unchecked(ptr)).wrapping_sub(1) };
+    }
+    ptr += 1;
+    output.push(unsafe { *tape.get_unchecked(ptr) });
+";
+        assert!(source.contains(expected), "{}", source);
+        assert!(!to_rust("run", &ops).contains("unsafe"));
+    }
+
+    #[test]
+    fn test_unbounded_pointer() {
+        // Every iteration moves the pointer further
+        let source = to_rust_unchecked("run", &parse("+[>+]").unwrap());
+        assert!(source.contains("        tape[ptr] = tape[ptr].wrapping_add(1);\n"));
+        assert!(!source.contains("unsafe"));
+        // One cell past the end of the tape
+        let code = format!("{}+", ">".repeat(TAPE_SIZE));
+        let source = to_rust_unchecked("run", &parse(&code).unwrap());
+        assert!(!source.contains("unsafe"));
+        let code = format!("{}+", ">".repeat(TAPE_SIZE - 1));
+        assert!(to_rust_unchecked("run", &parse(&code).unwrap()).contains("unsafe"));
+    }
+
+    #[test]
+    fn test_input_binding() {
+        let source = to_rust("echo", &parse(",.").unwrap());
+        assert!(source.contains("pub fn echo(input: &[u8]) -> Vec<u8> {"));
+        assert!(source.contains("let mut input = input.iter().copied();"));
+        assert!(source.contains("tape[ptr] = input.next().unwrap_or(0);"));
+    }
+
+    #[test]
+    fn test_async_program() {
+        let ops = parse(",[.,]").unwrap();
+        let source = to_rust_async("echo", &ops, 100);
+        assert!(source.starts_with(
+            "#[allow(unused_mut, unused_imports, unused_variables)]\npub async fn echo(\n"
+        ));
+        let body = "    while tape[ptr] != 0 {
+        steps += 3;
+        if steps >= 100 {
+            steps = 0;
+            yield_now().await;
+        }
+        write_byte(&mut output, tape[ptr]).await?;
+        flush(&mut output).await?;
+        tape[ptr] = read_byte(&mut input).await?;
+    }
+    flush(&mut output).await
+}
+";
+        assert!(source.ends_with(body));
+    }
+
+    #[test]
+    fn test_wasm_bindgen_program() {
+        let ops = parse(",.").unwrap();
+        let source = to_rust_wasm_bindgen("echo", &ops);
+        assert!(source.starts_with(
+            "#[wasm_bindgen::prelude::wasm_bindgen]\n#[allow(unused_mut)]\npub fn echo(input: &[u8]) -> Vec<u8> {"
+        ));
+        assert!(!source.contains("std::io"));
+    }
+
+    #[test]
+    fn test_no_std_program() {
+        let ops = parse(",[+.,]").unwrap();
+        let expected = "\
+#[allow(unused_mut)]
+pub fn run(tape: &mut [u8], input: &[u8], mut output: impl FnMut(u8)) {
+    let mut ptr: usize = 0;
+    let mut input = input.iter().copied();
+    tape[ptr] = input.next().unwrap_or(0);
+    while tape[ptr] != 0 {
+        tape[ptr] = tape[ptr].wrapping_add(1);
+        output(tape[ptr]);
+        tape[ptr] = input.next().unwrap_or(0);
+    }
+}
+";
+        assert_eq!(to_rust_no_std("run", &ops), expected);
+        let source = to_rust_no_std("run", &parse("+").unwrap());
+        assert!(source.contains("(tape: &mut [u8], _input: &[u8], mut _output: impl FnMut(u8))"));
+    }
+}
@@ -0,0 +1,12 @@
+//! Benchmarks of the transpiled example programs, run with `cargo bench`.
+
+use brainfuck_macro::brainfuck_bench;
+use criterion::{criterion_group, criterion_main};
+
+brainfuck_bench! {
+    hello: { code: include_str!("../programs/hello.b") },
+    shout: { code: include_str!("../programs/shout.b"), input: "the quick brown fox" },
+}
+
+criterion_group!(benches, hello, shout);
+criterion_main!(benches);
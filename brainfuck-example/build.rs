@@ -0,0 +1,26 @@
+//! Generates a program at build time and hands it to `brainfuck!(env = "BF_PROGRAM")`,
+//! as a build pipeline would, without writing it to a file. Also transpiles
+//! `programs/hello.b` into `$OUT_DIR/hello.rs`, and into a `no_std` function in
+//! `$OUT_DIR/hello_no_std.rs`, with the build helpers of `brainfuck-core`.
+
+use brainfuck_core::build::{compile_bf_to_rust, Options};
+
+fn main() {
+    let program = format!("{}.", "+".repeat(65));
+    println!("cargo:rustc-env=BF_PROGRAM={}", program);
+
+    let options = Options {
+        name: "hello".to_string(),
+        ..Options::default()
+    };
+    let source = compile_bf_to_rust("programs/hello.b", &options).unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(format!("{}/hello.rs", out_dir), source).unwrap();
+    let options = Options {
+        no_std: true,
+        ..options
+    };
+    let source = compile_bf_to_rust("programs/hello.b", &options).unwrap();
+    std::fs::write(format!("{}/hello_no_std.rs", out_dir), source).unwrap();
+    println!("cargo:rerun-if-changed=programs/hello.b");
+}
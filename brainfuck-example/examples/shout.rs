@@ -0,0 +1,5 @@
+//! A standalone binary generated from a Brainfuck program with brainfuck_main!
+//!
+//! Run with: echo hello | cargo run --example shout
+
+brainfuck_macro::brainfuck_main!(include_str!("../programs/shout.b"));
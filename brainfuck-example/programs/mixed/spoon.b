@@ -0,0 +1 @@
+11111001000101111111111111011000001101011001010
@@ -0,0 +1,5 @@
+Echo the input back in uppercase by subtracting 32 from each byte
+,[
+    --------------------------------.
+    ,
+]
@@ -1,6 +1,15 @@
 //! Integration tests for the brainfuck! macro
 
-use brainfuck_macro::brainfuck;
+use brainfuck_macro::{
+    alphuck, bf, blub, boolfuck, brainfuck, brainfuck_assert, brainfuck_async_fn, brainfuck_cfg,
+    brainfuck_cfg_match, brainfuck_check, brainfuck_const, brainfuck_define, brainfuck_difftest,
+    brainfuck_encode, brainfuck_eq, brainfuck_exercise, brainfuck_expand, brainfuck_extern_fn,
+    brainfuck_file, brainfuck_fmt, brainfuck_golf, brainfuck_image, brainfuck_ir, brainfuck_item,
+    brainfuck_iter, brainfuck_lazy, brainfuck_metrics, brainfuck_minify, brainfuck_mutants,
+    brainfuck_optimize, brainfuck_random, brainfuck_report, brainfuck_tbs, brainfuck_testcases,
+    brainfuck_to_c, brainfuck_to_ook, brainfuck_to_wat, brainfuck_trace, brainfuck_transpile, cow,
+    ook, pbrain, spoon, text_to_brainfuck,
+};
 
 #[test]
 fn test_hello_world() {
@@ -74,7 +83,7 @@ fn test_whitespace_handling() {
     assert_eq!(result, "A");
 }
 
-#[test] 
+#[test]
 fn test_sequential_output() {
     let result = brainfuck!("+++++[>+++++++++++++<-]>.+.+.+.+.");
     assert_eq!(result, "ABCDE");
@@ -85,3 +94,1106 @@ fn test_calculation() {
     let result = brainfuck!("+++++[>+++++++++++++<-]>.");
     assert_eq!(result, "A");
 }
+
+#[test]
+fn test_transpile_emits_function() {
+    let source = brainfuck_transpile!("+++++[>+++++++++++++<-]>.");
+    assert!(source.contains("pub fn run(_input: &[u8]) -> Vec<u8> {"));
+    assert!(source.contains("tape[ptr + 1] = tape[ptr + 1].wrapping_add(value.wrapping_mul(13));"));
+    assert!(source.contains("output.push(tape[ptr]);"));
+    assert!(!source.contains("unsafe"));
+}
+
+#[test]
+fn test_transpile_unchecked() {
+    // The pointer provably stays on the tape, so the tape is indexed unchecked
+    let source = brainfuck_transpile!("+++++[>+++++++++++++<-]>.", unchecked = true);
+    assert!(source.contains(
+        "unsafe { *tape.get_unchecked_mut(ptr + 1) = (*tape.get_unchecked(ptr + 1)).wrapping_add(value.wrapping_mul(13)) };"
+    ));
+    assert!(source.contains("output.push(unsafe { *tape.get_unchecked(ptr) });"));
+    let source = brainfuck_transpile!("+[>+]", unchecked = true);
+    assert!(!source.contains("unsafe"));
+}
+
+#[test]
+fn test_transpile_with_input() {
+    let source = brainfuck_transpile!(",[.,]");
+    assert!(source.contains("pub fn run(input: &[u8]) -> Vec<u8> {"));
+    assert!(source.contains("tape[ptr] = input.next().unwrap_or(0);"));
+}
+
+#[test]
+fn test_transpile_wasm_bindgen() {
+    let source = brainfuck_transpile!(",[.,]", wasm_bindgen = true);
+    assert!(source.starts_with("#[wasm_bindgen::prelude::wasm_bindgen]\n"));
+    assert!(source.contains("pub fn run(input: &[u8]) -> Vec<u8> {"));
+}
+
+#[test]
+fn test_transpile_to_c() {
+    let source = brainfuck_to_c!("+++++[>+++++++++++++<-]>.", name = "print_a");
+    assert!(source.starts_with("#include <stdio.h>\n"));
+    assert!(source.contains("void print_a(FILE *input, FILE *output) {"));
+    assert!(source.contains("tape[ptr + 1] += tape[ptr] * 13;"));
+    assert!(!source.contains("int main(void)"));
+}
+
+#[test]
+fn test_transpile_to_wat() {
+    let wat = brainfuck_to_wat!(",[.,]");
+    assert!(wat.contains("(import \"env\" \"getc\" (func $getc (result i32)))"));
+    assert!(wat.contains("(memory (export \"memory\") 1)"));
+    assert!(wat.contains("(call $putc (i32.load8_u (local.get $ptr)))"));
+}
+
+#[test]
+fn test_encode_dialects() {
+    assert_eq!(
+        brainfuck_to_ook!("Comment +[-]"),
+        "Ook. Ook. Ook! Ook? Ook! Ook! Ook? Ook!"
+    );
+    assert_eq!(
+        brainfuck_encode!("+[-]", dialect = "ook"),
+        brainfuck_to_ook!("+[-]")
+    );
+    assert_eq!(
+        brainfuck_encode!(">.<,", dialect = "pikalang"),
+        "pipi pikachu pichu pikapi"
+    );
+}
+
+#[test]
+fn test_ook_program() {
+    let a = ook!(
+        "Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook! Ook? Ook. Ook? Ook. Ook.
+         Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook.
+         Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook? Ook. Ook! Ook! Ook? Ook! Ook. Ook?
+         Ook! Ook."
+    );
+    assert_eq!(a, "A");
+}
+
+#[test]
+fn test_tbs_program() {
+    // Multi-character tokens; everything else is a comment
+    let hi = brainfuck_tbs!(
+        "plus (right plus left minus) right out; then plus out",
+        map = {
+            ">" = "right", "<" = "left", "+" = "plus", "-" = "minus",
+            "." = "out", "," = "in", "[" = "(", "]" = ")",
+        }
+    );
+    assert_eq!(hi, "\u{1}\u{2}");
+}
+
+#[test]
+fn test_pbrain_program() {
+    // Procedure 0 prints cell 1 and increments it
+    let abc = pbrain!(concat!("(>.+<)", "++++++++[>++++++++<-]>+<", ":::"));
+    assert_eq!(abc, "ABC");
+}
+
+#[test]
+fn test_extended_program() {
+    // Stores 32, prints 'a', then flips its case by XOR with the storage byte
+    let output = brainfuck!(
+        "++++[>++++++++<-]>$ >++++++++[<++++++++>-]<+. {}^. @ Not executed.",
+        extensions = "extended"
+    );
+    assert_eq!(output, "aA");
+}
+
+#[test]
+fn test_end_program() {
+    // Without the extension, the notes after `@` would print a second character
+    let a = brainfuck!(
+        "+++++[>+++++++++++++<-]>.@ Outputs A then stops.",
+        extensions = "end"
+    );
+    assert_eq!(a, "A");
+    assert_eq!(
+        brainfuck!("+++++[>+++++++++++++<-]>.@ Outputs A then stops."),
+        "AA"
+    );
+}
+
+#[test]
+fn test_decimal_program() {
+    let answer = brainfuck!("++++++[>+++++++<-]>:", extensions = "decimal");
+    assert_eq!(answer, "42");
+    // Cells print as unsigned bytes
+    assert_eq!(brainfuck!("-:", extensions = "decimal"), "255");
+}
+
+#[test]
+fn test_bitwise_program() {
+    // Cell 1 = 32, the case bit of ASCII letters, and cell 0 = 'h'
+    let output = brainfuck!(
+        concat!(
+            ">>++++[<++++++++>-]<<",
+            ">>+++++++++++++[<<++++++++>>-]<<",
+            "^. Flip the case of 'h'",
+            "^+. Flip it back and print the next letter",
+            "&. Keep only the case bit which is a space"
+        ),
+        extensions = "bitwise"
+    );
+    assert_eq!(output, "Hi ");
+}
+
+#[test]
+fn test_tapes_program() {
+    // The letter is on the first tape and the loop counter on the second
+    let output = brainfuck!(
+        concat!(
+            "+++++++++++++[>+++++<-]> Letter A",
+            "% +++ Counter",
+            "[ % .++ % - ] Print every second letter"
+        ),
+        extensions = "tapes",
+        tapes = 2
+    );
+    assert_eq!(output, "ACE");
+}
+
+#[test]
+fn test_brainfork_program() {
+    // Cell 0 = 'A' and cell 1 = 'a'. After the fork, the parent prints from cell 0 and the
+    // child from its copy of cell 1, taking turns one instruction at a time.
+    let output = brainfuck!(
+        ">>+++++[<<+++++++++++++>>-] +++++++++[<++++++++++>-]<+++++++ Y <.+.",
+        extensions = "brainfork"
+    );
+    assert_eq!(output, "aAbB");
+}
+
+#[test]
+fn test_boolfuck_program() {
+    // "Hi": 'H' is 0b0100_1000 and 'i' is 0b0110_1001, both output from the lowest bit
+    let hi = boolfuck!(concat!(";;; +;+ ;; +;+ ;", "+;+ ;; +;+ ; +;; +;"));
+    assert_eq!(hi, "Hi");
+}
+
+#[test]
+fn test_spoon_program() {
+    // +++++[>+++++++++++++<-]>. DUMP EXIT .
+    let a = spoon!(
+        "11111 00100 010 1111111111111 011 000 0011
+         010 001010 00101110 00101111 001010"
+    );
+    assert_eq!(a, "A");
+}
+
+#[test]
+fn test_alphuck_program() {
+    // +++++[>+++++++++++++<-]>.
+    let a = alphuck!(
+        "eeeee p                  // 5 *
+             a eeeeeeeeeeeee ci   // 13
+         s aj                     // = 65"
+    );
+    assert_eq!(a, "A");
+    // Without strict mode, words are comments as long as none of their letters is a command
+    assert_eq!(alphuck!("Run: eej", strict = false), "\u{2}");
+}
+
+#[test]
+fn test_dialect_option() {
+    // ++[>+++<-]>.
+    let six = brainfuck!(
+        "..-..- --- .-- ..-..-..- --. -.. ... .-- -.-",
+        dialect = "morsefuck"
+    );
+    assert_eq!(six, "\u{6}");
+    assert_eq!(brainfuck!("Ook. Ook. Ook! Ook.", dialect = "ook"), "\u{1}");
+}
+
+#[test]
+fn test_headsecks_program() {
+    // ++[>+++<-]>. disguised as mixed-case letters
+    assert_eq!(brainfuck!("HhNkHhHjIoKl", dialect = "headsecks"), "\u{6}");
+    assert_eq!(
+        brainfuck_encode!("++[>+++<-]>.", dialect = "headsecks"),
+        "hhnkhhhjiokl"
+    );
+}
+
+#[test]
+fn test_auto_dialect() {
+    assert_eq!(brainfuck!("Ook. Ook. Ook! Ook.", dialect = "auto"), "\u{1}");
+    assert_eq!(brainfuck!("1 001010", dialect = "auto"), "\u{1}");
+    // Plain Brainfuck when no dialect fits
+    assert_eq!(brainfuck!("Prints 1: +.", dialect = "auto"), "\u{1}");
+    assert_eq!(
+        brainfuck_minify!("Ook. Ook. Ook! Ook.", dialect = "auto"),
+        "+."
+    );
+}
+
+#[test]
+fn test_dialect_option_on_other_macros() {
+    assert_eq!(
+        brainfuck_minify!("Ook. Ook. Ook. Ook. Ook! Ook.", dialect = "ook"),
+        "++."
+    );
+    // ++[>+++<-]>.
+    assert_eq!(
+        brainfuck_check!("HhNkHhHjIoKl", dialect = "headsecks"),
+        "++[>+++<-]>."
+    );
+    assert!(brainfuck_transpile!("episaj", dialect = "alphuck").contains("output.push(tape[ptr]);"));
+}
+
+#[test]
+fn test_pikalang_program() {
+    // +++[>++<-]>. with words in any case
+    let six = brainfuck!(
+        "Pi pi pi PIKA pipi pi pi pichu ka CHU pipi pikachu",
+        dialect = "pikalang"
+    );
+    assert_eq!(six, "\u{6}");
+}
+
+#[test]
+fn test_cow_program() {
+    // 8 * 8 + 1 = 'A', printed as a character and as a number
+    let a = cow!(
+        "MoO MoO MoO MoO MoO MoO MoO MoO
+         MOO moO MoO MoO MoO MoO MoO MoO MoO MoO mOo MOo moo
+         moO MoO Moo OOM"
+    );
+    assert_eq!(a, "A65\n");
+}
+
+#[test]
+fn test_blub_program() {
+    // +++[>++<-]>.
+    let six = blub!(
+        "Blub. Blub. Blub. Blub. Blub. Blub. Blub! Blub? Blub. Blub? Blub. Blub. Blub. Blub.
+         Blub? Blub. Blub! Blub! Blub? Blub! Blub. Blub? Blub! Blub."
+    );
+    assert_eq!(six, "\u{6}");
+}
+
+#[test]
+fn test_file_program() {
+    let result = brainfuck_file!("programs/hello.b");
+    assert_eq!(result, "Hello World!\n");
+}
+
+#[test]
+fn test_file_program_with_input() {
+    let shout = brainfuck_file!("programs/shout.b", input_file = "programs/shout.in");
+    assert_eq!(shout, "ABC");
+    // Same as the sibling `.in` file picked up by `brainfuck_dir!`
+    assert_eq!(shout, programs::SHOUT);
+}
+
+#[test]
+fn test_image_program() {
+    assert_eq!(brainfuck_image!("art/ab.png"), "AB");
+}
+
+mod programs {
+    brainfuck_macro::brainfuck_dir!("programs/*");
+}
+
+#[test]
+fn test_dir_programs() {
+    assert_eq!(programs::HELLO, "Hello World!\n");
+    assert_eq!(programs::ABC, "ABC");
+}
+
+mod mixed {
+    brainfuck_macro::brainfuck_dir!("programs/mixed", dialect = "auto");
+}
+
+#[test]
+fn test_dir_detects_dialects() {
+    assert_eq!(mixed::PLAIN, "A");
+    assert_eq!(mixed::OOK, "B");
+    assert_eq!(mixed::SPOON, "C");
+}
+
+#[test]
+fn test_dir_program_with_input() {
+    assert_eq!(programs::SHOUT, "ABC");
+}
+
+#[test]
+fn test_split_literals() {
+    let a = brainfuck!(
+        "+++++"
+        "[>+++++++++++++<-]"
+        ">."
+    );
+    assert_eq!(a, "A");
+    assert_eq!(brainfuck!("+++++", "[>+++++++++++++<-]", ">.",), "A");
+    assert_eq!(
+        // Literals are joined as they are, like with `concat!`
+        brainfuck!("Ook. Ook. ", "Ook! Ook.", dialect = "ook"),
+        "\u{1}"
+    );
+}
+
+#[test]
+fn test_env_program() {
+    // Set by the build script
+    assert_eq!(brainfuck!(env = "BF_PROGRAM"), "A");
+    assert_eq!(brainfuck_minify!(env = "BF_PROGRAM").len(), 66);
+}
+
+#[test]
+fn test_placeholders() {
+    let a = brainfuck!("{FIVE}[>{FIVE}{FIVE}+++<-]>.", FIVE = "+++++");
+    assert_eq!(a, "A");
+    // Filled before decoding and combined with other options
+    let one = brainfuck!("{INC} Ook! Ook.", INC = "Ook. Ook.", dialect = "ook");
+    assert_eq!(one, "\u{1}");
+    // Without placeholder values, braces are commands of the extension
+    assert_eq!(brainfuck!("+{X}.", extensions = "extended"), "\u{1}");
+}
+
+brainfuck_define!(GREETER = "Prints A: +++++[>+++++++++++++<-]>.");
+brainfuck_define!(OOK_ONE = "Ook. Ook. Ook! Ook.");
+
+#[test]
+fn test_define() {
+    assert_eq!(brainfuck!(use GREETER), "A");
+    assert_eq!(brainfuck_minify!(use GREETER), "+++++[>+++++++++++++<-]>.");
+    // Options of the invocation apply to the defined code
+    assert_eq!(brainfuck!(use OOK_ONE, dialect = "ook"), "\u{1}");
+    assert_eq!(brainfuck_check!(use OOK_ONE, dialect = "ook"), "+.");
+    const METRICS: brainfuck_core::metrics::Metrics = brainfuck_metrics!(use GREETER);
+    assert_eq!((METRICS.loops, METRICS.minified_length), (1, 25));
+}
+
+#[test]
+fn test_bf_alias() {
+    assert_eq!(bf!("+++++[>+++++++++++++<-]>."), "A");
+    assert_eq!(bf!(use GREETER), brainfuck!(use GREETER));
+    assert_eq!(bf!("Ook. Ook. Ook! Ook.", dialect = "ook"), "\u{1}");
+}
+
+/// Documented like any constant
+#[brainfuck_const]
+pub const ATTRIBUTE_A: &str = "Prints A: +++++[>+++++++++++++<-]>.";
+
+#[brainfuck_const(dialect = "ook")]
+static OOK_TWO: &str = "Ook. Ook. Ook. Ook. Ook! Ook.";
+
+#[test]
+fn test_const_attribute() {
+    assert_eq!(ATTRIBUTE_A, "A");
+    assert_eq!(OOK_TWO, "\u{2}");
+}
+
+#[test]
+fn test_expand_debug() {
+    // The source constant does not change the output
+    let a = brainfuck!("+++++[>+++++++++++++<-]>.", expand_debug = true);
+    assert_eq!(a, "A");
+    const ONE: &str = brainfuck!("Ook. Ook. Ook! Ook.", dialect = "ook", expand_debug = true);
+    assert_eq!(ONE, "\u{1}");
+}
+
+#[test]
+fn test_runtime_interpreter() {
+    use brainfuck_core::machine::{Extensions, Interpreter};
+
+    const CODE: &str = "+++++[>+++++++++++++<-]>.%++.%.";
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_extensions(Extensions {
+        tapes: true,
+        ..Extensions::default()
+    });
+    interpreter.enable_tapes(2);
+    assert_eq!(
+        interpreter.execute(CODE).unwrap(),
+        brainfuck!("+++++[>+++++++++++++<-]>.%++.%.", extensions = "tapes")
+    );
+}
+
+#[test]
+fn test_debugger_save_state() {
+    use brainfuck_core::debug::{Breakpoint, Debugger, Snapshot, Stop};
+
+    // Save the state halfway through, as a playground would, and resume it elsewhere
+    let code = "+++++[>+++++++++++++<-]>.#+.";
+    let mut debugger = Debugger::new(code, b"").unwrap();
+    debugger.run_until(Breakpoint::Hash).unwrap();
+    let saved = serde_json::to_string(&debugger.snapshot()).unwrap();
+
+    let mut resumed = Debugger::new(code, b"").unwrap();
+    let snapshot: Snapshot = serde_json::from_str(&saved).unwrap();
+    resumed.restore(&snapshot).unwrap();
+    assert_eq!(resumed.run(), Ok(Stop::Finished));
+    assert_eq!(
+        resumed.output(),
+        brainfuck!("+++++[>+++++++++++++<-]>.#+.").as_bytes()
+    );
+}
+
+#[test]
+fn test_batch() {
+    const DIGITS: [&str; 3] = brainfuck!(
+        [
+            "{SIX}[>++++++++<-]>+.",
+            "{SIX}[>++++++++<-]>++.",
+            "{SIX}[>++++++++<-]>+++."
+        ],
+        SIX = "++++++",
+        batch = true
+    );
+    assert_eq!(DIGITS, ["1", "2", "3"]);
+    let ook = brainfuck!(
+        ["Ook. Ook. Ook! Ook.", "Ook! Ook."],
+        dialect = "ook",
+        batch = true
+    );
+    assert_eq!(ook, ["\u{1}", "\0"]);
+    let single: [&str; 1] = brainfuck!(["+.>++."], batch = true);
+    assert_eq!(single, ["\u{1}\u{2}"]);
+}
+
+#[test]
+fn test_stats_file() {
+    let output = brainfuck!(
+        "stats file [-] +++++[>+++++++++++++<-]>.",
+        stats_file = true
+    );
+    assert_eq!(output, "A");
+    let dir = concat!(env!("OUT_DIR"), "/brainfuck-stats");
+    let report = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .find(|report| report.contains("characters: 40\n"))
+        .unwrap();
+    assert!(report.contains("steps: 106\n"));
+    assert!(report.contains("peak_cells: 2\noutput_bytes: 1\nresult: ok\n"));
+    assert!(report.contains("ops: 10\nops_after_clear_loops: 9\nops_after_mul_loops: 5\n"));
+}
+
+#[test]
+fn test_coverage() {
+    let output = brainfuck!(
+        "++++++++[>+++++<-]>+.<[never reached]",
+        coverage = "../target/coverage.html"
+    );
+    assert_eq!(output, ")");
+    let html = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/coverage.html"
+    ))
+    .unwrap();
+    assert!(html.contains("<p>23 of 24 instructions executed (95.8%)</p>"));
+    assert!(html.contains(r#"<span class="hit" title="8 times">+</span>"#));
+    assert!(html.contains(r#"<span class="miss">]</span></pre>"#));
+}
+
+#[test]
+fn test_flame() {
+    let output = brainfuck!("++++++++[>+++++<-]>+.", flame = "../target/flame.folded");
+    assert_eq!(output, ")");
+    let folded = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/flame.folded"
+    ))
+    .unwrap();
+    assert_eq!(folded, "program 11\nprogram;loop@8 73\n");
+}
+
+#[test]
+fn test_with_source() {
+    let (program, output) = brainfuck!("+++++[>+++++++++++++<-]>.", with_source = true);
+    assert_eq!(program, "+++++[>+++++++++++++<-]>.");
+    assert_eq!(output, "A");
+    // The runtime interpreter agrees with the precomputed output
+    let run = brainfuck_core::interpret::run(program, &[]).unwrap();
+    assert_eq!(run, output.as_bytes());
+    const ONE: (&str, &str) =
+        brainfuck!("Ook. Ook. Ook! Ook.", dialect = "ook", with_source = true);
+    assert_eq!(ONE, ("+.", "\u{1}"));
+}
+
+#[test]
+fn test_literate() {
+    let a = brainfuck!(
+        "Prose may use any characters, even + and - or [brackets], as long as the
+         code is fenced: `+++++[>+++++++++++++<-]>.`",
+        literate = true
+    );
+    assert_eq!(a, "A");
+    let b = brainfuck!(
+        "~~++++++++[>++++++++<-]~~ (that is 64), then ~~>++.~~",
+        literate = true,
+        fence = "~~"
+    );
+    assert_eq!(b, "B");
+    // Fenced dialect code is joined with newlines, keeping tokens apart
+    let one = brainfuck!(
+        "`Ook. Ook.` then `Ook! Ook.`",
+        literate = true,
+        dialect = "ook"
+    );
+    assert_eq!(one, "\u{1}");
+}
+
+#[test]
+fn test_cfg_match() {
+    let separator = brainfuck_cfg_match! {
+        cfg(windows) => "++++[>+++++++++++++++++++++++<-]>.",
+        _ => "+++++[>+++++++++<-]>++.",
+    };
+    assert_eq!(separator, std::path::MAIN_SEPARATOR_STR);
+    // The first matching arm wins
+    const FIRST: &str = brainfuck_cfg_match! {
+        cfg(test) => "+.",
+        cfg(all()) => "++.",
+    };
+    assert_eq!(FIRST, "\u{1}");
+    let fallback = brainfuck_cfg_match! {
+        cfg(any()) => "+.",
+        _ => "+++.",
+    };
+    assert_eq!(fallback, "\u{3}");
+}
+
+brainfuck_item! {
+    /// Upper-cases its input
+    pub mod shout {
+        code: include_str!("../programs/shout.b"),
+        input: "abc",
+    }
+}
+
+#[test]
+fn test_item_module() {
+    assert!(shout::SOURCE.contains(','));
+    assert_eq!(shout::OUTPUT, "ABC");
+    assert!(shout::STEPS > shout::SOURCE.len());
+    assert_eq!(shout::run(b"abc"), shout::OUTPUT.as_bytes());
+    assert_eq!(shout::run(b"xyz"), b"XYZ");
+}
+
+mod generated {
+    // Generated code compiles in crates forbidding `unsafe`
+    #![forbid(unsafe_code)]
+
+    include!(concat!(env!("OUT_DIR"), "/hello.rs"));
+
+    brainfuck_macro::brainfuck_item! {
+        pub mod double {
+            code: "++[>++<-]>.",
+        }
+    }
+}
+
+#[test]
+fn test_build_script_output() {
+    // Transpiled by the build script from the same file the macro reads
+    let output = generated::hello(&[]);
+    assert_eq!(output, brainfuck_file!("programs/hello.b").as_bytes());
+    assert_eq!(generated::double::run(&[]), b"\x04");
+}
+
+mod generated_no_std {
+    include!(concat!(env!("OUT_DIR"), "/hello_no_std.rs"));
+}
+
+#[test]
+fn test_no_std_output() {
+    // Runs on a stack tape and writes into a fixed buffer, without allocating
+    let mut tape = [0u8; 64];
+    let mut output = [0u8; 64];
+    let mut len = 0;
+    generated_no_std::hello(&mut tape, &[], |byte| {
+        output[len] = byte;
+        len += 1;
+    });
+    assert_eq!(
+        &output[..len],
+        brainfuck_file!("programs/hello.b").as_bytes()
+    );
+
+    let source = brainfuck_transpile!(",[.,]", no_std = true);
+    assert!(
+        source.contains("pub fn run(tape: &mut [u8], input: &[u8], mut output: impl FnMut(u8)) {")
+    );
+    let mut echoed = Vec::new();
+    brainfuck_core::interpret::run_with_tape(",[.,]", &mut tape, b"abc", |byte| echoed.push(byte))
+        .unwrap();
+    assert_eq!(echoed, b"abc");
+}
+
+brainfuck_extern_fn!(",[--------------------------------.,]", name = "bf_upper");
+brainfuck_extern_fn!("+[<+]", name = "bf_underflow");
+
+#[test]
+fn test_extern_fn() {
+    let mut out = [0u8; 8];
+    let written = unsafe { bf_upper(b"ffi".as_ptr(), 3, out.as_mut_ptr(), out.len()) };
+    assert_eq!(&out[..written as usize], b"FFI");
+    // Only the first `cap` bytes are written when the output does not fit
+    let written = unsafe { bf_upper(b"abc".as_ptr(), 3, out.as_mut_ptr(), 2) };
+    assert_eq!((written, &out[..3]), (-1, &b"ABI"[..]));
+    let written = unsafe { bf_upper(std::ptr::null(), 0, std::ptr::null_mut(), 0) };
+    assert_eq!(written, 0);
+    let f: unsafe extern "C" fn(*const u8, usize, *mut u8, usize) -> isize = bf_underflow;
+    assert_eq!(unsafe { f(std::ptr::null(), 0, out.as_mut_ptr(), 0) }, -2);
+}
+
+brainfuck_async_fn!(",[.,]", name = "bf_echo");
+brainfuck_async_fn!("++++++++++[>+.<-]", name = "bf_count", yield_every = 12);
+
+#[test]
+fn test_async_fn() {
+    use std::future::Future;
+
+    let mut output = Vec::new();
+    futures::executor::block_on(bf_echo(&b"async"[..], &mut output)).unwrap();
+    assert_eq!(output, b"async");
+
+    // Ten iterations of six steps each, yielding after every twelve steps
+    let mut output = Vec::new();
+    let waker = futures::task::noop_waker();
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut yields = 0;
+    {
+        let mut future = std::pin::pin!(bf_count(&b""[..], &mut output));
+        while future.as_mut().poll(&mut cx).is_pending() {
+            yields += 1;
+        }
+    }
+    assert_eq!(yields, 5);
+    assert_eq!(output, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+}
+
+#[test]
+fn test_lines() {
+    let a = brainfuck!([
+        "+++++",              // 5
+        "[>+++++++++++++<-]", // 5 * 13 = 65
+        ">.",                 // 'A'
+    ]);
+    assert_eq!(a, "A");
+    assert_eq!(brainfuck_check!(["+", "."]), "+\n.");
+}
+
+#[test]
+fn test_raw_tokens() {
+    assert_eq!(brainfuck!(+++++[>+++++++++++++<-]>.), "A");
+    // Comments made of words, and options after the code
+    let answer = brainfuck!(six times seven ++++++[>+++++++<-]>:, extensions = "decimal");
+    assert_eq!(answer, "42");
+    assert_eq!(brainfuck_minify!(+++ clear [-] ---), "+++[-]---");
+}
+
+#[test]
+fn test_check_returns_code() {
+    let code = brainfuck_check!(",[.,] echo");
+    assert_eq!(code, ",[.,] echo");
+}
+
+#[test]
+fn test_check_with_lint() {
+    let code = brainfuck_check!("+++[>++<-]>.", lint = true);
+    assert_eq!(code, "+++[>++<-]>.");
+}
+
+#[test]
+fn test_minify() {
+    let code = brainfuck_minify!("Hello: ++ +- [ >+ <- ] Done >.<>");
+    assert_eq!(code, "++[>+<-]>.");
+}
+
+#[test]
+fn test_optimize() {
+    let code = brainfuck_optimize!("[.] ++ [>+<-] [>] > [+] .");
+    assert_eq!(code, "++[->+<]>[-].");
+}
+
+#[test]
+fn test_fmt() {
+    let code = brainfuck_fmt!("Setup ++[>++[>+<-]<-] print >>.");
+    assert_eq!(code, "++\n[\n    >++\n    [>+<-]\n    <-\n]\n>>.");
+}
+
+#[test]
+fn test_fmt_with_options() {
+    let code = brainfuck_fmt!("++++++[>+++<-]", indent = 1, width = 5);
+    assert_eq!(code, "+++++\n+\n[\n >+++\n <-\n]");
+}
+
+#[test]
+fn test_text_to_brainfuck() {
+    let code = text_to_brainfuck!("Hello");
+    assert_eq!(
+        code,
+        ">++++++++[<+++++++++>-]<.>++++[<+++++++>-]<+.+++++++..+++."
+    );
+}
+
+#[test]
+fn test_golf() {
+    let (code, len) = brainfuck_golf!("The quick brown fox jumps over the lazy dog");
+    assert_eq!(code.len(), len);
+    assert!(len < text_to_brainfuck!("The quick brown fox jumps over the lazy dog").len());
+    let output = brainfuck_core::interpret::run(code, b"").unwrap();
+    assert_eq!(output, b"The quick brown fox jumps over the lazy dog");
+}
+
+static LAZY_HELLO: std::sync::LazyLock<String> =
+    brainfuck_lazy!(include_str!("../programs/hello.b"));
+
+#[test]
+fn test_lazy_program() {
+    assert_eq!(*LAZY_HELLO, "Hello World!\n");
+    let shout = brainfuck_lazy!(include_str!("../programs/shout.b"), input = "lazy");
+    assert_eq!(*shout, "LAZY");
+}
+
+brainfuck_iter! {
+    /// Counts upwards forever.
+    struct Counter = "+[>+.<]";
+}
+
+brainfuck_iter! {
+    struct Shout = include_str!("../programs/shout.b");
+}
+
+#[test]
+fn test_output_iterator() {
+    let bytes: Vec<u8> = Counter::new().skip(254).take(3).collect();
+    assert_eq!(bytes, [255, 0, 1]);
+    assert_eq!(Shout::with_input(b"iter").collect::<Vec<u8>>(), b"ITER");
+    assert_eq!(Shout::default().count(), 0);
+}
+
+#[test]
+fn test_assert_matching_output() {
+    brainfuck_assert!("+++++[>+++++++++++++<-]>.+.+.", "ABC");
+    brainfuck_assert!("", "");
+}
+
+#[test]
+fn test_equivalent_programs() {
+    brainfuck_eq!("[-]+++[>++<-]>.", brainfuck_expand!("6+."));
+    brainfuck_eq!(",[.,]", ",[.[-],]", inputs = ["", "a", "hello"]);
+}
+
+brainfuck_testcases! {
+    testcase_output: { code: "+++++[>+++++++++++++<-]>.+.", expect: "AB" },
+    testcase_echo: { code: ",[.,]", input: "hello", expect: "hello" },
+    testcase_from_file: {
+        code: include_str!("../programs/shout.b"),
+        input: "abc",
+        expect: "ABC",
+    },
+}
+
+brainfuck_difftest!(",[>++<-]>[.-]", inputs = ["", "\u{3}", "abc"]);
+brainfuck_difftest!(include_str!("../programs/hello.b"), name = "difftest_hello");
+
+brainfuck_exercise!("++++++++[>++++++++<-]>+.+.", answer = "AB");
+brainfuck_exercise!(
+    ",[+.,]",
+    input = "HAL",
+    answer = "IBM",
+    name = "exercise_hal"
+);
+
+#[test]
+// Wrong answers are graded inside this test rather than as tests of their own
+#[allow(unnameable_test_items)]
+fn test_exercise_spoiler_free() {
+    brainfuck_exercise!("++++++++[>++++++++<-]>+.+.", answer = "AC");
+    brainfuck_exercise!(",[+.,]", input = "HAL", answer = "IBMX", name = "too_long");
+
+    for (test, message) in [
+        (exercise as fn(), "incorrect, differs at byte 1"),
+        (too_long, "incorrect, differs at byte 3"),
+    ] {
+        let panic = std::panic::catch_unwind(test).unwrap_err();
+        assert_eq!(panic.downcast_ref::<String>().unwrap(), message);
+    }
+}
+
+brainfuck_mutants!("++++++++[>++++++++<-]>+.", expect = "A");
+brainfuck_mutants!(
+    ",+.>",
+    input = "a",
+    expect = "b",
+    allow = "3",
+    name = "next"
+);
+
+#[test]
+// Surviving mutants are checked inside this test rather than as tests of their own
+#[allow(unnameable_test_items)]
+fn test_surviving_mutants() {
+    // Cell 0 starts at 0, so the clear loop does nothing, and the last `>` comes too late
+    brainfuck_mutants!("[-]++[>+++<-]>.>", expect = "\u{6}", name = "wasteful");
+
+    wasteful::original();
+    wasteful::mutant_0();
+    wasteful::mutant_14();
+    for (test, message) in [
+        (wasteful::mutant_1 as fn(), "deleting '-' at 1"),
+        (wasteful::mutant_2, "deleting the loop at 0..=2"),
+        (wasteful::mutant_15, "deleting '>' at 15"),
+    ] {
+        let panic = std::panic::catch_unwind(test).unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().unwrap(),
+            &format!(
+                "mutant survived: {} still prints the expected output",
+                message
+            )
+        );
+    }
+}
+
+mod golden {
+    // Tests tests/bf/letter_a.b and tests/bf/echo.b, but not no_golden.b without an .out
+    brainfuck_macro::brainfuck_golden_tests!("tests/bf");
+}
+
+#[test]
+fn test_expand_shorthand() {
+    let code = brainfuck_expand!("5+[>13+<-]>. 2+.");
+    assert_eq!(code, "+++++[>+++++++++++++<-]>. ++.");
+}
+
+#[test]
+fn test_include_str_argument() {
+    let result = brainfuck!(include_str!("../programs/hello.b"));
+    assert_eq!(result, "Hello World!\n");
+}
+
+#[test]
+fn test_concat_argument() {
+    let result = brainfuck!(concat!("+++++", "[>+++++++++++++<-]", ">."));
+    assert_eq!(result, "A");
+}
+
+#[test]
+fn test_nested_own_macros() {
+    assert_eq!(brainfuck!(text_to_brainfuck!("Hi!")), "Hi!");
+    assert_eq!(brainfuck!(brainfuck_expand!("5+[>13+<-]>.")), "A");
+}
+
+brainfuck_macro::brainfuck_doc! {
+    /// The letter A.
+    pub const LETTER_A: &str = "
+        +++++              Set cell 0 to 5
+        [>+++++++++++++<-] Add 13 to cell 1 five times
+        >.                 Print cell 1
+    ";
+}
+
+brainfuck_macro::brainfuck_doc! {
+    const UNDOCUMENTED = "+++++[>+++++++++++++<-]>+.";
+}
+
+#[test]
+fn test_doc_constants() {
+    assert_eq!(LETTER_A, "A");
+    assert_eq!(UNDOCUMENTED, "B");
+}
+
+#[test]
+fn test_trace() {
+    let trace = brainfuck_trace!("Read , then print .", input = "x");
+    assert_eq!(
+        trace,
+        " step    ip  op    ptr  cell  output\n    1     5  ,      0   120  \"\"\n    2    18  .      0   120  \"x\"\n"
+    );
+}
+
+#[test]
+fn test_trace_of_failing_program() {
+    let trace = brainfuck_trace!("+++<", steps = 1);
+    assert!(trace.ends_with("... 2 more steps not shown\nerror: Pointer moved below zero\n"));
+}
+
+#[test]
+fn test_tape_trace() {
+    let trace = brainfuck_trace!(
+        "++[>+++<-]>.",
+        tape = true,
+        window = 2,
+        every = 5,
+        file = "../target/tape_trace.txt"
+    );
+    let expected = "step 1: + at 0
+    0     1
+┌─────┬─────┐
+│   1 │   0 │
+└─────┴─────┘
+   ^
+step 6: + at 5
+    0     1
+┌─────┬─────┐
+│   2 │   2 │
+└─────┴─────┘
+         ^
+";
+    assert!(trace.starts_with(expected), "{}", trace);
+    assert!(trace.ends_with("output: \"\\u{6}\"\n"));
+    let written = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/tape_trace.txt"
+    ))
+    .unwrap();
+    assert_eq!(written, trace);
+}
+
+#[test]
+fn test_report() {
+    let report = brainfuck_report!(
+        ",[.,]",
+        input = "hi",
+        steps = 2,
+        path = "../target/report.md"
+    );
+    assert!(report.starts_with("# Brainfuck program\n\n## Source\n\n```brainfuck\n,\n[.,]\n```\n"));
+    assert!(report.contains("| Cells used | 1 |\n| Output bytes | 2 |\n"));
+    assert!(report.contains("... 6 more steps not shown\n```\n"));
+    let written =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../target/report.md"))
+            .unwrap();
+    assert_eq!(written, report);
+}
+
+#[test]
+fn test_json_frames() {
+    let json = brainfuck_trace!(
+        "++[>+<-]<",
+        json = true,
+        every = 3,
+        steps = 7,
+        file = "../target/frames.json"
+    );
+    let expected = r#"{"steps":13,"frames":[
+{"step":1,"ip":0,"instruction":"+","pointer":0,"changed":{"cell":0,"value":1},"output":null},
+{"step":4,"ip":3,"instruction":">","pointer":1,"changed":null,"output":null},
+{"step":7,"ip":6,"instruction":"-","pointer":0,"changed":{"cell":0,"value":1},"output":null}
+],"error":"Pointer moved below zero"}
+"#;
+    assert_eq!(json, expected);
+    let frames: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(frames["frames"][2]["changed"]["value"], 1);
+}
+
+#[test]
+fn test_random() {
+    let (program, output) = brainfuck_random!(42, length = 100, input = "abc");
+    let options = brainfuck_core::random::Options {
+        length: 100,
+        ..Default::default()
+    };
+    assert_eq!(program, brainfuck_core::random::program(42, &options));
+    let bytes = brainfuck_core::interpret::run(program, b"abc").unwrap();
+    assert_eq!(
+        bytes.into_iter().map(char::from).collect::<String>(),
+        output
+    );
+
+    let (program, _) = brainfuck_random!(7, length = 20, depth = 0, cells = 1);
+    assert_eq!(program.len(), 20);
+    assert!(program.chars().all(|c| "+-.".contains(c)));
+}
+
+#[test]
+fn test_proven_termination() {
+    // About 1.4 million steps, but every loop is counted down to 0
+    let output = brainfuck!("-[>-[>++++++++++++++++<-]<-]>>.");
+    assert_eq!(output, "\u{10}");
+}
+
+#[test]
+fn test_metrics() {
+    use brainfuck_core::metrics::Metrics;
+
+    const METRICS: Metrics = brainfuck_metrics!("Print A: +++++ [>+++++++++++++<-] +- >.");
+    assert_eq!(
+        METRICS,
+        Metrics::of("Print A: +++++ [>+++++++++++++<-] +- >.").unwrap()
+    );
+    assert_eq!(METRICS.histogram.increment, 19);
+    assert_eq!((METRICS.loops, METRICS.max_depth), (1, 1));
+    assert_eq!((METRICS.length, METRICS.minified_length), (39, 25));
+    let ook = brainfuck_metrics!("Ook. Ook. Ook! Ook.", dialect = "ook");
+    assert_eq!((ook.histogram.output, ook.instructions()), (1, 2));
+}
+
+#[test]
+fn test_ir_dump() {
+    let ir = brainfuck_ir!("++[>[-]<[->++<]>.<-]");
+    assert_eq!(
+        ir,
+        "add 2\nloop\n  move +1\n  clear\n  move -1\n  mul [+1] += 2\n  move +1\n  output\n  move -1\n  add -1\nend\n"
+    );
+}
+
+#[test]
+fn test_cfg_dot() {
+    let dot = brainfuck_cfg!("+[>+<-]>.");
+    assert!(dot.starts_with("digraph brainfuck {\n"));
+    assert!(dot.contains("n1 [shape=diamond, label=\"1\\n[\"];"));
+    assert!(dot.contains("n3 -> n2 [label=\"nonzero\"];"));
+    assert!(dot.contains("n4 -> end;"));
+}
+
+/// Every output mode expands to a literal, so invocations work in constant initializers,
+/// match patterns, array lengths and `concat!`
+mod positions {
+    use super::*;
+
+    macro_rules! test_positions {
+        ($($test:ident: $name:ident! $args:tt == $expected:expr;)*) => {$(
+            #[test]
+            fn $test() {
+                const VALUE: &str = $name! $args;
+                assert_eq!(VALUE, $expected);
+                assert!(matches!($expected, $name! $args));
+                assert_eq!(concat!($name! $args, "!"), format!("{}!", $expected));
+                let array = [0u8; $name! $args.len()];
+                assert_eq!(array.len(), $expected.len());
+            }
+        )*};
+    }
+
+    test_positions! {
+        test_brainfuck: brainfuck!("+++++[>+++++++++++++<-]>.") == "A";
+        test_brainfuck_options: brainfuck!("+++++[>+++++++++++++<-]>.@ Ends", extensions = "end") == "A";
+        test_brainfuck_dialect: brainfuck!("Ook. Ook. Ook! Ook.", dialect = "ook") == "\u{1}";
+        test_brainfuck_raw: brainfuck!(+++++[>+++++++++++++<-]>.) == "A";
+        test_bf: bf!("+++++[>+++++++++++++<-]>.") == "A";
+        test_check: brainfuck_check!("Checked: +.") == "Checked: +.";
+        test_minify: brainfuck_minify!("Minified: + [-] .") == "+[-].";
+        test_optimize: brainfuck_optimize!("[.] ++ .") == "++.";
+        test_fmt: brainfuck_fmt!("++[>+<-]") == "++\n[>+<-]";
+        test_ir: brainfuck_ir!("++.") == "add 2\noutput\n";
+        test_encode: brainfuck_encode!("+.", dialect = "blub") == "Blub. Blub. Blub! Blub.";
+        test_to_ook: brainfuck_to_ook!("+.") == "Ook. Ook. Ook! Ook.";
+        test_text_to_brainfuck: text_to_brainfuck!("A") == ">++++++++[<++++++++>-]<+.";
+        test_expand: brainfuck_expand!("3+.") == "+++.";
+        test_ook: ook!("Ook. Ook. Ook! Ook.") == "\u{1}";
+        test_blub: blub!("Blub. Blub. Blub! Blub.") == "\u{1}";
+        test_pbrain: pbrain!("(+.) :") == "\u{1}";
+        test_spoon: spoon!("1 001010") == "\u{1}";
+        test_alphuck: alphuck!("e j") == "\u{1}";
+    }
+
+    #[test]
+    fn test_with_source_tuple() {
+        const PAIR: (&str, &str) = brainfuck!("+.", with_source = true);
+        assert!(matches!(PAIR, brainfuck!("+.", with_source = true)));
+        const GOLF: (&str, usize) = brainfuck_golf!("A");
+        let array = [0u8; brainfuck_golf!("A").1];
+        assert_eq!(array.len(), GOLF.1);
+    }
+}
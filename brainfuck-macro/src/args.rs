@@ -0,0 +1,323 @@
+//! Parsing of macro arguments of the form `"code", key = value, ...`.
+
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Lit, LitBool, LitStr, Token};
+
+use crate::frontend;
+use crate::source::Code;
+
+/// Arguments of a macro taking Brainfuck code followed by optional `key = value` options
+pub(crate) struct MacroArgs {
+    pub code: Code,
+    pub options: Options,
+}
+
+impl MacroArgs {
+    /// The code of a macro without options besides `dialect`, decoded into Brainfuck
+    pub fn into_code(self) -> syn::Result<Code> {
+        let Self {
+            mut code,
+            mut options,
+        } = self;
+        options.decode_dialect(&mut code)?;
+        options.finish()?;
+        Ok(code)
+    }
+}
+
+impl Parse for MacroArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut code = Code::parse_with_raw(input)?;
+        // Options never start with a literal, so literals after commas continue the code
+        while input.peek(Token![,]) && input.peek2(LitStr) {
+            input.parse::<Token![,]>()?;
+            code.append(input.parse()?);
+        }
+        let mut options = if input.is_empty() {
+            Options::default()
+        } else {
+            input.parse::<Token![,]>()?;
+            input.parse()?
+        };
+        options.fill_placeholders(&mut code)?;
+        options.extract_literate(&mut code)?;
+        Ok(Self { code, options })
+    }
+}
+
+/// A list of `key = literal` options, consumed by name
+#[derive(Default)]
+pub(crate) struct Options {
+    entries: Vec<(Ident, Lit)>,
+}
+
+impl Parse for Options {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut entries: Vec<(Ident, Lit)> = Vec::new();
+        while !input.is_empty() {
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Lit = input.parse()?;
+            if entries.iter().any(|(existing, _)| *existing == name) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("duplicate option `{}`", name),
+                ));
+            }
+            entries.push((name, value));
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl Options {
+    /// Remove and return the raw value of an option
+    fn take(&mut self, name: &str) -> Option<Lit> {
+        let index = self.entries.iter().position(|(key, _)| key == name)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Take a boolean option, defaulting to `false`
+    pub fn flag(&mut self, name: &str) -> syn::Result<bool> {
+        self.flag_or(name, false)
+    }
+
+    /// Take a boolean option with the given default
+    pub fn flag_or(&mut self, name: &str, default: bool) -> syn::Result<bool> {
+        match self.take(name) {
+            None => Ok(default),
+            Some(Lit::Bool(LitBool { value, .. })) => Ok(value),
+            Some(other) => Err(syn::Error::new(
+                other.span(),
+                format!("option `{}` expects `true` or `false`", name),
+            )),
+        }
+    }
+
+    /// Take a string option
+    pub fn string(&mut self, name: &str) -> syn::Result<Option<LitStr>> {
+        match self.take(name) {
+            None => Ok(None),
+            Some(Lit::Str(value)) => Ok(Some(value)),
+            Some(other) => Err(syn::Error::new(
+                other.span(),
+                format!("option `{}` expects a string literal", name),
+            )),
+        }
+    }
+
+    /// Take a non-negative integer option
+    pub fn usize(&mut self, name: &str) -> syn::Result<Option<usize>> {
+        match self.take(name) {
+            None => Ok(None),
+            Some(Lit::Int(value)) => value.base10_parse().map(Some),
+            Some(other) => Err(syn::Error::new(
+                other.span(),
+                format!("option `{}` expects an integer", name),
+            )),
+        }
+    }
+
+    /// Take the `dialect` option and decode the code from that dialect into Brainfuck
+    pub fn decode_dialect(&mut self, code: &mut Code) -> syn::Result<()> {
+        let Some(name) = self.string("dialect")? else {
+            return Ok(());
+        };
+        match frontend::select(&name, code)? {
+            Some(frontend) => code.decode(frontend),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the options named like placeholders and substitute their values for `{NAME}`
+    /// in the code. Without such options, the code is left alone, so braces in programs
+    /// using them as commands keep working.
+    pub fn fill_placeholders(&mut self, code: &mut Code) -> syn::Result<()> {
+        let (placeholders, entries) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(name, _)| is_placeholder(&name.to_string()));
+        self.entries = entries;
+        if placeholders.is_empty() {
+            return Ok(());
+        }
+
+        let mut values = Vec::new();
+        for (name, value) in &placeholders {
+            match value {
+                Lit::Str(value) => values.push((name.to_string(), value.value())),
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        format!("placeholder `{}` expects a string literal", name),
+                    ))
+                }
+            }
+        }
+        let mut used = vec![false; values.len()];
+        let span = code.span();
+        code.transform(|text| fill(text, &values, &mut used))
+            .map_err(|name| {
+                syn::Error::new(span, format!("no value for placeholder `{{{}}}`", name))
+            })?;
+        if let Some(index) = used.iter().position(|&used| !used) {
+            return Err(syn::Error::new(
+                placeholders[index].0.span(),
+                format!("unused placeholder `{}`", placeholders[index].0),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Take the `literate` and `fence` options and, for literate code, keep only the
+    /// fenced parts of the code, one per line
+    pub fn extract_literate(&mut self, code: &mut Code) -> syn::Result<()> {
+        let literate = self.flag("literate")?;
+        let fence = self.string("fence")?;
+        if !literate {
+            return match fence {
+                Some(fence) => Err(syn::Error::new(
+                    fence.span(),
+                    "option `fence` requires `literate = true`",
+                )),
+                None => Ok(()),
+            };
+        }
+        let fence = fence.map_or_else(|| DEFAULT_FENCE.to_string(), |fence| fence.value());
+        if fence.is_empty() {
+            return Err(syn::Error::new(
+                code.span(),
+                "option `fence` cannot be empty",
+            ));
+        }
+        let span = code.span();
+        code.transform(|text| fenced(text, &fence))
+            .map_err(|position| {
+                syn::Error::new(
+                    span,
+                    format!("unclosed fence `{}` at position {}", fence, position),
+                )
+            })
+    }
+
+    /// Fail on any option that was not consumed
+    pub fn finish(self) -> syn::Result<()> {
+        match self.entries.into_iter().next() {
+            None => Ok(()),
+            Some((name, _)) => Err(syn::Error::new(
+                name.span(),
+                format!("unknown option `{}`", name),
+            )),
+        }
+    }
+}
+
+/// The fence around commands in literate code, unless set with the `fence` option
+const DEFAULT_FENCE: &str = "`";
+
+/// The parts of literate code between pairs of fences, joined with newlines. Fails with
+/// the character position of a fence that is never closed.
+fn fenced(text: &str, fence: &str) -> Result<String, usize> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(fence) {
+        let after = &rest[start + fence.len()..];
+        let Some(end) = after.find(fence) else {
+            let offset = text.len() - rest.len() + start;
+            return Err(text[..offset].chars().count());
+        };
+        parts.push(&after[..end]);
+        rest = &after[end + fence.len()..];
+    }
+    Ok(parts.join("\n"))
+}
+
+/// Whether a name is a placeholder: an uppercase letter followed by uppercase letters,
+/// digits and underscores
+fn is_placeholder(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_')
+}
+
+/// Replace every `{NAME}` in `code` by the value given for `NAME`, marking the values
+/// used. Braces around anything else are kept. Fails with the first name without value.
+fn fill(code: &str, values: &[(String, String)], used: &mut [bool]) -> Result<String, String> {
+    let mut filled = String::new();
+    let mut rest = code;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if is_placeholder(&after[..end]) => {
+                let name = &after[..end];
+                let index = values
+                    .iter()
+                    .position(|(value_name, _)| value_name == name)
+                    .ok_or_else(|| name.to_string())?;
+                filled.push_str(&values[index].1);
+                used[index] = true;
+                rest = &after[end + 1..];
+            }
+            _ => {
+                filled.push('{');
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fenced() {
+        let text =
+            "Set the cell to 5 with `+++++`, then add 13 five times: `[>+++++++++++++<-]`.\n\
+                    Finally, print it: `>.`";
+        assert_eq!(fenced(text, "`").unwrap(), "+++++\n[>+++++++++++++<-]\n>.");
+        // Markdown code blocks close their fences too
+        assert_eq!(fenced("Code:\n```\n+.\n```\n", "`").unwrap(), "\n\n+.\n\n");
+        assert_eq!(fenced("a <bf>+.</bf> b", "<bf>").unwrap_err(), 2);
+        assert_eq!(fenced("A `+` and `-", "`").unwrap_err(), 10);
+        assert_eq!(fenced("No commands, only prose.", "`").unwrap(), "");
+    }
+
+    #[test]
+    fn test_fill() {
+        let values = [
+            ("PRELUDE".to_string(), "+++++".to_string()),
+            ("N_2".to_string(), "++".to_string()),
+        ];
+        let mut used = [false; 2];
+        assert_eq!(
+            fill("{PRELUDE}[>{N_2}<-]{PRELUDE}", &values, &mut used).unwrap(),
+            "+++++[>++<-]+++++"
+        );
+        assert_eq!(used, [true, true]);
+        // Braces around anything but a placeholder name are kept
+        let mut used = [false; 2];
+        assert_eq!(fill("{}{x}{", &values, &mut used).unwrap(), "{}{x}{");
+        assert_eq!(used, [false, false]);
+        assert_eq!(
+            fill("{MISSING}", &values, &mut used).unwrap_err(),
+            "MISSING"
+        );
+    }
+
+    #[test]
+    fn test_is_placeholder() {
+        assert!(is_placeholder("PRELUDE"));
+        assert!(is_placeholder("COUNT_2"));
+        assert!(!is_placeholder("dialect"));
+        assert!(!is_placeholder("_X"));
+        assert!(!is_placeholder(""));
+    }
+}
@@ -0,0 +1,115 @@
+//! Compile-time interpreter for Boolfuck.
+//!
+//! [Boolfuck](https://esolangs.org/wiki/Boolfuck) works like Brainfuck on a tape of bits:
+//! `+` flips the current bit, `;` outputs it, and `[` and `]` loop while it is set. There
+//! is no `-` and no `.`. Output bits are collected into bytes starting with the least
+//! significant bit, and a final incomplete byte is padded with zeros. The tape size, step
+//! limit and errors are the same as for Brainfuck.
+
+use crate::{BrainfuckError, Interpreter, MAX_STEPS, TAPE_SIZE};
+
+/// Execute Boolfuck code and return the output
+pub(crate) fn execute(code: &str) -> Result<String, BrainfuckError> {
+    let jump_table = Interpreter::find_matching_brackets(code)?;
+    let chars: Vec<char> = code.chars().collect();
+
+    let mut tape = vec![false; TAPE_SIZE];
+    let mut pointer = 0;
+    let mut output = String::new();
+    // The output byte being assembled and the number of bits in it
+    let mut byte = 0u8;
+    let mut bits = 0;
+
+    let mut ip = 0;
+    let mut steps = 0;
+    while ip < chars.len() {
+        if steps >= MAX_STEPS {
+            return Err(BrainfuckError::MaxStepsExceeded(MAX_STEPS));
+        }
+        steps += 1;
+
+        match chars[ip] {
+            '>' => {
+                if pointer >= TAPE_SIZE - 1 {
+                    return Err(BrainfuckError::PointerOverflow(TAPE_SIZE));
+                }
+                pointer += 1;
+            }
+            '<' => {
+                if pointer == 0 {
+                    return Err(BrainfuckError::PointerUnderflow);
+                }
+                pointer -= 1;
+            }
+            '+' => tape[pointer] = !tape[pointer],
+            ';' => {
+                byte |= (tape[pointer] as u8) << bits;
+                bits += 1;
+                if bits == 8 {
+                    output.push(byte as char);
+                    byte = 0;
+                    bits = 0;
+                }
+            }
+            ',' => return Err(BrainfuckError::InputNotSupported),
+            '[' if !tape[pointer] => {
+                if let Some(matching) = jump_table[ip] {
+                    ip = matching;
+                }
+            }
+            ']' if tape[pointer] => {
+                if let Some(matching) = jump_table[ip] {
+                    ip = matching;
+                }
+            }
+            _ => {}
+        }
+        ip += 1;
+    }
+
+    if bits > 0 {
+        output.push(byte as char);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_bits() {
+        // 'A' is 0b0100_0001, output from the least significant bit
+        assert_eq!(execute("+;+;;;;;+;+;").unwrap(), "A");
+        // Incomplete bytes are padded with zeros
+        assert_eq!(execute("+;+;").unwrap(), "\u{1}");
+        assert_eq!(execute("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_loops() {
+        // Outputs the set bit, then clears it to leave the loop
+        assert_eq!(execute("+[;+]").unwrap(), "\u{1}");
+        assert_eq!(execute(">+[<]>;").unwrap(), "\u{1}");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            execute("+[;"),
+            Err(BrainfuckError::UnmatchedOpenBracket(1))
+        ));
+        assert!(matches!(
+            execute("<"),
+            Err(BrainfuckError::PointerUnderflow)
+        ));
+        assert!(matches!(
+            execute(","),
+            Err(BrainfuckError::InputNotSupported)
+        ));
+        assert!(matches!(
+            execute("+[]"),
+            Err(BrainfuckError::MaxStepsExceeded(MAX_STEPS))
+        ));
+    }
+}
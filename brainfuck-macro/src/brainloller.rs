@@ -0,0 +1,141 @@
+//! Brainloller: Brainfuck programs stored as the pixels of a PNG image.
+//!
+//! The instruction pointer starts at the top-left pixel moving right. Eight colors map
+//! to the Brainfuck commands, two more rotate the direction of travel, and all other
+//! colors are ignored. The program ends when the pointer leaves the image. Since the
+//! path does not depend on the tape, it is walked once to produce plain Brainfuck.
+//!
+//! Every position and direction has exactly one predecessor, so the path can never
+//! return to an earlier state and always leaves the image.
+
+/// Rotate the direction of travel clockwise (cyan)
+const ROTATE_CLOCKWISE: [u8; 3] = [0, 255, 255];
+/// Rotate the direction of travel counterclockwise (dark cyan)
+const ROTATE_COUNTERCLOCKWISE: [u8; 3] = [0, 128, 128];
+
+/// The command for each command color
+const COMMANDS: [([u8; 3], char); 8] = [
+    ([255, 0, 0], '>'),
+    ([128, 0, 0], '<'),
+    ([0, 255, 0], '+'),
+    ([0, 128, 0], '-'),
+    ([0, 0, 255], '.'),
+    ([0, 0, 128], ','),
+    ([255, 255, 0], '['),
+    ([128, 128, 0], ']'),
+];
+
+/// An RGB image
+pub(crate) struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Decode a PNG file into RGB pixels, ignoring any alpha channel
+pub(crate) fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Invalid PNG: {}", e))?;
+    let size = reader
+        .output_buffer_size()
+        .ok_or_else(|| "PNG image is too large".to_string())?;
+    let mut buffer = vec![0; size];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| format!("Invalid PNG: {}", e))?;
+    let data = &buffer[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgb => data.chunks(3).map(|p| [p[0], p[1], p[2]]).collect(),
+        png::ColorType::Rgba => data.chunks(4).map(|p| [p[0], p[1], p[2]]).collect(),
+        png::ColorType::Grayscale => data.iter().map(|&g| [g, g, g]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks(2).map(|p| [p[0], p[0], p[0]]).collect(),
+        png::ColorType::Indexed => return Err("Unexpected indexed PNG output".to_string()),
+    };
+
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+/// Walk the instruction pointer through the image, collecting the commands it passes
+pub(crate) fn to_brainfuck(image: &Image) -> String {
+    // Directions in clockwise order: right, down, left, up
+    const STEPS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+    let mut code = String::new();
+    let (mut x, mut y, mut direction) = (0usize, 0usize, 0usize);
+
+    while x < image.width && y < image.height {
+        let color = image.pixel(x, y);
+        if color == ROTATE_CLOCKWISE {
+            direction = (direction + 1) % 4;
+        } else if color == ROTATE_COUNTERCLOCKWISE {
+            direction = (direction + 3) % 4;
+        } else if let Some(&(_, command)) = COMMANDS.iter().find(|(c, _)| *c == color) {
+            code.push(command);
+        }
+
+        let (dx, dy) = STEPS[direction];
+        match (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+            (Some(next_x), Some(next_y)) => (x, y) = (next_x, next_y),
+            _ => break,
+        }
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const GREEN: [u8; 3] = [0, 255, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+    const WHITE: [u8; 3] = [255, 255, 255];
+
+    fn image(width: usize, pixels: Vec<[u8; 3]>) -> Image {
+        Image {
+            width,
+            height: pixels.len() / width,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_straight_line() {
+        let img = image(4, vec![GREEN, WHITE, RED, BLUE]);
+        assert_eq!(to_brainfuck(&img), "+>.");
+    }
+
+    #[test]
+    fn test_rotation() {
+        // + + cw
+        // . - cw   (second row is read right to left)
+        let dark_green = [0, 128, 0];
+        let img = image(
+            3,
+            vec![
+                GREEN,
+                GREEN,
+                ROTATE_CLOCKWISE,
+                BLUE,
+                dark_green,
+                ROTATE_CLOCKWISE,
+            ],
+        );
+        assert_eq!(to_brainfuck(&img), "++-.");
+    }
+}
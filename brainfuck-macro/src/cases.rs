@@ -0,0 +1,85 @@
+//! Parsing of named program entries, as used by the macros generating tests and
+//! benchmarks: `name: { code: "...", input: "...", expect: "..." }, ...`.
+
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Ident, LitByteStr, LitStr, Token};
+
+use crate::source::Code;
+
+/// A comma-separated list of entries
+pub(crate) struct Cases(pub Vec<Case>);
+
+impl Parse for Cases {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let cases = Punctuated::<Case, Token![,]>::parse_terminated(input)?;
+        Ok(Self(cases.into_iter().collect()))
+    }
+}
+
+/// A single named program. Which of the optional fields are required is up to the macro.
+pub(crate) struct Case {
+    pub name: Ident,
+    pub code: Code,
+    pub input: Option<LitStr>,
+    pub expect: Option<LitStr>,
+}
+
+impl Parse for Case {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let content;
+        syn::braced!(content in input);
+        Self::parse_fields(name, &content)
+    }
+}
+
+impl Case {
+    /// The input as a byte string literal, empty if none was given
+    pub fn input_bytes(&self) -> LitByteStr {
+        let input = self.input.as_ref().map(LitStr::value).unwrap_or_default();
+        LitByteStr::new(input.as_bytes(), self.name.span())
+    }
+
+    /// Parse the fields of the entry called `name`, as found between its braces
+    pub fn parse_fields(name: Ident, content: ParseStream) -> syn::Result<Self> {
+        let mut code = None;
+        let mut case_input = None;
+        let mut expect = None;
+        while !content.is_empty() {
+            let field: Ident = content.parse()?;
+            content.parse::<Token![:]>()?;
+            let duplicate = match field.to_string().as_str() {
+                "code" => code.replace(content.parse()?).is_some(),
+                "input" => case_input.replace(content.parse()?).is_some(),
+                "expect" => expect.replace(content.parse()?).is_some(),
+                _ => {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        format!("unknown field `{}`", field),
+                    ))
+                }
+            };
+            if duplicate {
+                return Err(syn::Error::new(
+                    field.span(),
+                    format!("duplicate field `{}`", field),
+                ));
+            }
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<Token![,]>()?;
+        }
+
+        let code = code
+            .ok_or_else(|| syn::Error::new(name.span(), format!("`{}` is missing `code`", name)))?;
+        Ok(Self {
+            name,
+            code,
+            input: case_input,
+            expect,
+        })
+    }
+}
@@ -0,0 +1,170 @@
+//! Control-flow graphs of programs in Graphviz DOT format.
+
+/// Longest command run shown in a node label before it is shortened
+const MAX_LABEL_COMMANDS: usize = 16;
+
+/// A node of the graph
+enum Node {
+    /// Straight-line commands from `start` to `end` (inclusive character positions)
+    Run {
+        start: usize,
+        end: usize,
+        commands: String,
+    },
+    /// `[` at the given position, with the index of its matching `]` node
+    Open { position: usize, close: usize },
+    /// `]` at the given position, with the index of its matching `[` node
+    Close { position: usize, open: usize },
+}
+
+/// Render the control-flow graph of the code as a DOT digraph.
+///
+/// Straight-line runs of commands become boxes labelled with their position range, and
+/// each bracket becomes a diamond branching on whether the current cell is zero. The
+/// code must have matching brackets.
+pub(crate) fn to_dot(code: &str) -> String {
+    let nodes = build(code);
+    let name = |index: usize| {
+        if index < nodes.len() {
+            format!("n{}", index)
+        } else {
+            "end".to_string()
+        }
+    };
+
+    let mut out = String::from("digraph brainfuck {\n");
+    out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+    out.push_str("    start [shape=circle];\n");
+    out.push_str("    end [shape=doublecircle];\n");
+
+    for (i, node) in nodes.iter().enumerate() {
+        let label = match node {
+            Node::Run {
+                start,
+                end,
+                commands,
+            } => {
+                let shown = if commands.len() > MAX_LABEL_COMMANDS {
+                    format!("{}...", &commands[..MAX_LABEL_COMMANDS])
+                } else {
+                    commands.clone()
+                };
+                format!("[label=\"{}..{}\\n{}\"]", start, end, shown)
+            }
+            Node::Open { position, .. } => format!("[shape=diamond, label=\"{}\\n[\"]", position),
+            Node::Close { position, .. } => format!("[shape=diamond, label=\"{}\\n]\"]", position),
+        };
+        out.push_str(&format!("    n{} {};\n", i, label));
+    }
+
+    out.push_str(&format!("    start -> {};\n", name(0)));
+    for (i, node) in nodes.iter().enumerate() {
+        match node {
+            Node::Run { .. } => out.push_str(&format!("    n{} -> {};\n", i, name(i + 1))),
+            Node::Open { close, .. } => {
+                out.push_str(&format!(
+                    "    n{} -> {} [label=\"nonzero\"];\n",
+                    i,
+                    name(i + 1)
+                ));
+                out.push_str(&format!(
+                    "    n{} -> {} [label=\"zero\"];\n",
+                    i,
+                    name(close + 1)
+                ));
+            }
+            Node::Close { open, .. } => {
+                out.push_str(&format!(
+                    "    n{} -> {} [label=\"nonzero\"];\n",
+                    i,
+                    name(open + 1)
+                ));
+                out.push_str(&format!(
+                    "    n{} -> {} [label=\"zero\"];\n",
+                    i,
+                    name(i + 1)
+                ));
+            }
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Split the code into runs and brackets, linking matching brackets
+fn build(code: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut open_stack = Vec::new();
+
+    for (position, ch) in code.chars().enumerate() {
+        match ch {
+            '+' | '-' | '<' | '>' | '.' | ',' => {
+                if let Some(Node::Run { end, commands, .. }) = nodes.last_mut() {
+                    *end = position;
+                    commands.push(ch);
+                } else {
+                    nodes.push(Node::Run {
+                        start: position,
+                        end: position,
+                        commands: ch.to_string(),
+                    });
+                }
+            }
+            '[' => {
+                open_stack.push(nodes.len());
+                nodes.push(Node::Open { position, close: 0 });
+            }
+            ']' => {
+                let open = open_stack.pop().unwrap_or_default();
+                let close = nodes.len();
+                if let Some(Node::Open { close: link, .. }) = nodes.get_mut(open) {
+                    *link = close;
+                }
+                nodes.push(Node::Close { position, open });
+            }
+            _ => {}
+        }
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loop_graph() {
+        let expected = r#"digraph brainfuck {
+    node [shape=box, fontname="monospace"];
+    start [shape=circle];
+    end [shape=doublecircle];
+    n0 [label="0..1\n++"];
+    n1 [shape=diamond, label="3\n["];
+    n2 [label="4..4\n-"];
+    n3 [shape=diamond, label="5\n]"];
+    n4 [label="6..6\n."];
+    start -> n0;
+    n0 -> n1;
+    n1 -> n2 [label="nonzero"];
+    n1 -> n4 [label="zero"];
+    n2 -> n3;
+    n3 -> n2 [label="nonzero"];
+    n3 -> n4 [label="zero"];
+    n4 -> end;
+}
+"#;
+        assert_eq!(to_dot("++ [-]."), expected);
+    }
+
+    #[test]
+    fn test_empty_program() {
+        assert!(to_dot("just a comment").contains("    start -> end;\n"));
+    }
+
+    #[test]
+    fn test_long_runs_shortened() {
+        let dot = to_dot(&"+".repeat(20));
+        assert!(dot.contains("n0 [label=\"0..19\\n++++++++++++++++...\"];"));
+    }
+}
@@ -0,0 +1,246 @@
+//! Compile-time interpreter for COW.
+//!
+//! [COW](https://esolangs.org/wiki/COW) has twelve instructions, each a three letter
+//! variation of "moo"; all other text is ignored. Besides the Brainfuck-like commands it
+//! has a register, integer output and an instruction that executes the current cell as an
+//! instruction. Cells are bytes, and the tape size, step limit and errors are shared with
+//! the Brainfuck interpreter.
+
+use crate::{BrainfuckError, MAX_STEPS, TAPE_SIZE};
+
+/// The instructions, in the order of their numbers
+const INSTRUCTIONS: [&str; 12] = [
+    "moo", "mOo", "moO", "mOO", "Moo", "MOo", "MoO", "MOO", "OOO", "MMM", "OOM", "oom",
+];
+
+/// Instruction numbers with special meaning
+const LOOP_END: u8 = 0;
+const EXECUTE: u8 = 3;
+const LOOP_START: u8 = 7;
+
+/// Error returned when a COW program cannot be run to completion
+#[derive(Debug)]
+pub(crate) enum CowError {
+    /// A `MOO` without matching `moo`, at the given character position
+    UnmatchedLoopStart(usize),
+    /// A `moo` without matching `MOO`, at the given character position
+    UnmatchedLoopEnd(usize),
+    /// An error shared with Brainfuck
+    Execution(BrainfuckError),
+}
+
+impl From<BrainfuckError> for CowError {
+    fn from(error: BrainfuckError) -> Self {
+        CowError::Execution(error)
+    }
+}
+
+impl std::fmt::Display for CowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CowError::UnmatchedLoopStart(pos) => write!(f, "Unmatched `MOO` at position {}", pos),
+            CowError::UnmatchedLoopEnd(pos) => write!(f, "Unmatched `moo` at position {}", pos),
+            CowError::Execution(error) => error.fmt(f),
+        }
+    }
+}
+
+/// Split source into instructions with their character positions
+fn tokenize(source: &str) -> Vec<(usize, u8)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut instructions = Vec::new();
+    let mut position = 0;
+    while position + 3 <= chars.len() {
+        let word: String = chars[position..position + 3].iter().collect();
+        match INSTRUCTIONS
+            .iter()
+            .position(|&instruction| instruction == word)
+        {
+            Some(number) => {
+                instructions.push((position, number as u8));
+                position += 3;
+            }
+            None => position += 1,
+        }
+    }
+    instructions
+}
+
+/// What to do after an instruction
+enum Flow {
+    /// Continue with the instruction at this index
+    Jump(usize),
+    /// End the program
+    Exit,
+}
+
+/// The state of a running program
+struct Cow {
+    instructions: Vec<(usize, u8)>,
+    tape: Vec<u8>,
+    pointer: usize,
+    register: Option<u8>,
+    output: String,
+}
+
+impl Cow {
+    /// Execute instruction `number` as if it was at index `ip`
+    fn execute(&mut self, number: u8, ip: usize) -> Result<Flow, CowError> {
+        let cell = &mut self.tape[self.pointer];
+        match number {
+            LOOP_END => return self.loop_end(ip).map(Flow::Jump),
+            1 => {
+                if self.pointer == 0 {
+                    return Err(BrainfuckError::PointerUnderflow.into());
+                }
+                self.pointer -= 1;
+            }
+            2 => {
+                if self.pointer >= TAPE_SIZE - 1 {
+                    return Err(BrainfuckError::PointerOverflow(TAPE_SIZE).into());
+                }
+                self.pointer += 1;
+            }
+            EXECUTE => match *cell {
+                // Executing this instruction itself would loop forever, so it exits
+                EXECUTE | 12.. => return Ok(Flow::Exit),
+                number => return self.execute(number, ip),
+            },
+            4 if *cell == 0 => return Err(BrainfuckError::InputNotSupported.into()),
+            4 => self.output.push(*cell as char),
+            5 => *cell = cell.wrapping_sub(1),
+            6 => *cell = cell.wrapping_add(1),
+            LOOP_START if *cell == 0 => return self.skip_loop(ip).map(Flow::Jump),
+            8 => *cell = 0,
+            9 => match self.register.take() {
+                Some(value) => *cell = value,
+                None => self.register = Some(*cell),
+            },
+            10 => self.output.push_str(&format!("{}\n", cell)),
+            11 => return Err(BrainfuckError::InputNotSupported.into()),
+            _ => {}
+        }
+        Ok(Flow::Jump(ip + 1))
+    }
+
+    /// The index after the `moo` matching the `MOO` at `ip`. The instruction right after
+    /// the `MOO` is not considered, so it cannot be the matching `moo`.
+    fn skip_loop(&self, ip: usize) -> Result<usize, CowError> {
+        let mut depth = 0;
+        for index in ip + 2..self.instructions.len() {
+            match self.instructions[index].1 {
+                LOOP_START => depth += 1,
+                LOOP_END if depth == 0 => return Ok(index + 1),
+                LOOP_END => depth -= 1,
+                _ => {}
+            }
+        }
+        Err(CowError::UnmatchedLoopStart(self.position(ip)))
+    }
+
+    /// The index of the `MOO` matching the `moo` at `ip`. The instruction right before the
+    /// `moo` is not considered, so it cannot be the matching `MOO`.
+    fn loop_end(&self, ip: usize) -> Result<usize, CowError> {
+        let mut depth = 0;
+        for index in (0..ip.saturating_sub(1)).rev() {
+            match self.instructions[index].1 {
+                LOOP_END => depth += 1,
+                LOOP_START if depth == 0 => return Ok(index),
+                LOOP_START => depth -= 1,
+                _ => {}
+            }
+        }
+        Err(CowError::UnmatchedLoopEnd(self.position(ip)))
+    }
+
+    /// The character position of the instruction at `ip`
+    fn position(&self, ip: usize) -> usize {
+        self.instructions[ip].0
+    }
+}
+
+/// Execute COW code and return the output
+pub(crate) fn execute(code: &str) -> Result<String, CowError> {
+    let mut cow = Cow {
+        instructions: tokenize(code),
+        tape: vec![0; TAPE_SIZE],
+        pointer: 0,
+        register: None,
+        output: String::new(),
+    };
+
+    let mut ip = 0;
+    let mut steps = 0;
+    while let Some(&(_, number)) = cow.instructions.get(ip) {
+        if steps >= MAX_STEPS {
+            return Err(BrainfuckError::MaxStepsExceeded(MAX_STEPS).into());
+        }
+        steps += 1;
+
+        match cow.execute(number, ip)? {
+            Flow::Jump(next) => ip = next,
+            Flow::Exit => break,
+        }
+    }
+    Ok(cow.output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("MoO xx moomOO MMMoom"),
+            vec![(0, 6), (7, 0), (10, 3), (14, 9), (17, 11)]
+        );
+    }
+
+    #[test]
+    fn test_loop() {
+        // Add 2 to cell 1 three times, then print it
+        let code = "MoO MoO MoO MOO moO MoO MoO mOo MOo moo moO OOM";
+        assert_eq!(execute(code).unwrap(), "6\n");
+        // The loop is skipped when the cell is 0
+        assert_eq!(execute("MOO MoO moo OOM").unwrap(), "0\n");
+    }
+
+    #[test]
+    fn test_register_and_execute() {
+        // Copy 2 into the register, clear the cell, paste it back and print it
+        assert_eq!(execute("MoO MoO MMM OOO MMM OOM").unwrap(), "2\n");
+        // Cell 6 executes MoO; cell 3 exits
+        assert_eq!(execute("MoO MoO MoO MoO MoO MoO mOO OOM").unwrap(), "7\n");
+        assert_eq!(execute("MoO MoO MoO mOO OOM").unwrap(), "");
+    }
+
+    #[test]
+    fn test_skipped_neighbour() {
+        // The `moo` right after `MOO` is skipped when searching, so the second one matches
+        assert_eq!(execute("MOO moo MoO moo OOM").unwrap(), "0\n");
+        assert!(matches!(
+            execute("MOO moo"),
+            Err(CowError::UnmatchedLoopStart(0))
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            execute("OOO MoO moo"),
+            Err(CowError::UnmatchedLoopEnd(8))
+        ));
+        assert!(matches!(
+            execute("Moo"),
+            Err(CowError::Execution(BrainfuckError::InputNotSupported))
+        ));
+        // An odd cell increased by 2 never reaches 0
+        assert!(matches!(
+            execute("MoO MOO MoO MoO moo"),
+            Err(CowError::Execution(BrainfuckError::MaxStepsExceeded(
+                MAX_STEPS
+            )))
+        ));
+    }
+}
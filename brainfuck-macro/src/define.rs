@@ -0,0 +1,91 @@
+//! Programs defined once with `brainfuck_define!` and used by name with `use NAME`.
+//!
+//! A procedural macro cannot see what other invocations expanded to, so the registry is
+//! made of `macro_rules!` macros: every definition becomes a macro named like the program,
+//! holding its code. `brainfuck!(use NAME, ...)` expands to a call of that macro, which
+//! calls `brainfuck!` back with the code in place of `use NAME`. Definitions are therefore
+//! scoped like `macro_rules!` macros: they can be used after the definition, in the same
+//! module and its child modules.
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Token};
+
+use crate::source::Code;
+
+/// The arguments of `brainfuck_define!`: `NAME = code`
+pub(crate) struct Definition {
+    name: Ident,
+    code: Code,
+}
+
+impl Parse for Definition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let code = input.parse()?;
+        input.parse::<Option<Token![;]>>()?;
+        Ok(Self { name, code })
+    }
+}
+
+impl Definition {
+    /// The macro recording the code, followed by items tracking the files it was read from
+    pub fn expand(&self) -> TokenStream {
+        let name = &self.name;
+        let literal = self.code.literal();
+        let tracking = self.code.tracking();
+        quote! {
+            #[allow(unused_macros)]
+            macro_rules! #name {
+                (($($callback:tt)*) $($rest:tt)*) => {
+                    $($callback)*! { #literal $($rest)* }
+                };
+            }
+            #tracking
+        }
+    }
+}
+
+/// If the input of the macro `callback` starts with `use NAME`, expand to a call of the
+/// program defined as `NAME`, passing the rest of the input along
+pub(crate) fn redirect(callback: &str, input: TokenStream) -> Option<TokenStream> {
+    let mut tokens = input.into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(keyword)) if keyword == "use" => {}
+        _ => return None,
+    }
+    let Some(TokenTree::Ident(name)) = tokens.next() else {
+        return None;
+    };
+    let callback = Ident::new(callback, proc_macro2::Span::call_site());
+    let rest: TokenStream = tokens.collect();
+    Some(quote! {
+        #name! { (::brainfuck_macro::#callback) #rest }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect() {
+        let expanded = redirect("brainfuck", quote!(use GREETER, dialect = "ook")).unwrap();
+        assert_eq!(
+            expanded.to_string(),
+            quote!(GREETER! { (::brainfuck_macro::brainfuck), dialect = "ook" }).to_string()
+        );
+        assert!(redirect("brainfuck", quote!("+.")).is_none());
+        assert!(redirect("brainfuck", quote!(use "+.")).is_none());
+    }
+
+    #[test]
+    fn test_expand() {
+        let definition: Definition = syn::parse2(quote!(GREETER = "+" "+.")).unwrap();
+        let expanded = definition.expand().to_string();
+        assert!(expanded.contains("macro_rules ! GREETER"));
+        assert!(expanded.contains("\"++.\""));
+    }
+}
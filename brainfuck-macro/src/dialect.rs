@@ -0,0 +1,590 @@
+//! Brainfuck dialects that substitute a fixed token for each of the eight commands.
+//!
+//! Programs can be encoded into and decoded from any dialect. Decoding keeps track of
+//! where each command came from, so errors point at the original tokens.
+//!
+//! [`Substitution`] covers dialects defined by the user, whose tokens are arbitrary
+//! strings without separators.
+
+use crate::frontend::{DecodeError, Decoded, DialectFrontend};
+
+/// The commands in the order of [`Dialect::tokens`]
+pub(crate) const COMMANDS: [char; 8] = ['>', '<', '+', '-', '.', ',', '[', ']'];
+
+/// A substitution dialect: one token per command, joined by a separator
+pub(crate) struct Dialect {
+    pub name: &'static str,
+    /// The name as written in messages
+    pub title: &'static str,
+    /// Tokens for `>`, `<`, `+`, `-`, `.`, `,`, `[` and `]`
+    tokens: [&'static str; 8],
+    separator: &'static str,
+    /// Tokens beyond the eight commands, decoded to an extension command or to nothing
+    extra: &'static [(&'static str, Option<char>)],
+    /// Characters that may appear anywhere without being part of a token
+    comment: fn(char) -> bool,
+    /// Whether words match tokens regardless of case. The tokens must be lowercase.
+    ignore_case: bool,
+}
+
+/// All supported dialects
+pub(crate) const DIALECTS: &[Dialect] = &[
+    Dialect {
+        name: "ook",
+        title: "Ook!",
+        tokens: [
+            "Ook. Ook?",
+            "Ook? Ook.",
+            "Ook. Ook.",
+            "Ook! Ook!",
+            "Ook! Ook.",
+            "Ook. Ook!",
+            "Ook! Ook?",
+            "Ook? Ook!",
+        ],
+        separator: " ",
+        extra: &[],
+        comment: char::is_whitespace,
+        ignore_case: false,
+    },
+    Dialect {
+        name: "blub",
+        title: "Blub",
+        tokens: [
+            "Blub. Blub?",
+            "Blub? Blub.",
+            "Blub. Blub.",
+            "Blub! Blub!",
+            "Blub! Blub.",
+            "Blub. Blub!",
+            "Blub! Blub?",
+            "Blub? Blub!",
+        ],
+        separator: " ",
+        extra: &[],
+        comment: char::is_whitespace,
+        ignore_case: false,
+    },
+    Dialect {
+        name: "alphuck",
+        title: "Alphuck",
+        tokens: ["a", "c", "e", "i", "j", "o", "p", "s"],
+        separator: "",
+        extra: &[],
+        // Other letters are most likely typos rather than comments
+        comment: |ch| !ch.is_alphabetic(),
+        ignore_case: false,
+    },
+    Dialect {
+        name: "pikalang",
+        title: "Pikalang",
+        tokens: [
+            "pipi", "pichu", "pi", "ka", "pikachu", "pikapi", "pika", "chu",
+        ],
+        separator: " ",
+        extra: &[],
+        comment: char::is_whitespace,
+        ignore_case: true,
+    },
+    Dialect {
+        name: "spoon",
+        title: "Spoon",
+        tokens: [
+            "010", "011", "1", "000", "001010", "0010110", "00100", "0011",
+        ],
+        separator: "",
+        // EXIT ends the program; DUMP prints the memory for debugging, which is not
+        // possible at compile time
+        extra: &[("00101111", Some('@')), ("00101110", None)],
+        comment: char::is_whitespace,
+        ignore_case: false,
+    },
+    Dialect {
+        name: "morsefuck",
+        title: "Morsefuck",
+        tokens: [".--", "--.", "..-", "-..", "-.-", ".-.", "---", "..."],
+        separator: "",
+        extra: &[],
+        comment: char::is_whitespace,
+        ignore_case: false,
+    },
+];
+
+/// Look up a dialect by name
+pub(crate) fn find(name: &str) -> Option<&'static Dialect> {
+    DIALECTS.iter().find(|dialect| dialect.name == name)
+}
+
+impl Dialect {
+    /// Encode the commands of Brainfuck code in this dialect, dropping comments
+    pub fn encode(&self, code: &str) -> String {
+        let tokens: Vec<&str> = code
+            .chars()
+            .filter_map(|ch| COMMANDS.iter().position(|&command| command == ch))
+            .map(|index| self.tokens[index])
+            .collect();
+        tokens.join(self.separator)
+    }
+
+    /// The token for a Brainfuck command
+    pub fn token(&self, command: char) -> &'static str {
+        let index = COMMANDS
+            .iter()
+            .position(|&c| c == command)
+            .expect("not a Brainfuck command");
+        self.tokens[index]
+    }
+
+    /// Decode a program written in this dialect into Brainfuck.
+    ///
+    /// The result may contain `@`, ending the program, if the dialect has such a command.
+    pub fn decode(&self, source: &str) -> Result<Decoded, DecodeError> {
+        if self.separator.is_empty() {
+            self.decode_packed(source, false)
+        } else {
+            self.decode_words(source)
+        }
+    }
+
+    /// Decode a program written in a dialect whose tokens have no separators, treating
+    /// every character that does not start a token as a comment
+    pub fn decode_lenient(&self, source: &str) -> Decoded {
+        assert!(self.separator.is_empty(), "tokens must not have separators");
+        self.decode_packed(source, true)
+            .expect("lenient decoding never fails")
+    }
+
+    /// Decode a dialect whose tokens are written without separators. Comment characters
+    /// are ignored, even inside a token. Every other character must be part of a token,
+    /// unless `lenient` is set.
+    fn decode_packed(&self, source: &str, lenient: bool) -> Result<Decoded, DecodeError> {
+        let chars: Vec<(usize, char)> = source
+            .chars()
+            .enumerate()
+            .filter(|&(_, ch)| !(self.comment)(ch))
+            .collect();
+        let tokens: Vec<(Vec<char>, Option<char>)> = COMMANDS
+            .iter()
+            .zip(self.tokens)
+            .map(|(&command, token)| (token, Some(command)))
+            .chain(self.extra.iter().copied())
+            .map(|(token, command)| (token.chars().collect(), command))
+            .collect();
+
+        let mut decoded = Decoded {
+            code: String::new(),
+            positions: Vec::new(),
+        };
+        let mut index = 0;
+        while let Some(&(position, _)) = chars.get(index) {
+            let rest: Vec<char> = chars[index..].iter().map(|&(_, ch)| ch).collect();
+            let Some((token, command)) = tokens.iter().find(|(token, _)| rest.starts_with(token))
+            else {
+                if lenient {
+                    index += 1;
+                    continue;
+                }
+                // How far the source follows some token before going wrong
+                let matched = tokens
+                    .iter()
+                    .map(|(token, _)| token.iter().zip(&rest).take_while(|(a, b)| a == b).count())
+                    .max()
+                    .unwrap_or(0);
+                return Err(match chars.get(index + matched) {
+                    Some(&(position, ch)) => DecodeError {
+                        position,
+                        message: format!("Unexpected character `{}`", ch),
+                        help: None,
+                    },
+                    None => DecodeError {
+                        position,
+                        message: format!("Incomplete token `{}`", rest.iter().collect::<String>()),
+                        help: None,
+                    },
+                });
+            };
+            if let Some(command) = command {
+                decoded.code.push(*command);
+                decoded.positions.push(position);
+            }
+            index += token.len();
+        }
+        Ok(decoded)
+    }
+
+    /// Decode a dialect whose tokens are made of words. The source is split into words at
+    /// whitespace, and consecutive words are matched against the tokens. Every word must
+    /// be part of a token.
+    fn decode_words(&self, source: &str) -> Result<Decoded, DecodeError> {
+        let words = words(source);
+        let normalized: Vec<String> = words
+            .iter()
+            .map(|&(_, word)| match self.ignore_case {
+                true => word.to_lowercase(),
+                false => word.to_string(),
+            })
+            .collect();
+        let tokens: Vec<Vec<&str>> = self
+            .tokens
+            .iter()
+            .map(|token| token.split(' ').collect())
+            .collect();
+
+        let mut decoded = Decoded {
+            code: String::new(),
+            positions: Vec::new(),
+        };
+        let mut index = 0;
+        while let Some(&(position, word)) = words.get(index) {
+            let rest: Vec<&str> = normalized[index..].iter().map(String::as_str).collect();
+            let matched = tokens
+                .iter()
+                .enumerate()
+                .filter(|(_, token)| rest.starts_with(token))
+                .max_by_key(|(_, token)| token.len());
+            let Some((command, token)) = matched else {
+                let starts_token = tokens.iter().any(|token| token[0] == rest[0]);
+                let next = words.get(index + 1).map(|&(_, next)| next);
+                let (message, help) = match next {
+                    Some(next) if starts_token => {
+                        (format!("Invalid token `{} {}`", word, next), None)
+                    }
+                    None if starts_token => (format!("Incomplete token `{}`", word), None),
+                    _ => {
+                        let help = closest_word(rest[0], tokens.iter().flatten().copied())
+                            .map(|closest| format!("did you mean `{}`?", closest));
+                        (format!("Unknown word `{}`", word), help)
+                    }
+                };
+                return Err(DecodeError {
+                    position,
+                    message,
+                    help,
+                });
+            };
+            decoded.code.push(COMMANDS[command]);
+            decoded.positions.push(position);
+            index += token.len();
+        }
+        Ok(decoded)
+    }
+}
+
+impl DialectFrontend for Dialect {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn title(&self) -> &'static str {
+        self.title
+    }
+
+    fn decode(&self, source: &str) -> Result<Decoded, DecodeError> {
+        Dialect::decode(self, source)
+    }
+
+    fn encode(&self, code: &str) -> String {
+        Dialect::encode(self, code)
+    }
+
+    fn token_at(&self, _source: &str, command: char, _position: usize) -> String {
+        self.token(command).to_string()
+    }
+}
+
+/// A user-defined dialect: one token per command, written without separators.
+///
+/// No token may be a prefix of another, so the source can be decoded unambiguously by
+/// matching tokens from left to right. Characters that do not start a token are comments.
+pub(crate) struct Substitution {
+    /// Tokens for `>`, `<`, `+`, `-`, `.`, `,`, `[` and `]`
+    tokens: [String; 8],
+}
+
+impl Substitution {
+    /// Check that the tokens can be decoded unambiguously. On error, returns the index of
+    /// the offending token along with a message.
+    pub fn new(tokens: [String; 8]) -> Result<Self, (usize, String)> {
+        if let Some(index) = tokens.iter().position(String::is_empty) {
+            let message = format!("the token for `{}` is empty", COMMANDS[index]);
+            return Err((index, message));
+        }
+        for (index, token) in tokens.iter().enumerate() {
+            let conflict = tokens
+                .iter()
+                .enumerate()
+                .find(|&(other, prefix)| other != index && token.starts_with(prefix.as_str()));
+            if let Some((other, prefix)) = conflict {
+                let message = if prefix == token {
+                    format!(
+                        "`{}` and `{}` share the token \"{}\"",
+                        COMMANDS[index], COMMANDS[other], token
+                    )
+                } else {
+                    format!(
+                        "the token \"{}\" for `{}` is a prefix of the token \"{}\" for `{}`",
+                        prefix, COMMANDS[other], token, COMMANDS[index]
+                    )
+                };
+                return Err((index, message));
+            }
+        }
+        Ok(Self { tokens })
+    }
+
+    /// The token for a Brainfuck command
+    pub fn token(&self, command: char) -> &str {
+        let index = COMMANDS
+            .iter()
+            .position(|&c| c == command)
+            .expect("not a Brainfuck command");
+        &self.tokens[index]
+    }
+
+    /// Decode a program written in this dialect into Brainfuck, skipping comments
+    pub fn decode(&self, source: &str) -> Decoded {
+        let mut decoded = Decoded {
+            code: String::new(),
+            positions: Vec::new(),
+        };
+        let mut skip = 0;
+        for (position, (byte, _)) in source.char_indices().enumerate() {
+            if skip > 0 {
+                // Inside a token that was already decoded
+                skip -= 1;
+                continue;
+            }
+            let rest = &source[byte..];
+            if let Some(command) = self
+                .tokens
+                .iter()
+                .position(|token| rest.starts_with(token.as_str()))
+            {
+                decoded.code.push(COMMANDS[command]);
+                decoded.positions.push(position);
+                skip = self.tokens[command].chars().count() - 1;
+            }
+        }
+        decoded
+    }
+}
+
+/// The candidate closest to a misspelled word, if any is close enough to be a plausible
+/// suggestion
+fn closest_word<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (word.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the processed prefix of `a` to every prefix of `b`
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Split source into whitespace-separated words with their character positions
+fn words(source: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (position, (byte, ch)) in source.char_indices().enumerate() {
+        match (start, ch.is_whitespace()) {
+            (None, false) => start = Some((position, byte)),
+            (Some((word_position, word_byte)), true) => {
+                words.push((word_position, &source[word_byte..byte]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((word_position, word_byte)) = start {
+        words.push((word_position, &source[word_byte..]));
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let code = "+[-]> comment .";
+        assert_eq!(
+            find("ook").unwrap().encode(code),
+            "Ook. Ook. Ook! Ook? Ook! Ook! Ook? Ook! Ook. Ook? Ook! Ook."
+        );
+        assert_eq!(find("alphuck").unwrap().encode(code), "episaj");
+        assert_eq!(
+            find("spoon").unwrap().encode(code),
+            "1001000000011010001010"
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let ook = find("ook").unwrap();
+        let decoded = ook.decode("Ook. Ook.\n  Ook! Ook?  Ook? Ook!").unwrap();
+        assert_eq!(decoded.code, "+[]");
+        assert_eq!(decoded.positions, vec![0, 12, 23]);
+        let source = ook.encode("++[->+<]>.");
+        assert_eq!(ook.decode(&source).unwrap().code, "++[->+<]>.");
+    }
+
+    #[test]
+    fn test_decode_packed() {
+        let spoon = find("spoon").unwrap();
+        let decoded = spoon
+            .decode("11 00100 000\n0011 00101110 010 001010 00101111 1")
+            .unwrap();
+        assert_eq!(decoded.code, "++[-]>.@+");
+        assert_eq!(decoded.positions, vec![0, 1, 3, 9, 13, 27, 31, 38, 47]);
+        let source = spoon.encode("++[->+<]>.");
+        assert_eq!(spoon.decode(&source).unwrap().code, "++[->+<]>.");
+    }
+
+    #[test]
+    fn test_decode_packed_errors() {
+        let spoon = find("spoon").unwrap();
+        let error = |source| spoon.decode(source).err().unwrap().to_string();
+        assert_eq!(error("1 1 0012"), "Unexpected character `2` at position 7");
+        assert_eq!(error("1 00 1"), "Incomplete token `001` at position 2");
+        assert_eq!(error("1 001"), "Incomplete token `001` at position 2");
+    }
+
+    #[test]
+    fn test_morsefuck_triplets() {
+        let morsefuck = find("morsefuck").unwrap();
+        let decoded = morsefuck.decode("..-..- ---\n-.. ...").unwrap();
+        assert_eq!(decoded.code, "++[-]");
+        assert_eq!(decoded.positions, vec![0, 3, 7, 11, 15]);
+        assert_eq!(
+            morsefuck.decode("..- .-").err().unwrap().to_string(),
+            "Incomplete token `.-` at position 4"
+        );
+        assert_eq!(
+            morsefuck.decode("..- .:-").err().unwrap().to_string(),
+            "Unexpected character `:` at position 5"
+        );
+    }
+
+    #[test]
+    fn test_alphuck_comments() {
+        let alphuck = find("alphuck").unwrap();
+        assert_eq!(alphuck.decode("ee, p (e!) s.").unwrap().code, "++[+]");
+        assert_eq!(
+            alphuck
+                .decode("ee pes # increment")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unexpected character `n` at position 10"
+        );
+        // The letters of comments are decoded as commands where they match
+        assert_eq!(
+            alphuck.decode_lenient("ee pes # increment").code,
+            "++[+]-<++"
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let ook = find("ook").unwrap();
+        let error = |source| ook.decode(source).err().unwrap().to_string();
+        assert_eq!(
+            error("Ook. Ook? Ook?"),
+            "Incomplete token `Ook?` at position 10"
+        );
+        assert_eq!(
+            error("Ook? Ook?"),
+            "Invalid token `Ook? Ook?` at position 0"
+        );
+        assert_eq!(
+            error("Ook. Ook. Eek!"),
+            "Unknown word `Eek!` at position 10"
+        );
+    }
+
+    fn substitution(tokens: [&str; 8]) -> Result<Substitution, (usize, String)> {
+        Substitution::new(tokens.map(String::from))
+    }
+
+    #[test]
+    fn test_substitution_decode() {
+        let tokens = ["r", "l", "inc", "dec", "out", "get", "(", ")"];
+        let dialect = substitution(tokens).unwrap();
+        let decoded = dialect.decode("inc inc (dec r inc l) r out");
+        assert_eq!(decoded.code, "++[->+<]>.");
+        assert_eq!(decoded.positions, vec![0, 4, 8, 9, 13, 15, 19, 20, 22, 24]);
+        assert_eq!(dialect.token('['), "(");
+    }
+
+    #[test]
+    fn test_substitution_ambiguity() {
+        let tokens = ["a", "ab", "c", "d", "e", "f", "g", "h"];
+        assert_eq!(
+            substitution(tokens).err().unwrap(),
+            (
+                1,
+                "the token \"a\" for `>` is a prefix of the token \"ab\" for `<`".to_string()
+            )
+        );
+        let tokens = ["a", "b", "c", "d", "e", "f", "g", "a"];
+        assert_eq!(
+            substitution(tokens).err().unwrap(),
+            (0, "`>` and `]` share the token \"a\"".to_string())
+        );
+        let tokens = ["a", "b", "", "d", "e", "f", "g", "h"];
+        assert_eq!(
+            substitution(tokens).err().unwrap(),
+            (2, "the token for `+` is empty".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pikalang_case_and_suggestions() {
+        let pikalang = find("pikalang").unwrap();
+        assert_eq!(pikalang.decode("Pi PI pika KA chu").unwrap().code, "++[-]");
+        assert_eq!(
+            pikalang.decode("pi Pikachuu").err().unwrap().to_string(),
+            "Unknown word `Pikachuu` at position 3; did you mean `pikachu`?"
+        );
+        assert_eq!(
+            pikalang.decode("pi bulbasaur").err().unwrap().to_string(),
+            "Unknown word `bulbasaur` at position 3"
+        );
+        // Other dialects are case sensitive
+        let ook = find("ook").unwrap();
+        assert_eq!(
+            ook.decode("ook. Ook.").err().unwrap().to_string(),
+            "Unknown word `ook.` at position 0; did you mean `Ook.`?"
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("pikachu", "pikachu"), 0);
+        assert_eq!(edit_distance("pikchu", "pikachu"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "pi"), 2);
+    }
+
+    #[test]
+    fn test_unknown_dialect() {
+        assert!(find("klingon").is_none());
+    }
+}
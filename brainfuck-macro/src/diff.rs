@@ -0,0 +1,46 @@
+//! Readable descriptions of mismatching outputs.
+
+/// Index of the first character at which two strings differ, if any
+pub(crate) fn first_difference(expected: &str, actual: &str) -> Option<usize> {
+    let mut expected_chars = expected.chars();
+    let mut actual_chars = actual.chars();
+    let mut index = 0;
+    loop {
+        match (expected_chars.next(), actual_chars.next()) {
+            (None, None) => return None,
+            (e, a) if e != a => return Some(index),
+            _ => index += 1,
+        }
+    }
+}
+
+/// Describe how `actual` differs from `expected`, or `None` if they are equal
+pub(crate) fn describe_mismatch(expected: &str, actual: &str) -> Option<String> {
+    let index = first_difference(expected, actual)?;
+    Some(format!(
+        "output differs at byte {}\n  expected: {:?}\n    actual: {:?}",
+        index, expected, actual
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference("abc", "abc"), None);
+        assert_eq!(first_difference("abc", "abd"), Some(2));
+        assert_eq!(first_difference("abc", "ab"), Some(2));
+        assert_eq!(first_difference("", "x"), Some(0));
+    }
+
+    #[test]
+    fn test_describe_mismatch() {
+        assert_eq!(describe_mismatch("A", "A"), None);
+        assert_eq!(
+            describe_mismatch("Hi\n", "Ho").unwrap(),
+            "output differs at byte 1\n  expected: \"Hi\\n\"\n    actual: \"Ho\""
+        );
+    }
+}
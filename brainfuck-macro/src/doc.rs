@@ -0,0 +1,87 @@
+//! Rendering of rustdoc sections describing a program.
+
+/// Render the documentation for a program: the formatted source, a table pairing each
+/// commented source line with its comment, and the output.
+pub(crate) fn render(code: &str, output: &str) -> String {
+    let mut doc = render_program(code);
+
+    let annotations = annotate(code);
+    if !annotations.is_empty() {
+        doc.push_str("\n# Annotations\n\n| Line | Code | Note |\n| ---: | --- | --- |\n");
+        for (line, commands, note) in annotations {
+            doc.push_str(&format!(
+                "| {} | `{}` | {} |\n",
+                line,
+                commands,
+                note.replace('|', "\\|")
+            ));
+        }
+    }
+
+    doc.push_str("\n# Output\n\n```text\n");
+    doc.push_str(output);
+    if !output.ends_with('\n') {
+        doc.push('\n');
+    }
+    doc.push_str("```\n");
+    doc
+}
+
+/// Render the formatted source of a program under a `# Program` heading
+pub(crate) fn render_program(code: &str) -> String {
+    let formatted = brainfuck_core::format::format(code, &Default::default())
+        .unwrap_or_else(|_| code.trim().to_string());
+    format!("# Program\n\n```text\n{}\n```\n", formatted)
+}
+
+/// Split each source line that has both commands and comment text into
+/// `(line number, commands, comment)`
+fn annotate(code: &str) -> Vec<(usize, String, String)> {
+    code.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let commands: String = line.chars().filter(|c| "<>+-.,[]".contains(*c)).collect();
+            let note = line
+                .chars()
+                .filter(|c| !"<>+-.,[]".contains(*c))
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            (!commands.is_empty() && !note.is_empty()).then_some((i + 1, commands, note))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let code = "+++++  Set cell 0 to 5\n[>+++++++++++++<-]\n>.  Print A";
+        let expected = "\
+# Program
+
+```text
++++++
+[>+++++++++++++<-]
+>.
+```
+
+# Annotations
+
+| Line | Code | Note |
+| ---: | --- | --- |
+| 1 | `+++++` | Set cell 0 to 5 |
+| 3 | `>.` | Print A |
+
+# Output
+
+```text
+A
+```
+";
+        assert_eq!(render(code, "A"), expected);
+    }
+}
@@ -0,0 +1,36 @@
+//! Opt-in extensions of the instruction set, selected with the `extensions` option.
+//!
+//! The option takes a comma-separated list of extension names. Without it, every
+//! character other than the eight Brainfuck commands is a comment, as in plain Brainfuck.
+
+use brainfuck_core::machine::Extensions;
+use syn::LitStr;
+
+/// Parse the comma-separated list of extension names of the option
+pub(crate) fn parse(list: &LitStr) -> syn::Result<Extensions> {
+    Extensions::parse(&list.value()).map_err(|message| syn::Error::new(list.span(), message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(list: &str) -> syn::Result<Extensions> {
+        super::parse(&LitStr::new(list, proc_macro2::Span::call_site()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse(" extended ").unwrap().extended);
+        assert!(parse("extended,brainfork").unwrap().brainfork);
+        assert_eq!(
+            parse("extended, klingon").err().unwrap().to_string(),
+            "unknown extension `klingon`; supported extensions are extended, end, brainfork, bitwise, tapes, decimal"
+        );
+        assert_eq!(
+            parse("extended,extended").err().unwrap().to_string(),
+            "duplicate extension `extended`"
+        );
+        assert!(parse("bitwise, extended").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Loading program sources from the invoking crate's directory.
+
+use std::path::{Path, PathBuf};
+
+use quote::quote;
+
+/// Resolve a path relative to `CARGO_MANIFEST_DIR` of the crate being compiled
+pub(crate) fn resolve(relative: &str) -> PathBuf {
+    let base = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    base.join(relative)
+}
+
+/// Read a file relative to `CARGO_MANIFEST_DIR`, returning its full path and contents
+pub(crate) fn read_relative(relative: &str) -> Result<(PathBuf, String), String> {
+    let path = resolve(relative);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Brainfuck file '{}': {}", path.display(), e))?;
+    Ok((path, contents))
+}
+
+/// Tokens that make the compiler track `path`, so changes to it trigger a rebuild
+pub(crate) fn track(path: &Path) -> proc_macro2::TokenStream {
+    let path = path.to_string_lossy();
+    quote! {
+        const _: &[u8] = include_bytes!(#path);
+    }
+}
+
+/// Find the Brainfuck programs (`.b` or `.bf` files) matching a glob relative to
+/// `CARGO_MANIFEST_DIR`, sorted by path.
+///
+/// Only the final path component may contain wildcards (`*` and `?`). A pattern naming a
+/// directory matches every program inside it.
+pub(crate) fn find_programs(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let full = resolve(pattern);
+    let (dir, file_pattern) = if full.is_dir() {
+        (full, "*".to_string())
+    } else {
+        let file_pattern = full
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        (
+            full.parent().map(Path::to_path_buf).unwrap_or_default(),
+            file_pattern,
+        )
+    };
+
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read directory '{}': {}", dir.display(), e))?;
+
+    let mut programs = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read directory '{}': {}", dir.display(), e))?
+            .path();
+        let is_program = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("b") | Some("bf")
+        );
+        let name = path.file_name().map(|name| name.to_string_lossy());
+        if is_program
+            && path.is_file()
+            && name.is_some_and(|name| wildcard_match(&file_pattern, &name))
+        {
+            programs.push(path);
+        }
+    }
+
+    programs.sort();
+    Ok(programs)
+}
+
+/// Read the input for `,` from the `.in` file next to a program, if there is one
+pub(crate) fn read_input(program: &Path) -> Result<Option<Vec<u8>>, String> {
+    let path = program.with_extension("in");
+    match std::fs::read(&path) {
+        Ok(input) => Ok(Some(input)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!(
+            "Failed to read input file '{}': {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+/// Name the constant of each program after its file stem, failing if two programs
+/// (such as `x.b` and `x.bf`) would define the same constant
+pub(crate) fn const_names(programs: &[PathBuf]) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = Vec::with_capacity(programs.len());
+    for (i, path) in programs.iter().enumerate() {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = const_name(&stem);
+        if let Some(first) = names.iter().position(|other| *other == name) {
+            return Err(format!(
+                "Brainfuck programs '{}' and '{}' both define the constant `{}`",
+                programs[first].display(),
+                programs[i].display(),
+                name
+            ));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters and `?` any one
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Turn a file stem into a `SCREAMING_SNAKE_CASE` constant name
+pub(crate) fn const_name(stem: &str) -> String {
+    let mut name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    // A lone `_` is not a name, `const _` would define nothing
+    if name == "_" {
+        name.insert(0, 'P');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.b", "hello.b"));
+        assert!(wildcard_match("*", "hello.bf"));
+        assert!(wildcard_match("h?llo*", "hello.b"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("*.b", "hello.bf"));
+        assert!(!wildcard_match("h?llo", "hllo"));
+    }
+
+    #[test]
+    fn test_const_name() {
+        assert_eq!(const_name("hello"), "HELLO");
+        assert_eq!(const_name("hello-world.v2"), "HELLO_WORLD_V2");
+        assert_eq!(const_name("99bottles"), "_99BOTTLES");
+        assert_eq!(const_name("-"), "P_");
+        assert_eq!(const_name("_"), "P_");
+        assert_eq!(const_name("--"), "__");
+    }
+
+    #[test]
+    fn test_const_names() {
+        let programs = [PathBuf::from("p/hello-world.b"), PathBuf::from("p/x.b")];
+        assert_eq!(const_names(&programs).unwrap(), ["HELLO_WORLD", "X"]);
+        let programs = [
+            PathBuf::from("p/hello_world.b"),
+            PathBuf::from("p/x.b"),
+            PathBuf::from("p/x.bf"),
+        ];
+        assert_eq!(
+            const_names(&programs).unwrap_err(),
+            "Brainfuck programs 'p/x.b' and 'p/x.bf' both define the constant `X`"
+        );
+    }
+
+    #[test]
+    fn test_read_input() {
+        let dir = std::env::temp_dir().join("brainfuck_macro_read_input_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("echo.b");
+        let _ = std::fs::remove_file(dir.join("echo.in"));
+        assert_eq!(read_input(&program), Ok(None));
+        std::fs::write(dir.join("echo.in"), "hi").unwrap();
+        assert_eq!(read_input(&program), Ok(Some(b"hi".to_vec())));
+        std::fs::remove_file(dir.join("echo.in")).unwrap();
+        // An `.in` that exists but cannot be read is an error, not a missing input
+        std::fs::create_dir(dir.join("echo.in")).unwrap();
+        let error = read_input(&program).unwrap_err();
+        assert!(error.starts_with("Failed to read input file '"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+//! Frontends turning the source of a dialect into Brainfuck, selected with the `dialect`
+//! option.
+//!
+//! A frontend decodes source into a stream of Brainfuck commands, recording for each
+//! command where it came from, so errors found in the decoded program can point at the
+//! original tokens. The macros taking Brainfuck code run it through the selected frontend
+//! before anything else, so adding a dialect only takes a module implementing
+//! [`DialectFrontend`] and an entry in [`frontends`].
+
+use syn::LitStr;
+
+use crate::source::Code;
+use crate::{dialect, headsecks};
+
+/// The `dialect` option value that detects the dialect from the source
+const AUTO: &str = "auto";
+
+/// A dialect that can be decoded into and encoded from Brainfuck
+pub(crate) trait DialectFrontend: Sync {
+    /// The name used in the `dialect` option
+    fn name(&self) -> &'static str;
+
+    /// The name as written in messages
+    fn title(&self) -> &'static str;
+
+    /// Decode a program into Brainfuck. The result may contain `@`, ending the program,
+    /// if the dialect has such a command.
+    fn decode(&self, source: &str) -> Result<Decoded, DecodeError>;
+
+    /// Encode the commands of Brainfuck code in this dialect, dropping comments
+    fn encode(&self, code: &str) -> String;
+
+    /// The token as written in `source` for the command decoded at character `position`
+    fn token_at(&self, source: &str, command: char, position: usize) -> String;
+
+    /// Whether `dialect = "auto"` may pick this dialect. Dialects that accept almost any
+    /// text would otherwise be picked for everything.
+    fn detectable(&self) -> bool {
+        true
+    }
+}
+
+/// Brainfuck decoded from a dialect
+pub(crate) struct Decoded {
+    /// The Brainfuck commands
+    pub code: String,
+    /// For each command, the character position of its token in the source
+    pub positions: Vec<usize>,
+}
+
+/// Error returned when source cannot be decoded
+#[derive(Debug)]
+pub(crate) struct DecodeError {
+    /// Character position of the offending word
+    pub position: usize,
+    pub message: String,
+    /// A suggestion for fixing the source
+    pub help: Option<String>,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)?;
+        if let Some(help) = &self.help {
+            write!(f, "; {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+/// All built-in frontends
+pub(crate) fn frontends() -> impl Iterator<Item = &'static dyn DialectFrontend> {
+    let substitutions = dialect::DIALECTS
+        .iter()
+        .map(|dialect| dialect as &dyn DialectFrontend);
+    substitutions.chain([&headsecks::Headsecks as &dyn DialectFrontend])
+}
+
+/// The names of all frontends, for error messages
+pub(crate) fn names() -> String {
+    let names: Vec<_> = frontends().map(|frontend| frontend.name()).collect();
+    names.join(", ")
+}
+
+/// Look up the frontend named by a `dialect` option
+pub(crate) fn find(name: &LitStr) -> syn::Result<&'static dyn DialectFrontend> {
+    frontends()
+        .find(|frontend| frontend.name() == name.value())
+        .ok_or_else(|| unknown_dialect(name, &names()))
+}
+
+/// The frontend picked for a `dialect` option
+pub(crate) struct Pick {
+    /// The frontend, or `None` for plain Brainfuck
+    pub frontend: Option<&'static dyn DialectFrontend>,
+    /// Why `auto` may have picked the wrong dialect
+    pub warning: Option<String>,
+}
+
+/// Pick the frontend for `source` named by a `dialect` option, which may be `auto`
+pub(crate) fn pick(name: &LitStr, source: &str) -> syn::Result<Pick> {
+    if name.value() != AUTO {
+        let frontend =
+            find(name).map_err(|_| unknown_dialect(name, &format!("{}, {}", AUTO, names())))?;
+        return Ok(Pick {
+            frontend: Some(frontend),
+            warning: None,
+        });
+    }
+    let candidates = detect(source);
+    let warning = match candidates[..] {
+        [first, _, ..] => {
+            let others: Vec<_> = candidates[1..].iter().map(|other| other.title()).collect();
+            Some(format!(
+                "the code could be {} or {}; picked {}, set the dialect explicitly to choose another",
+                first.title(),
+                others.join(" or "),
+                first.title()
+            ))
+        }
+        _ => None,
+    };
+    Ok(Pick {
+        frontend: candidates.first().copied(),
+        warning,
+    })
+}
+
+/// Select the frontend for `code` named by a `dialect` option, which may be `auto`.
+/// `None` stands for plain Brainfuck. An ambiguous detection adds a warning to `code`.
+pub(crate) fn select(
+    name: &LitStr,
+    code: &mut Code,
+) -> syn::Result<Option<&'static dyn DialectFrontend>> {
+    let pick = pick(name, &code.value())?;
+    if let Some(warning) = pick.warning {
+        code.warn(name.span(), &warning);
+    }
+    Ok(pick.frontend)
+}
+
+/// The dialects `source` could be written in: those decoding it without errors into at
+/// least one command, in the order of [`frontends`]. Empty for plain Brainfuck.
+pub(crate) fn detect(source: &str) -> Vec<&'static dyn DialectFrontend> {
+    frontends()
+        .filter(|frontend| frontend.detectable())
+        .filter(|frontend| {
+            frontend
+                .decode(source)
+                .is_ok_and(|decoded| !decoded.code.is_empty())
+        })
+        .collect()
+}
+
+fn unknown_dialect(name: &LitStr, supported: &str) -> syn::Error {
+    syn::Error::new(
+        name.span(),
+        format!(
+            "unknown dialect `{}`; supported dialects are {}",
+            name.value(),
+            supported
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(name: &str) -> syn::Result<&'static dyn DialectFrontend> {
+        super::find(&LitStr::new(name, proc_macro2::Span::call_site()))
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("morsefuck").unwrap().title(), "Morsefuck");
+        assert_eq!(find("headsecks").unwrap().title(), "Headsecks");
+        assert_eq!(
+            find("klingon").err().unwrap().to_string(),
+            "unknown dialect `klingon`; supported dialects are ook, blub, alphuck, pikalang, spoon, morsefuck, headsecks"
+        );
+    }
+
+    #[test]
+    fn test_detect() {
+        let detected = |source| -> Vec<_> {
+            detect(source)
+                .iter()
+                .map(|frontend| frontend.name())
+                .collect()
+        };
+        assert_eq!(detected("Ook. Ook. Ook! Ook."), ["ook"]);
+        assert_eq!(detected("Blub. Blub. Blub! Blub."), ["blub"]);
+        assert_eq!(detected("1 1 001010"), ["spoon"]);
+        assert_eq!(detected("..-..- --- .-- ..- -.-"), ["morsefuck"]);
+        assert_eq!(detected("Pika pikachu"), ["pikalang"]);
+        // Plain Brainfuck, with or without comments
+        assert!(detected("++[>+<-]>.").is_empty());
+        assert!(detected("Prints A: +++++[>+++++++++++++<-]>.").is_empty());
+        // `pi` and `pipi` are also valid Alphuck
+        assert_eq!(detected("pipi pi pi"), ["alphuck", "pikalang"]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Every frontend decodes what it encodes
+        for frontend in frontends() {
+            let encoded = frontend.encode("+[->,<]. comment");
+            let decoded = frontend.decode(&encoded).unwrap();
+            assert_eq!(decoded.code, "+[->,<].", "{}", frontend.name());
+        }
+    }
+}
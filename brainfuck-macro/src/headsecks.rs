@@ -0,0 +1,85 @@
+//! The Headsecks frontend.
+//!
+//! In [Headsecks](https://esolangs.org/wiki/Headsecks) every character is a command,
+//! given by its code point modulo 8, so programs can pass for ordinary text. There are no
+//! comments: whitespace and punctuation are commands too.
+
+use crate::dialect::COMMANDS;
+use crate::frontend::{DecodeError, Decoded, DialectFrontend};
+
+/// The commands by code point modulo 8
+const COMMANDS_BY_REMAINDER: [char; 8] = ['+', '-', '<', '>', '.', ',', '[', ']'];
+
+/// Letters used when encoding, in the order of [`COMMANDS`]. Any character with the same
+/// code point modulo 8 would do.
+const TOKENS: [char; 8] = ['k', 'j', 'h', 'i', 'l', 'm', 'n', 'o'];
+
+pub(crate) struct Headsecks;
+
+impl DialectFrontend for Headsecks {
+    fn name(&self) -> &'static str {
+        "headsecks"
+    }
+
+    fn title(&self) -> &'static str {
+        "Headsecks"
+    }
+
+    fn decode(&self, source: &str) -> Result<Decoded, DecodeError> {
+        let code = source
+            .chars()
+            .map(|ch| COMMANDS_BY_REMAINDER[ch as usize % 8])
+            .collect();
+        Ok(Decoded {
+            code,
+            positions: (0..source.chars().count()).collect(),
+        })
+    }
+
+    fn encode(&self, code: &str) -> String {
+        code.chars()
+            .filter_map(|ch| COMMANDS.iter().position(|&command| command == ch))
+            .map(|index| TOKENS[index])
+            .collect()
+    }
+
+    fn token_at(&self, source: &str, _command: char, position: usize) -> String {
+        source
+            .chars()
+            .nth(position)
+            .map(String::from)
+            .unwrap_or_default()
+    }
+
+    fn detectable(&self) -> bool {
+        // Every text is valid Headsecks
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // Every character is a command, including whitespace and multi-byte characters
+        let decoded = Headsecks.decode("Hi n\u{e9}").unwrap();
+        assert_eq!(decoded.code, "+-+[-");
+        assert_eq!(decoded.positions, vec![0, 1, 2, 3, 4]);
+        assert_eq!(Headsecks.token_at("Hello", '.', 2), "l");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(Headsecks.encode("++[>-<]. comment"), "hhnkijol");
+        let decoded = Headsecks.decode("Hello").unwrap();
+        assert_eq!(
+            Headsecks
+                .decode(&Headsecks.encode(&decoded.code))
+                .unwrap()
+                .code,
+            decoded.code
+        );
+    }
+}
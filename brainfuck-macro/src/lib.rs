@@ -14,6 +14,23 @@
 //! }
 //! ```
 //!
+//! ## Importing
+//!
+//! Procedural macro crates can only export macros, so there is no `prelude` module. The
+//! crate exports nothing else, so a glob import serves as one, bringing the whole macro
+//! family into scope. [`bf!`] is a short alias for [`brainfuck!`].
+//!
+//! ```rust
+//! use brainfuck_macro::*;
+//!
+//! assert_eq!(bf!("+++++[>+++++++++++++<-]>."), "A");
+//! assert_eq!(brainfuck_minify!("+ comment ."), "+.");
+//! ```
+//!
+//! The interpreter behind the macros is `brainfuck_core::machine::Interpreter`, for
+//! running programs at runtime with the same semantics; depend on `brainfuck-core` to
+//! use it.
+//!
 //! ## Brainfuck Language
 //!
 //! The macro supports all standard Brainfuck operations:
@@ -32,168 +49,41 @@
 //! - The tape size is limited to 30,000 cells
 //! - Maximum execution steps is limited to 1,000,000 to prevent infinite loops at compile time
 
+use brainfuck_core::machine::{BrainfuckError, Extensions, Interpreter, MAX_STEPS, TAPE_SIZE};
+use brainfuck_core::metrics::Metrics;
+use brainfuck_core::trace::{Coverage, ExecutionHook, Frames, TapeTrace, Trace};
+use brainfuck_core::{ir, optimize, random, reduce, termination, transpile};
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, LitStr};
 
-/// The maximum number of cells in the Brainfuck tape
-const TAPE_SIZE: usize = 30_000;
-
-/// The maximum number of execution steps to prevent infinite loops
-const MAX_STEPS: usize = 1_000_000;
-
-/// Error types for Brainfuck execution
-#[derive(Debug)]
-enum BrainfuckError {
-    /// Unmatched opening bracket
-    UnmatchedOpenBracket(usize),
-    /// Unmatched closing bracket
-    UnmatchedCloseBracket(usize),
-    /// Pointer moved out of bounds (left)
-    PointerUnderflow,
-    /// Pointer moved out of bounds (right)
-    PointerOverflow,
-    /// Input operation not supported at compile time
-    InputNotSupported,
-    /// Execution exceeded maximum steps
-    MaxStepsExceeded,
-}
-
-impl std::fmt::Display for BrainfuckError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            BrainfuckError::UnmatchedOpenBracket(pos) => {
-                write!(f, "Unmatched '[' at position {}", pos)
-            }
-            BrainfuckError::UnmatchedCloseBracket(pos) => {
-                write!(f, "Unmatched ']' at position {}", pos)
-            }
-            BrainfuckError::PointerUnderflow => {
-                write!(f, "Pointer moved below zero")
-            }
-            BrainfuckError::PointerOverflow => {
-                write!(f, "Pointer moved beyond tape size ({})", TAPE_SIZE)
-            }
-            BrainfuckError::InputNotSupported => {
-                write!(f, "Input operation ',' is not supported at compile time")
-            }
-            BrainfuckError::MaxStepsExceeded => {
-                write!(f, "Execution exceeded maximum steps ({})", MAX_STEPS)
-            }
-        }
-    }
-}
-
-/// Brainfuck interpreter that executes code at compile time
-struct BrainfuckInterpreter {
-    tape: Vec<u8>,
-    pointer: usize,
-    output: String,
-}
-
-impl BrainfuckInterpreter {
-    /// Create a new Brainfuck interpreter
-    fn new() -> Self {
-        Self {
-            tape: vec![0; TAPE_SIZE],
-            pointer: 0,
-            output: String::new(),
-        }
-    }
-
-    /// Find matching bracket positions for jump operations
-    fn find_matching_brackets(code: &str) -> Result<Vec<Option<usize>>, BrainfuckError> {
-        let mut jump_table = vec![None; code.len()];
-        let mut stack = Vec::new();
-
-        for (i, ch) in code.chars().enumerate() {
-            match ch {
-                '[' => {
-                    stack.push(i);
-                }
-                ']' => {
-                    if let Some(open_pos) = stack.pop() {
-                        jump_table[open_pos] = Some(i);
-                        jump_table[i] = Some(open_pos);
-                    } else {
-                        return Err(BrainfuckError::UnmatchedCloseBracket(i));
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        if let Some(open_pos) = stack.pop() {
-            return Err(BrainfuckError::UnmatchedOpenBracket(open_pos));
-        }
-
-        Ok(jump_table)
-    }
-
-    /// Execute Brainfuck code and return the output
-    fn execute(&mut self, code: &str) -> Result<String, BrainfuckError> {
-        let jump_table = Self::find_matching_brackets(code)?;
-        let chars: Vec<char> = code.chars().collect();
-        
-        let mut ip = 0; // instruction pointer
-        let mut steps = 0;
-
-        while ip < chars.len() {
-            if steps >= MAX_STEPS {
-                return Err(BrainfuckError::MaxStepsExceeded);
-            }
-            steps += 1;
-
-            match chars[ip] {
-                '>' => {
-                    if self.pointer >= TAPE_SIZE - 1 {
-                        return Err(BrainfuckError::PointerOverflow);
-                    }
-                    self.pointer += 1;
-                }
-                '<' => {
-                    if self.pointer == 0 {
-                        return Err(BrainfuckError::PointerUnderflow);
-                    }
-                    self.pointer -= 1;
-                }
-                '+' => {
-                    self.tape[self.pointer] = self.tape[self.pointer].wrapping_add(1);
-                }
-                '-' => {
-                    self.tape[self.pointer] = self.tape[self.pointer].wrapping_sub(1);
-                }
-                '.' => {
-                    self.output.push(self.tape[self.pointer] as char);
-                }
-                ',' => {
-                    return Err(BrainfuckError::InputNotSupported);
-                }
-                '[' => {
-                    if self.tape[self.pointer] == 0 {
-                        if let Some(matching) = jump_table[ip] {
-                            ip = matching;
-                        }
-                    }
-                }
-                ']' => {
-                    if self.tape[self.pointer] != 0 {
-                        if let Some(matching) = jump_table[ip] {
-                            ip = matching;
-                        }
-                    }
-                }
-                _ => {
-                    // Ignore non-Brainfuck characters (comments)
-                }
-            }
-
-            ip += 1;
-        }
+mod args;
+mod boolfuck;
+mod brainloller;
+mod cases;
+mod cfg;
+mod cow;
+mod define;
+mod dialect;
+mod diff;
+mod doc;
+mod extensions;
+mod files;
+mod frontend;
+mod headsecks;
+mod lint;
+mod mutants;
+mod report;
+mod shorthand;
+mod source;
+mod stats;
+mod transpile_c;
+mod transpile_wat;
 
-        Ok(self.output.clone())
-    }
-}
+/// The most steps `brainfuck!` allows a program proven to stop; programs proven to stop
+/// only after more steps keep the usual limit, so they fail fast instead of keeping the
+/// compiler busy for ages
+const MAX_PROVEN_STEPS: usize = 100_000_000;
 
 /// Execute Brainfuck code at compile time and produce a `&'static str`.
 ///
@@ -206,13 +96,79 @@ impl BrainfuckInterpreter {
 /// assert_eq!(hello, "Hello World!\n");
 /// ```
 ///
+/// The output is a string literal, so the macro also works in `match` patterns and
+/// `concat!`. Code read from files or environment variables, and `expand_debug`, expand
+/// to a block instead, which still works in constant initializers.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// const GREETING: &str = concat!(brainfuck!("+++++[>+++++++++++++<-]>."), "!");
+/// assert!(matches!(GREETING, "A!"));
+/// ```
+///
+/// # Options
+///
+/// - `extensions = "<names>"` - Comma-separated extensions of the instruction set (see
+///   below)
+/// - `tapes = <n>` - The number of tapes of the `tapes` extension, at most 16 (default 2)
+/// - `dialect = "<name>"` - Decode the code from a dialect first: `ook`, `blub`,
+///   `alphuck`, `pikalang`, `spoon`, `morsefuck` or `headsecks`. Syntax errors point at
+///   the dialect's tokens. Cannot be combined with `extensions`. `auto` picks the dialect
+///   the code decodes in without errors, or plain Brainfuck if there is none; if several
+///   fit, the first one in the list above is picked with a warning. Headsecks is never
+///   picked, since any text is valid Headsecks.
+/// - `NAME = "<code>"` - Substitute the code for `{NAME}` placeholders, for any name in
+///   uppercase (see below)
+/// - `literate = true` - Treat only the text between backticks as code, and everything
+///   else as prose (see below)
+/// - `fence = "<text>"` - The fence around code in literate code, instead of a backtick
+/// - `with_source = true` - Expand to a `(&'static str, &'static str)` of the program and
+///   its output, to display or run the program at runtime. With a dialect, the program
+///   is the decoded Brainfuck.
+/// - `expand_debug = true` - Expand to a block with a `BRAINFUCK_SOURCE` constant holding
+///   the code as written, documented with its length and options, and a
+///   `BRAINFUCK_OUTPUT` constant documented with the formatted program, so `cargo expand`
+///   output and editor hovers describe themselves
+/// - `batch = true` - Run every string of an array as its own program and expand to a
+///   `[&'static str; N]` of their outputs, in order. The other options apply to every
+///   program. Cannot be combined with `with_source` or `expand_debug`.
+/// - `stats_file = true` - Write a report on the run to
+///   `$OUT_DIR/brainfuck-stats/<hash>.txt` (or `target/brainfuck-stats` of crates
+///   without a build script): the hash of the program, its steps, the wall time, the
+///   cells used, the output size and the operations left after every optimizer pass.
+///   Cannot be combined with `dialect`.
+/// - `coverage = "<path>"` - Write an HTML page to the path, relative to
+///   `CARGO_MANIFEST_DIR`, showing which instructions the run executed and how often,
+///   like a coverage report. Instructions never executed are red, which shows the
+///   branches the input did not reach. Cannot be combined with `dialect` or `batch`.
+/// - `flame = "<path>"` - Write the steps of the run to the path, relative to
+///   `CARGO_MANIFEST_DIR`, as folded stacks of the loops they ran in, for flame graph
+///   tools like `inferno-flamegraph`, so the loops worth optimizing stand out. Cannot be
+///   combined with `dialect` or `batch`.
+/// - `minimize = true` - When the program fails, search by delta debugging for a minimal
+///   sub-program failing with the same kind of error and add it to the compile error.
+///   Every candidate is run, so programs hitting the step limit take a while to reduce.
+///   Cannot be combined with `dialect`.
+///
 /// # Errors
 ///
 /// The macro will produce a compile-time error if:
 /// - The Brainfuck code has unmatched brackets
 /// - The code attempts to use input operations (`,`)
 /// - The pointer moves out of bounds
-/// - Execution exceeds the maximum step limit
+/// - Execution exceeds the maximum step limit, unless the program has only loops that
+///   provably stop (see `brainfuck_core::termination`) within 100,000,000 steps, which
+///   may take as long as they need
+/// - The code is a variable or another expression only known at runtime; use
+///   `brainfuck_core::interpret::run` to run such code
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::brainfuck;
+///
+/// let code = "+.";
+/// let output = brainfuck!(code);
+/// ```
 ///
 /// # Supported Operations
 ///
@@ -225,114 +181,4145 @@ impl BrainfuckInterpreter {
 /// - `]` - Loop end (jump to matching `[` if cell is nonzero)
 ///
 /// All other characters are treated as comments and ignored.
+///
+/// # Extensions
+///
+/// `extensions = "extended"` enables
+/// [Extended Brainfuck Type I](https://esolangs.org/wiki/Extended_Brainfuck):
+///
+/// - `@` - End the program
+/// - `$` - Copy the current cell into the storage byte
+/// - `!` - Copy the storage byte into the current cell
+/// - `{` / `}` - Shift the current cell one bit left / right
+/// - `~` - Invert the bits of the current cell
+/// - `^` / `&` / `|` - Combine the current cell with the storage byte by XOR / AND / OR
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// // 'A' is 0b0100_0001; shifting it left and back right loses no bits
+/// let a = brainfuck!("+++++[>+++++++++++++<-]>{}.@ this is not executed.", extensions = "extended");
+/// assert_eq!(a, "A");
+/// ```
+///
+/// `extensions = "end"` enables just `@`, which ends the program (with all Brainfork
+/// threads). Programs written for interpreters honoring it can keep trailing notes that
+/// would otherwise run as code.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!("+++++[>+++++++++++++<-]>.@ Prints A, then a newline.", extensions = "end");
+/// assert_eq!(a, "A");
+/// ```
+///
+/// `extensions = "bitwise"` enables bitwise operators whose second operand is the cell
+/// right of the current one, with the result stored in the current cell. It cannot be
+/// combined with `extended`, which uses the same characters.
+///
+/// - `&` / `|` / `^` - AND / OR / XOR
+/// - `{` / `}` - Shift left / right by the number of bits in the next cell
+/// - `~` - Invert the bits of the current cell
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// // Cell 0 = 'a' and cell 1 = 32; flipping bit 5 of 'a' gives 'A'
+/// let a = brainfuck!(">++++++++[<++++++++++++>-] >++++[<++++++++>-] <<+ ^.", extensions = "bitwise");
+/// assert_eq!(a, "A");
+/// ```
+///
+/// `extensions = "tapes"` gives the program several tapes, each with its own pointer. `%`
+/// switches to the next tape, and from the last tape back to the first. The number of
+/// tapes is set with the `tapes` option.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// // Keep a counter on the second tape while building 'A' on the first
+/// let a = brainfuck!("%+++++[%>+++++++++++++<%-]%>.", extensions = "tapes");
+/// assert_eq!(a, "A");
+/// ```
+///
+/// `extensions = "decimal"` enables decimal I/O, so numeric results are readable without
+/// converting them to digits by hand:
+///
+/// - `:` - Output the current cell as decimal digits
+/// - `;` - Read a decimal number into the current cell, skipping leading whitespace. Like
+///   `,`, it fails in `brainfuck!`, which has no input.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let answer = brainfuck!("++++++[>+++++++<-]>:", extensions = "decimal");
+/// assert_eq!(answer, "42");
+/// ```
+///
+/// `extensions = "brainfork"` enables the `Y` command of
+/// [Brainfork](https://esolangs.org/wiki/Brainfork), which forks the running thread. The
+/// parent continues with the current cell cleared. The child continues with its own copy
+/// of the tape, with the pointer moved one cell right and that cell set to 1. Threads take
+/// turns executing one instruction each, so the output is deterministic. Up to 64 threads
+/// can run at once.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// // Only the child, whose pointer moved to a cell set to 1, enters the loop and prints
+/// // cell 0
+/// let output = brainfuck!(">+++++[<+++++++++++++>-] Y [<<.>>-]", extensions = "brainfork");
+/// assert_eq!(output, "A");
+/// ```
+///
+/// # Code Argument
+///
+/// Besides a string literal, the code may be given as a `concat!(...)` or
+/// `include_str!(...)` invocation, or as the output of `brainfuck_expand!` or
+/// `text_to_brainfuck!`. These are evaluated by the macro itself, since macro arguments
+/// are not expanded before a procedural macro sees them. Files read by `include_str!`
+/// are tracked like with the standard macro. The same forms are accepted by every macro
+/// taking Brainfuck code.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!(concat!("+++++", "[>+++++++++++++<-]", ">."));
+/// assert_eq!(a, "A");
+/// ```
+///
+/// Several string literals are joined into one program, whether they are separated by
+/// whitespace or, in macros taking options, by commas:
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!(
+///     "+++++"
+///     "[>+++++++++++++<-]"
+///     ">."
+/// );
+/// assert_eq!(a, brainfuck!("+++++", "[>+++++++++++++<-]", ">."));
+/// ```
+///
+/// The code may also be an array of string literals, joined with newlines, so every line
+/// of a long program can have its own Rust comment:
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!([
+///     "+++++",              // 5
+///     "[>+++++++++++++<-]", // 5 * 13 = 65
+///     ">.",                 // 'A'
+/// ]);
+/// assert_eq!(a, "A");
+/// ```
+///
+/// Macros taking options also accept the code as raw tokens, without quotes. Rust splits
+/// them into punctuation, from which the code is reconstructed, so some programs cannot be
+/// written this way:
+///
+/// - Brackets, parentheses and braces must be balanced, also in comments
+/// - Comments must be valid Rust tokens, so no unmatched quotes as in `don't`; `//`
+///   comments are dropped, and whitespace is not preserved
+/// - A `,` followed by an option, a string literal or nothing ends the code
+/// - Code starting like a macro invocation, such as `a!(...)`, is read as one
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!(+++++[>+++++++++++++<-]>.);
+/// assert_eq!(a, "A");
+/// ```
+///
+/// Macros taking options can also read the code from an environment variable of the
+/// compiler with `env = "<name>"`, for example one set by a build script with
+/// `cargo:rustc-env`. Changing the variable triggers a rebuild.
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck;
+///
+/// let output = brainfuck!(env = "BF_PROGRAM");
+/// ```
+///
+/// In macros taking options, options with uppercase names fill `{NAME}` placeholders in
+/// the code, before a dialect is decoded. Every placeholder needs a value and every value
+/// must be used. Without such options, braces are left alone.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!("{FIVE}[>{FIVE}{FIVE}+++<-]>.", FIVE = "+++++");
+/// assert_eq!(a, "A");
+/// ```
+///
+/// With `literate = true`, only the text between pairs of backticks is code, so prose
+/// around it may use any characters. The fenced parts are joined with newlines, after
+/// placeholders are filled. `fence = "<text>"` sets another fence.
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck;
+///
+/// let a = brainfuck!(
+///     "Set a cell to 5 with `+++++`, add 13 to the next one five times with
+///      `[>+++++++++++++<-]`, and print it: `>.`",
+///     literate = true
+/// );
+/// assert_eq!(a, "A");
+/// ```
+///
+/// Macros taking options also accept `use NAME` in place of the code, for a program
+/// defined with [`brainfuck_define!`]:
+///
+/// ```rust
+/// use brainfuck_macro::{brainfuck, brainfuck_define};
+///
+/// brainfuck_define!(PRINT_A = "+++++[>+++++++++++++<-]>.");
+///
+/// let a = brainfuck!(use PRINT_A);
+/// assert_eq!(a, "A");
+/// ```
 #[proc_macro]
 pub fn brainfuck(input: TokenStream) -> TokenStream {
-    let input_str = parse_macro_input!(input as LitStr);
-    let code = input_str.value();
+    if let Some(expanded) = define::redirect("brainfuck", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
 
-    let mut interpreter = BrainfuckInterpreter::new();
-    
-    match interpreter.execute(&code) {
-        Ok(output) => {
-            let expanded = quote! {
-                #output
-            };
-            TokenStream::from(expanded)
-        }
-        Err(e) => {
-            let error_msg = format!("Brainfuck execution error: {}", e);
-            let expanded = quote! {
-                compile_error!(#error_msg)
-            };
-            TokenStream::from(expanded)
+    TokenStream::from(expand_brainfuck(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+/// A short alias for [`brainfuck!`], taking the same code and options.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::bf;
+///
+/// let a = bf!("+++++[>+++++++++++++<-]>.");
+/// assert_eq!(a, "A");
+/// ```
+#[proc_macro]
+pub fn bf(input: TokenStream) -> TokenStream {
+    brainfuck(input)
+}
+
+/// Replace the Brainfuck code of a constant or static with its output, keeping the
+/// item's name, visibility, type and attributes.
+///
+/// This reads more naturally than `brainfuck!` for strings embedded at module level.
+/// Procedural macros of one kind cannot share a name with another, so the attribute is
+/// not called `brainfuck`. Its arguments are the options of `brainfuck!`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_const;
+///
+/// /// The letter A.
+/// #[brainfuck_const]
+/// pub const LETTER_A: &str = "+++++[>+++++++++++++<-]>.";
+///
+/// #[brainfuck_const(dialect = "ook")]
+/// static ONE: &str = "Ook. Ook. Ook! Ook.";
+///
+/// assert_eq!(LETTER_A, "A");
+/// assert_eq!(ONE, "\u{1}");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the item is not a `const` or `static`,
+/// and under the same conditions as `brainfuck!`.
+#[proc_macro_attribute]
+pub fn brainfuck_const(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as syn::Item);
+    let attr = proc_macro2::TokenStream::from(attr);
+    TokenStream::from(expand_const(attr, item).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_const(
+    attr: proc_macro2::TokenStream,
+    mut item: syn::Item,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let expr = match &mut item {
+        syn::Item::Const(item) => &mut item.expr,
+        syn::Item::Static(item) => &mut item.expr,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &item,
+                "#[brainfuck_const] expects a `const` or `static` item",
+            ))
         }
-    }
+    };
+    let args = if attr.is_empty() {
+        quote! { #expr }
+    } else {
+        quote! { #expr, #attr }
+    };
+    **expr = syn::Expr::Verbatim(expand_brainfuck(syn::parse2(args)?)?);
+    Ok(quote! { #item })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn expand_brainfuck(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs { code, mut options } = args;
+    let extensions = options.string("extensions")?;
+    let dialect = options.string("dialect")?;
+    let tapes = options.usize("tapes")?;
+    let debug = options.flag("expand_debug")?;
+    let with_source = options.flag("with_source")?;
+    let batch = options.flag("batch")?;
+    let stats_file = options.flag("stats_file")?;
+    let coverage = options.string("coverage")?;
+    let flame = options.string("flame")?;
+    let minimize = options.flag("minimize")?;
+    options.finish()?;
 
-    #[test]
-    fn test_hello_world() {
-        let code = "++++++++++[>+++++++>++++++++++>+++>+<<<<-]>++.>+.+++++++..+++.>++.<<+++++++++++++++.>.+++.------.--------.>+.>.";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code).unwrap();
-        assert_eq!(result, "Hello World!\n");
+    let run = Run {
+        extensions,
+        dialect,
+        tapes,
+        stats_file,
+        coverage,
+        flame,
+        minimize,
+    };
+    if batch {
+        return expand_batch(code, run, debug || with_source);
     }
-
-    #[test]
-    fn test_simple_output() {
-        // 5 * 13 = 65 = 'A'
-        let code = "+++++[>+++++++++++++<-]>.";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code).unwrap();
-        assert_eq!(result, "A");
+    let source = code.value();
+    let program = with_source.then(|| decoded_program(&source, run.dialect.as_ref()));
+    let mut details = vec![format!("Characters: {}", source.chars().count())];
+    let dialect_name = run.dialect.as_ref().map(LitStr::value);
+    if let Some(name) = &dialect_name {
+        details.push(format!("Dialect: {}", name));
     }
+    if let Some(list) = &run.extensions {
+        details.push(format!("Extensions: {}", list.value()));
+    }
+    let mut expanded = expand_run(code, run)?;
+    if debug {
+        // The program of a dialect is not Brainfuck to format
+        let program = dialect_name.is_none().then(|| doc::render_program(&source));
+        expanded = expand_debug(&source, &details, program, expanded);
+    }
+    if let Some(program) = program {
+        expanded = quote! { (#program, #expanded) };
+    }
+    Ok(expanded)
+}
 
-    #[test]
-    fn test_loop() {
-        let code = "+++++[>++++<-]>."; // 5 * 4 = 20
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code).unwrap();
-        assert_eq!(result, "\u{14}"); // ASCII 20
+/// Run every line of an array as its own program for `batch = true`, with the options
+/// parsed once for all of them, and expand to an array of the outputs
+fn expand_batch(
+    code: source::Code,
+    run: Run,
+    described: bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let Some(lines) = code.lines() else {
+        return Err(syn::Error::new(
+            code.span(),
+            "option `batch` expects an array of programs",
+        ));
+    };
+    if described {
+        return Err(syn::Error::new(
+            code.span(),
+            "option `batch` cannot be combined with `with_source` or `expand_debug`",
+        ));
+    }
+    for (option, path) in [("coverage", &run.coverage), ("flame", &run.flame)] {
+        if let Some(path) = path {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("option `{}` cannot be combined with `batch`", option),
+            ));
+        }
     }
+    let outputs = (0..lines.len())
+        .map(|index| expand_run(code.line(index), run.clone()))
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(code.track(quote! { [#(#outputs),*] }))
+}
 
-    #[test]
-    fn test_unmatched_open_bracket() {
-        let code = "[++";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code);
-        assert!(matches!(result, Err(BrainfuckError::UnmatchedOpenBracket(_))));
+/// The Brainfuck program of `source`, decoded from the dialect if one is set and the
+/// source decodes. Errors are left for the execution to report.
+fn decoded_program(source: &str, dialect: Option<&LitStr>) -> String {
+    dialect
+        .and_then(|name| frontend::pick(name, source).ok()?.frontend)
+        .and_then(|frontend| frontend.decode(source).ok())
+        .map_or_else(|| source.to_string(), |decoded| decoded.code)
+}
+
+/// The options of `brainfuck!` that apply to every program of a batch
+#[derive(Clone)]
+struct Run {
+    extensions: Option<LitStr>,
+    dialect: Option<LitStr>,
+    tapes: Option<usize>,
+    stats_file: bool,
+    coverage: Option<LitStr>,
+    flame: Option<LitStr>,
+    minimize: bool,
+}
+
+/// Run the code of `brainfuck!` with its options
+fn expand_run(mut code: source::Code, run: Run) -> syn::Result<proc_macro2::TokenStream> {
+    let Run {
+        extensions,
+        dialect,
+        tapes,
+        stats_file,
+        coverage,
+        flame,
+        minimize,
+    } = run;
+    if let Some(name) = dialect {
+        if let Some(list) = extensions {
+            return Err(syn::Error::new(
+                list.span(),
+                "option `extensions` cannot be combined with `dialect`",
+            ));
+        }
+        let option = if stats_file {
+            Some("stats_file")
+        } else if coverage.is_some() {
+            Some("coverage")
+        } else if flame.is_some() {
+            Some("flame")
+        } else {
+            minimize.then_some("minimize")
+        };
+        if let Some(option) = option {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("option `{}` cannot be combined with `dialect`", option),
+            ));
+        }
+        if let Some(frontend) = frontend::select(&name, &mut code)? {
+            return Ok(expand_dialect(&code, frontend));
+        }
+        return Ok(code.track(expand_execution(&code.value())));
     }
 
-    #[test]
-    fn test_unmatched_close_bracket() {
-        let code = "++]";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code);
-        assert!(matches!(result, Err(BrainfuckError::UnmatchedCloseBracket(_))));
+    let source = code.value();
+    let mut builder = Interpreter::builder();
+    match extensions {
+        Some(list) => builder = builder.extensions(extensions::parse(&list)?),
+        // Programs proven to stop may take all the steps they need
+        None => {
+            let bound = termination::step_bound(&source, 8);
+            if let Some(bound) = bound.filter(|&bound| bound <= MAX_PROVEN_STEPS) {
+                builder = builder.max_steps(bound.max(MAX_STEPS));
+            }
+        }
+    }
+    if let Some(count) = tapes {
+        builder = builder.tapes(count);
     }
+    let interpreter = builder
+        .clone()
+        .build()
+        .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("option {}", e)))?;
+    let result = if coverage.is_some() || flame.is_some() {
+        let mut hook = Coverage::new();
+        let result = execute_hooked(interpreter, &source, stats_file, &mut hook)?;
+        if let Some(path) = &coverage {
+            write_report(path, "coverage", hook.render_html(&source, &path.value()))?;
+        }
+        if let Some(path) = &flame {
+            write_report(path, "flame data", hook.render_folded(&source))?;
+        }
+        result
+    } else {
+        execute_hooked(interpreter, &source, stats_file, &mut ())?
+    };
+    if let (true, Err(error)) = (minimize, &result) {
+        let reduced = reduce::minimize_error(&source, error, || {
+            builder
+                .clone()
+                .build()
+                .expect("the options built an interpreter")
+        });
+        let error_msg = format!(
+            "Brainfuck execution error: {}\nminimal failing program: {:?}",
+            error, reduced
+        );
+        return Ok(code.track(quote! { compile_error!(#error_msg) }));
+    }
+    Ok(code.track(expand_result(result)))
+}
 
-    #[test]
-    fn test_input_not_supported() {
-        let code = ",";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code);
-        assert!(matches!(result, Err(BrainfuckError::InputNotSupported)));
+/// Write a report on a run to the path of an option, relative to `CARGO_MANIFEST_DIR`
+fn write_report(path: &LitStr, what: &str, contents: String) -> syn::Result<()> {
+    let full = files::resolve(&path.value());
+    std::fs::write(&full, contents).map_err(|e| {
+        syn::Error::new(
+            path.span(),
+            format!("couldn't write {} to '{}': {}", what, full.display(), e),
+        )
+    })
+}
+
+/// Execute code with a hook, writing the report of `stats_file = true` if requested
+fn execute_hooked(
+    mut interpreter: Interpreter,
+    code: &str,
+    stats_file: bool,
+    hook: &mut impl ExecutionHook,
+) -> syn::Result<Result<String, brainfuck_core::machine::Error>> {
+    if stats_file {
+        return stats::execute(interpreter, code, hook);
     }
+    Ok(interpreter.execute_with_hook(code, hook))
+}
 
-    #[test]
-    fn test_pointer_underflow() {
-        let code = "<";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code);
-        assert!(matches!(result, Err(BrainfuckError::PointerUnderflow)));
+/// Wrap an expansion in a block holding the source as written, documented with details
+/// about it, and the output, documented with the formatted program, for
+/// `expand_debug = true`. `cargo expand` output and editor hovers then show where the
+/// output came from.
+fn expand_debug(
+    source: &str,
+    details: &[String],
+    program: Option<String>,
+    expanded: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let details = details.iter().map(|detail| format!(" - {}", detail));
+    let program = program.map(|program| quote! { #[doc = ""] #[doc = #program] });
+    quote! {
+        {
+            #[doc = " The source of this `brainfuck!` invocation, kept by `expand_debug = true`."]
+            #[doc = ""]
+            #(#[doc = #details])*
+            #[allow(dead_code)]
+            const BRAINFUCK_SOURCE: &str = #source;
+            #[doc = " The output of `BRAINFUCK_SOURCE`."]
+            #program
+            const BRAINFUCK_OUTPUT: &str = #expanded;
+            BRAINFUCK_OUTPUT
+        }
     }
+}
 
-    #[test]
-    fn test_nested_loops() {
-        // 2 outer * 2 inner * 2 innermost = 8 in cell 2
-        let code = "++[>++[>++<-]<-]>>.";
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code).unwrap();
-        assert_eq!(result, "\u{08}"); // ASCII 8
+/// Execute Brainfuck code from a file at compile time and produce a `&'static str`.
+///
+/// The path is resolved relative to the `CARGO_MANIFEST_DIR` of the crate invoking the
+/// macro. The file is registered as a dependency, so editing it triggers a rebuild.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_file;
+///
+/// let hello = brainfuck_file!("programs/hello.b");
+/// assert_eq!(hello, "Hello World!\n");
+///
+/// let shouted = brainfuck_file!("programs/shout.b", input_file = "programs/shout.in");
+/// ```
+///
+/// # Options
+///
+/// - `input_file = "<path>"` - Read the input for `,` from a file, resolved like the
+///   program and also tracked. Once the input is exhausted, `,` stores 0. `brainfuck_dir!`
+///   reads input the same way from a sibling `.in` file.
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the program or input file cannot be
+/// read, and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn brainfuck_file(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as PathArgs);
+
+    TokenStream::from(expand_file(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_file(args: PathArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let PathArgs { path, mut options } = args;
+    let input_file = options.string("input_file")?;
+    options.finish()?;
+
+    let (path, code) = match files::read_relative(&path.value()) {
+        Ok(file) => file,
+        Err(error_msg) => {
+            return Ok(quote! {
+                compile_error!(#error_msg)
+            });
+        }
+    };
+    let tracked = files::track(&path);
+
+    let (interpreter, tracked_input) = match input_file {
+        Some(input_file) => {
+            let input_path = files::resolve(&input_file.value());
+            let input = std::fs::read(&input_path).map_err(|e| {
+                syn::Error::new(
+                    input_file.span(),
+                    format!("couldn't read input file '{}': {}", input_path.display(), e),
+                )
+            })?;
+            (
+                Interpreter::with_input(&input),
+                Some(files::track(&input_path)),
+            )
+        }
+        None => (Interpreter::new(), None),
+    };
+    let output = expand_interpreter(interpreter, &code);
+    Ok(quote! {
+        {
+            #tracked
+            #tracked_input
+            #output
+        }
+    })
+}
+
+/// Execute every Brainfuck program in a directory at compile time, producing one
+/// `pub const NAME: &str` per program.
+///
+/// The argument is a path relative to `CARGO_MANIFEST_DIR`, either a directory or a glob
+/// whose final component may contain `*` and `?`. Only `.b` and `.bf` files are considered.
+/// Each constant is named after the file stem in `SCREAMING_SNAKE_CASE`
+/// (`hello-world.b` becomes `HELLO_WORLD`, and `-.b` becomes `P_`).
+///
+/// If a program has a sibling file with the same stem and an `.in` extension, its contents
+/// are used as the program's input for `,`. Once the input is exhausted, `,` stores 0.
+///
+/// With `dialect = "auto"`, an archive mixing Brainfuck and dialects can be run as is:
+/// every program is decoded from the dialect detected for it (see `brainfuck!`).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_dir;
+///
+/// // Defines `HELLO` from programs/hello.b, `ECHO` from programs/echo.b with the
+/// // input in programs/echo.in, ...
+/// brainfuck_dir!("programs/*");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode every program from a dialect first, or from the one
+///   detected for it with `auto`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the directory cannot be read, if no
+/// programs match, if two programs would define the same constant (like `x.b` and
+/// `x.bf`), if an `.in` file exists but cannot be read, or if any program fails under
+/// the same conditions as `brainfuck!`. Every matched file is tracked, but files added to
+/// the directory later are not noticed until the invoking crate is rebuilt.
+#[proc_macro]
+pub fn brainfuck_dir(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as PathArgs);
+
+    TokenStream::from(expand_dir(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+/// Arguments of `brainfuck_file!` and `brainfuck_dir!`: a path, or a glob for
+/// `brainfuck_dir!`, followed by optional options
+struct PathArgs {
+    path: LitStr,
+    options: args::Options,
+}
+
+impl syn::parse::Parse for PathArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let options = if input.is_empty() {
+            args::Options::default()
+        } else {
+            input.parse::<syn::Token![,]>()?;
+            input.parse()?
+        };
+        Ok(Self { path, options })
+    }
+}
+
+fn expand_dir(args: PathArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let PathArgs {
+        path: pattern,
+        mut options,
+    } = args;
+    let dialect = options.string("dialect")?;
+    options.finish()?;
+
+    let programs = match files::find_programs(&pattern.value()) {
+        Ok(programs) if programs.is_empty() => {
+            let error_msg = format!("No Brainfuck programs match '{}'", pattern.value());
+            return Ok(quote! {
+                compile_error!(#error_msg);
+            });
+        }
+        Ok(programs) => programs,
+        Err(error_msg) => {
+            return Ok(quote! {
+                compile_error!(#error_msg);
+            });
+        }
+    };
+
+    let names = match files::const_names(&programs) {
+        Ok(names) => names,
+        Err(error_msg) => {
+            return Ok(quote! {
+                compile_error!(#error_msg);
+            });
+        }
+    };
+
+    let mut items = Vec::new();
+    for (path, name) in programs.into_iter().zip(names) {
+        let mut code = match std::fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(e) => {
+                let error_msg =
+                    format!("Failed to read Brainfuck file '{}': {}", path.display(), e);
+                items.push(quote! { compile_error!(#error_msg); });
+                continue;
+            }
+        };
+
+        let input = match files::read_input(&path) {
+            Ok(input) => input,
+            Err(error_msg) => {
+                items.push(quote! { compile_error!(#error_msg); });
+                continue;
+            }
+        };
+        let mut interpreter = match &input {
+            Some(input) => Interpreter::with_input(input),
+            None => Interpreter::new(),
+        };
+
+        if let Some(name) = &dialect {
+            let pick = frontend::pick(name, &code)?;
+            if let Some(warning) = pick.warning {
+                let warning = format!("in '{}', {}", path.display(), warning);
+                items.push(source::warning(name.span(), &warning));
+            }
+            if let Some(frontend) = pick.frontend {
+                code = match frontend.decode(&code) {
+                    Ok(decoded) => decoded.code,
+                    Err(e) => {
+                        let error_msg = format!(
+                            "{} syntax error in '{}': {}",
+                            frontend.title(),
+                            path.display(),
+                            e
+                        );
+                        items.push(quote! { compile_error!(#error_msg); });
+                        continue;
+                    }
+                };
+                // Decoded code may contain `@`, ending the program
+                interpreter.enable_extensions(Extensions {
+                    end: true,
+                    ..Default::default()
+                });
+            }
+        }
+
+        let name = syn::Ident::new(&name, proc_macro2::Span::call_site());
+        let tracked = files::track(&path);
+        let tracked_input = input
+            .as_ref()
+            .map(|_| files::track(&path.with_extension("in")));
+
+        match interpreter.execute(&code) {
+            Ok(output) => items.push(quote! {
+                #tracked
+                #tracked_input
+                pub const #name: &str = #output;
+            }),
+            Err(e) => {
+                let error_msg = format!("Brainfuck execution error in '{}': {}", path.display(), e);
+                items.push(quote! { compile_error!(#error_msg); });
+            }
+        }
+    }
+
+    Ok(quote! {
+        #(#items)*
+    })
+}
+
+/// Define a Brainfuck program under a name, for later invocations to use with `use NAME`
+/// in place of the code.
+///
+/// The definition expands to a `macro_rules!` macro named like the program, so it follows
+/// the same scoping: the name can be used after the definition, in the same module and
+/// its child modules, and must not clash with other macros in scope. The code may be
+/// given in any form accepted by `brainfuck!`, and the options of each invocation apply
+/// to it as usual.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::{brainfuck, brainfuck_define, brainfuck_minify};
+///
+/// brainfuck_define!(GREETER = "Prints A: +++++[>+++++++++++++<-]>.");
+///
+/// assert_eq!(brainfuck!(use GREETER), "A");
+/// assert_eq!(brainfuck_minify!(use GREETER), "+++++[>+++++++++++++<-]>.");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the arguments are not a name, `=` and
+/// code. Errors in the code itself are reported where it is used.
+#[proc_macro]
+pub fn brainfuck_define(input: TokenStream) -> TokenStream {
+    let definition = parse_macro_input!(input as define::Definition);
+    TokenStream::from(definition.expand())
+}
+
+/// Validate Brainfuck code at compile time without executing it, producing the code
+/// itself as a `&'static str`.
+///
+/// This is useful for crates that ship Brainfuck source to a runtime interpreter but
+/// still want to catch mistakes while compiling.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_check;
+///
+/// let code = brainfuck_check!(",[.,]");
+/// assert_eq!(code, ",[.,]");
+/// ```
+///
+/// # Options
+///
+/// - `lint = true` - Also run the lint passes and reject code they flag: cancelling
+///   `+-`/`<>` pairs, empty loops, and loops that can never be entered
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`. The
+///   decoded Brainfuck is produced.
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::brainfuck_check;
+///
+/// let code = brainfuck_check!("++-.", lint = true); // Cancelling '+' and '-' pair
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched
+/// brackets, or if linting is enabled and any lint fires.
+#[proc_macro]
+pub fn brainfuck_check(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_check", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+    TokenStream::from(expand_check(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_check(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let lint = options.flag("lint")?;
+    options.finish()?;
+
+    let source = code.value();
+    if let Err(e) = Interpreter::find_matching_brackets(&source) {
+        let error_msg = format!("Brainfuck syntax error: {}", e);
+        return Ok(quote! {
+            compile_error!(#error_msg)
+        });
+    }
+
+    if lint {
+        let lints = lint::check(&source);
+        if !lints.is_empty() {
+            let findings: Vec<String> = lints.iter().map(ToString::to_string).collect();
+            let error_msg = format!("Brainfuck lint failed: {}", findings.join("; "));
+            return Ok(quote! {
+                compile_error!(#error_msg)
+            });
+        }
+    }
+
+    let literal = code.literal();
+    Ok(code.track(quote! {
+        #literal
+    }))
+}
+
+/// Minify Brainfuck code at compile time, producing the stripped code as a `&'static str`.
+///
+/// Comments and whitespace are removed, and runs of `+`/`-` and `<`/`>` are folded to
+/// their net effect, so cancelling pairs disappear entirely. Since cells wrap, a net
+/// increment above 128 is written as the equivalent (shorter) run of `-`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_minify;
+///
+/// let code = brainfuck_minify!("
+///     +++++ +-   Set cell 0 to 5
+///     [ >+++++++++++++ <- ]
+///     >.         Output 'A'
+/// ");
+/// assert_eq!(code, "+++++[>+++++++++++++<-]>.");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_minify(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_minify", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let minified = ir::to_source(&ops);
+            TokenStream::from(code.track(quote! {
+                #minified
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Generate the random program of a seed at compile time and snapshot its output,
+/// producing a `(&'static str, &'static str)` of the program and its output.
+///
+/// The program is the one `brainfuck_core::random::program` generates for the seed, so it
+/// always terminates and keeps the pointer on the first `cells` cells. The output is
+/// computed with the same interpreter as `brainfuck!`, which makes the pair a snapshot to
+/// check other implementations against, like an optimizer or a transpiler.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_random;
+///
+/// let (program, output) = brainfuck_random!(42, length = 100, input = "abc");
+/// let bytes = brainfuck_core::interpret::run(program, b"abc").unwrap();
+/// assert_eq!(bytes.into_iter().map(char::from).collect::<String>(), output);
+/// ```
+///
+/// # Options
+///
+/// - `length = <n>` - The number of commands to generate, not counting the ones closing
+///   loops (default 64)
+/// - `depth = <n>` - The deepest nesting of loops (default 2)
+/// - `cells = <n>` - The number of cells the pointer stays within (default 8)
+/// - `input = "<text>"` - Generate `,` and read it from this input; without it, the
+///   program reads no input
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the seed is not an integer or an option
+/// is unknown.
+#[proc_macro]
+pub fn brainfuck_random(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as RandomArgs);
+    TokenStream::from(expand_random(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+/// Arguments of `brainfuck_random!`: a seed followed by optional options
+struct RandomArgs {
+    seed: syn::LitInt,
+    options: args::Options,
+}
+
+impl syn::parse::Parse for RandomArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let seed = input.parse()?;
+        let options = if input.is_empty() {
+            args::Options::default()
+        } else {
+            input.parse::<syn::Token![,]>()?;
+            input.parse()?
+        };
+        Ok(Self { seed, options })
+    }
+}
+
+fn expand_random(args: RandomArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let RandomArgs { seed, mut options } = args;
+    let defaults = random::Options::default();
+    let length = options.usize("length")?.unwrap_or(defaults.length);
+    let max_depth = options.usize("depth")?.unwrap_or(defaults.max_depth);
+    let cells = options.usize("cells")?.unwrap_or(defaults.cells);
+    let input = options.string("input")?;
+    options.finish()?;
+
+    let random_options = random::Options {
+        length,
+        max_depth,
+        cells,
+        input: input.is_some(),
+        ..defaults
+    };
+    let program = random::program(seed.base10_parse()?, &random_options);
+    let input = input.map_or_else(String::new, |input| input.value());
+    match Interpreter::with_input(input.as_bytes()).execute(&program) {
+        Ok(output) => Ok(quote! { (#program, #output) }),
+        Err(e) => {
+            let error_msg = format!("Brainfuck execution error: {}", e);
+            Ok(quote! { compile_error!(#error_msg) })
+        }
+    }
+}
+
+/// Compute static metrics of Brainfuck code at compile time, producing a constant
+/// `brainfuck_core::metrics::Metrics`.
+///
+/// The metrics are the number of every command, the number of loops and their deepest
+/// nesting, and the length of the program as written and minified (see
+/// `brainfuck_minify!`), for leaderboards and code-review tooling. The invoking crate
+/// needs `brainfuck-core` as a dependency for the type.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_core::metrics::Metrics;
+/// use brainfuck_macro::brainfuck_metrics;
+///
+/// const METRICS: Metrics = brainfuck_metrics!("Print A: +++++ [>+++++++++++++<-] +- >.");
+/// assert_eq!(METRICS.histogram.increment, 19);
+/// assert_eq!((METRICS.loops, METRICS.max_depth), (1, 1));
+/// assert_eq!((METRICS.length, METRICS.minified_length), (39, 25));
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_metrics(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_metrics", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+
+    match Metrics::of(&code.value()) {
+        Ok(metrics) => {
+            let Metrics {
+                histogram: h,
+                loops,
+                max_depth,
+                length,
+                minified_length,
+            } = metrics;
+            let (right, left, increment, decrement) = (h.right, h.left, h.increment, h.decrement);
+            let (output, input) = (h.output, h.input);
+            TokenStream::from(code.track(quote! {
+                ::brainfuck_core::metrics::Metrics {
+                    histogram: ::brainfuck_core::metrics::Histogram {
+                        right: #right,
+                        left: #left,
+                        increment: #increment,
+                        decrement: #decrement,
+                        output: #output,
+                        input: #input,
+                    },
+                    loops: #loops,
+                    max_depth: #max_depth,
+                    length: #length,
+                    minified_length: #minified_length,
+                }
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Optimize Brainfuck code at compile time, producing equivalent Brainfuck as a
+/// `&'static str`.
+///
+/// Goes further than `brainfuck_minify!`: besides stripping comments and folding runs,
+/// the optimizer passes of `brainfuck_ir!` are applied and the result is written back as
+/// Brainfuck. Clear loops are canonicalized to `[-]`, multiplication loops to the form
+/// `[->++<]` (decrementing the counter first), and loops that can never be entered are
+/// removed: loops at the start of the program and loops directly after another loop.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_optimize;
+///
+/// let code = brainfuck_optimize!("[comment loop] +++++ [>++++ +++++++++<-] > [+] [-] +.");
+/// assert_eq!(code, "+++++[->+++++++++++++<]>[-]+.");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_optimize(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_optimize", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let ops = optimize::PassManager::optimize()
+                .pass(optimize::dce)
+                .run(ops);
+            let optimized = ir::to_source(&ops);
+            TokenStream::from(code.track(quote! {
+                #optimized
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Pretty-print Brainfuck code at compile time, producing the formatted code as a
+/// `&'static str`.
+///
+/// Comments are dropped and every bracket depth is indented one level. Loops without
+/// nested loops stay on one line if they fit within the line width. The same formatter is
+/// available to build scripts and tools as `brainfuck_core::format::format`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_fmt;
+///
+/// let code = brainfuck_fmt!("++[>++[>+<-]<-]>>.", indent = 2);
+/// assert_eq!(code, "++\n[\n  >++\n  [>+<-]\n  <-\n]\n>>.");
+/// ```
+///
+/// # Options
+///
+/// - `indent = <n>` - Spaces per bracket depth (default 4)
+/// - `width = <n>` - Maximum line width including indentation (default 80)
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_fmt(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_fmt", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+    TokenStream::from(expand_fmt(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_fmt(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let mut format_options = brainfuck_core::format::FormatOptions::default();
+    if let Some(indent) = options.usize("indent")? {
+        format_options.indent = indent;
+    }
+    if let Some(width) = options.usize("width")? {
+        format_options.width = width;
+    }
+    options.finish()?;
+
+    match brainfuck_core::format::format(&code.value(), &format_options) {
+        Ok(formatted) => Ok(code.track(quote! {
+            #formatted
+        })),
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            Ok(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Generate a Brainfuck program that prints the given text, producing it as a `&'static str`.
+///
+/// Characters are built with multiplication loops where that is shorter than a plain run
+/// of `+` or `-`. Each character is printed as one byte, so only characters up to
+/// `U+00FF` are supported, matching how `brainfuck!` turns output bytes into characters.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::text_to_brainfuck;
+///
+/// let code = text_to_brainfuck!("HI");
+/// assert_eq!(code, ">++++++++[<+++++++++>-]<.+.");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the text contains a character above
+/// `U+00FF`.
+#[proc_macro]
+pub fn text_to_brainfuck(input: TokenStream) -> TokenStream {
+    let input_str = parse_macro_input!(input as LitStr);
+
+    match text_bytes(&input_str.value()) {
+        Ok(bytes) => {
+            let code = brainfuck_core::generate::print_bytes(&bytes);
+            TokenStream::from(quote! {
+                #code
+            })
+        }
+        Err(error_msg) => TokenStream::from(quote! {
+            compile_error!(#error_msg)
+        }),
+    }
+}
+
+/// Search for a short Brainfuck program printing the given text, producing the program
+/// and its length as a `(&'static str, usize)`.
+///
+/// Unlike `text_to_brainfuck!`, several cells are set up at once by a shared
+/// multiplication loop, and every character is printed from the cell that is cheapest to
+/// reach. The search takes the best of many candidates, so the result is never longer
+/// than that of `text_to_brainfuck!` and usually a lot shorter for longer texts. The
+/// length makes a convenient baseline to beat when golfing by hand.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::{brainfuck_golf, text_to_brainfuck};
+///
+/// let (code, len) = brainfuck_golf!("Hello, World!");
+/// assert_eq!(code.len(), len);
+/// assert!(len < text_to_brainfuck!("Hello, World!").len());
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the text contains a character above
+/// `U+00FF`.
+#[proc_macro]
+pub fn brainfuck_golf(input: TokenStream) -> TokenStream {
+    let input_str = parse_macro_input!(input as LitStr);
+
+    match text_bytes(&input_str.value()) {
+        Ok(bytes) => {
+            let code = brainfuck_core::generate::golf(&bytes);
+            let len = code.len();
+            TokenStream::from(quote! {
+                (#code, #len)
+            })
+        }
+        Err(error_msg) => TokenStream::from(quote! {
+            compile_error!(#error_msg)
+        }),
+    }
+}
+
+/// The bytes `brainfuck!` would print as the given text, or an error message naming the
+/// first character above `U+00FF`
+fn text_bytes(text: &str) -> Result<Vec<u8>, String> {
+    text.chars()
+        .map(|ch| {
+            u8::try_from(ch).map_err(|_| {
+                format!(
+                    "Character {:?} (U+{:04X}) cannot be printed as a single Brainfuck byte",
+                    ch, ch as u32
+                )
+            })
+        })
+        .collect()
+}
+
+/// Execute Brainfuck code at compile time and fail compilation unless its output equals
+/// the expected string.
+///
+/// On success the macro expands to `()`, so it can be used as a statement in tests. On
+/// failure the compile error shows the first differing byte along with the expected and
+/// actual output.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_assert;
+///
+/// brainfuck_assert!("+++++[>+++++++++++++<-]>.+.", "AB");
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::brainfuck_assert;
+///
+/// // error: Brainfuck assertion failed: output differs at byte 1
+/// brainfuck_assert!("+++++[>+++++++++++++<-]>.+.", "AC");
+/// ```
+///
+/// # Errors
+///
+/// Besides a mismatching output, the macro will produce a compile-time error under the
+/// same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn brainfuck_assert(input: TokenStream) -> TokenStream {
+    let AssertArgs { code, expected } = parse_macro_input!(input as AssertArgs);
+
+    let mut interpreter = Interpreter::new();
+    let error_msg = match interpreter.execute(&code.value()) {
+        Ok(actual) => match diff::describe_mismatch(&expected.value(), &actual) {
+            None => return TokenStream::from(code.track(quote! { () })),
+            Some(mismatch) => format!("Brainfuck assertion failed: {}", mismatch),
+        },
+        Err(e) => format!("Brainfuck execution error: {}", e),
+    };
+
+    TokenStream::from(quote! {
+        compile_error!(#error_msg)
+    })
+}
+
+/// Arguments of `brainfuck_assert!`: the code and the expected output
+struct AssertArgs {
+    code: source::Code,
+    expected: LitStr,
+}
+
+impl syn::parse::Parse for AssertArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let code = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let expected = input.parse()?;
+        input.parse::<Option<syn::Token![,]>>()?;
+        Ok(Self { code, expected })
+    }
+}
+
+/// Execute two Brainfuck programs at compile time and fail compilation unless their
+/// outputs are equal.
+///
+/// The first program is the reference. With `inputs = [...]`, both programs are run once
+/// per input and must agree on all of them; without it they are run without input. Like
+/// `brainfuck_assert!`, the macro expands to `()` on success.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_eq;
+///
+/// brainfuck_eq!("++++++[>+++++++++++<-]>-.", "+++++[>+++++++++++++<-]>.");
+/// brainfuck_eq!(",[.,]", ",[.[-],]", inputs = ["", "echo"]);
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::brainfuck_eq;
+///
+/// // error: Brainfuck programs differ on input "ab": output differs at byte 1
+/// brainfuck_eq!(",[.,]", ",.", inputs = ["a", "ab"]);
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error naming the input and the first differing
+/// byte if the outputs differ, and otherwise under the same conditions as `brainfuck!`
+/// for either program.
+#[proc_macro]
+pub fn brainfuck_eq(input: TokenStream) -> TokenStream {
+    let EqArgs {
+        reference,
+        candidate,
+        inputs,
+    } = parse_macro_input!(input as EqArgs);
+
+    let runs: Vec<Option<String>> = match inputs {
+        Some(inputs) => inputs.iter().map(|input| Some(input.value())).collect(),
+        None => vec![None],
+    };
+
+    for input in &runs {
+        let run = |code: &source::Code| {
+            let mut interpreter = match input {
+                Some(input) => Interpreter::with_input(input.as_bytes()),
+                None => Interpreter::new(),
+            };
+            interpreter.execute(&code.value())
+        };
+        let error_msg = match (run(&reference), run(&candidate)) {
+            (Ok(expected), Ok(actual)) => match diff::describe_mismatch(&expected, &actual) {
+                None => continue,
+                Some(mismatch) => match input {
+                    Some(input) => format!(
+                        "Brainfuck programs differ on input {:?}: {}",
+                        input, mismatch
+                    ),
+                    None => format!("Brainfuck programs differ: {}", mismatch),
+                },
+            },
+            (Err(e), _) => format!("Brainfuck execution error in first program: {}", e),
+            (_, Err(e)) => format!("Brainfuck execution error in second program: {}", e),
+        };
+        return TokenStream::from(quote! {
+            compile_error!(#error_msg)
+        });
+    }
+
+    TokenStream::from(reference.track(candidate.track(quote! { () })))
+}
+
+/// Arguments of `brainfuck_eq!`: two programs and an optional `inputs = [...]` list
+struct EqArgs {
+    reference: source::Code,
+    candidate: source::Code,
+    inputs: Option<Vec<LitStr>>,
+}
+
+impl syn::parse::Parse for EqArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let reference = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let candidate = input.parse()?;
+        let mut inputs = None;
+        if input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            let name: syn::Ident = input.parse()?;
+            if name != "inputs" {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("unknown option `{}`", name),
+                ));
+            }
+            input.parse::<syn::Token![=]>()?;
+            let content;
+            syn::bracketed!(content in input);
+            let list =
+                content.parse_terminated(<LitStr as syn::parse::Parse>::parse, syn::Token![,])?;
+            inputs = Some(list.into_iter().collect());
+            input.parse::<Option<syn::Token![,]>>()?;
+        }
+        Ok(Self {
+            reference,
+            candidate,
+            inputs,
+        })
+    }
+}
+
+/// Generate a `#[test]` function checking that the transpiled program agrees with the
+/// reference interpreter.
+///
+/// The test runs every input of `inputs = [...]` (or no input without it) through
+/// `brainfuck_core::interpret::run` and through the program transpiled to Rust (see
+/// `brainfuck_transpile!`), both without and with the loop optimizations, and asserts
+/// that all three outputs are identical. This guards the optimizer and the transpiler
+/// against semantic drift on programs that matter to the invoking crate, which needs
+/// `brainfuck-core` as a (dev-)dependency.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_difftest;
+///
+/// brainfuck_difftest!(",[>++<-]>[.-]", inputs = ["", "\u{3}", "abc"]);
+/// brainfuck_difftest!(include_str!("../programs/hello.b"), name = "hello_difftest");
+/// ```
+///
+/// # Options
+///
+/// - `inputs = ["<input>", ...]` - The inputs to run the program over
+/// - `name = "<name>"` - The name of the test function (default `difftest`)
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the code has unmatched brackets, or if
+/// an option is unknown or repeated.
+#[proc_macro]
+pub fn brainfuck_difftest(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as DifftestArgs);
+
+    TokenStream::from(expand_difftest(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_difftest(args: DifftestArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let DifftestArgs { code, inputs, name } = args;
+    let ops = ir::parse(&code.value())
+        .map_err(|e| syn::Error::new(code.span(), format!("Brainfuck syntax error: {}", e)))?;
+    let name = match name {
+        Some(name) => function_name(Some(name))?,
+        None => syn::Ident::new("difftest", proc_macro2::Span::call_site()),
+    };
+
+    let transpile = |name: &str, ops: &[ir::Op]| -> proc_macro2::TokenStream {
+        transpile::to_rust(name, ops)
+            .parse()
+            .expect("transpiled code is valid Rust")
+    };
+    let unoptimized = transpile("unoptimized", &ops);
+    let optimized = transpile("optimized", &optimize::optimize(ops));
+    let tracking = code.tracking();
+    let literal = code.literal();
+    let inputs = inputs
+        .unwrap_or_else(|| vec![LitStr::new("", proc_macro2::Span::call_site())])
+        .into_iter()
+        .map(|input| syn::LitByteStr::new(input.value().as_bytes(), input.span()));
+    Ok(quote! {
+        #[test]
+        fn #name() {
+            #tracking
+            #unoptimized
+            #optimized
+            let inputs: &[&[u8]] = &[#(#inputs),*];
+            for &input in inputs {
+                let expected = ::brainfuck_core::interpret::run(#literal, input)
+                    .expect("the reference interpreter failed");
+                let shown = ::std::string::String::from_utf8_lossy(input);
+                ::std::assert_eq!(
+                    unoptimized(input),
+                    expected,
+                    "the transpiled program differs on input {:?}",
+                    shown
+                );
+                ::std::assert_eq!(
+                    optimized(input),
+                    expected,
+                    "the optimized transpiled program differs on input {:?}",
+                    shown
+                );
+            }
+        }
+    })
+}
+
+/// Arguments of `brainfuck_difftest!`: the code followed by optional options
+struct DifftestArgs {
+    code: source::Code,
+    inputs: Option<Vec<LitStr>>,
+    name: Option<LitStr>,
+}
+
+impl syn::parse::Parse for DifftestArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let code = input.parse()?;
+        let mut inputs = None;
+        let mut name = None;
+        while input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            let option: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            if option == "inputs" && inputs.is_none() {
+                let content;
+                syn::bracketed!(content in input);
+                let list = content
+                    .parse_terminated(<LitStr as syn::parse::Parse>::parse, syn::Token![,])?;
+                inputs = Some(list.into_iter().collect());
+            } else if option == "name" && name.is_none() {
+                name = Some(input.parse()?);
+            } else {
+                let message = if option == "inputs" || option == "name" {
+                    format!("duplicate option `{}`", option)
+                } else {
+                    format!("unknown option `{}`", option)
+                };
+                return Err(syn::Error::new(option.span(), message));
+            }
+        }
+        Ok(Self { code, inputs, name })
+    }
+}
+
+/// Generate one `#[test]` function per Brainfuck test case.
+///
+/// Each entry is written as `name: { code: "...", input: "...", expect: "..." }`, where
+/// `input` is optional. The code accepts the same forms as `brainfuck!` and is checked
+/// for unmatched brackets while compiling, but the test itself runs the program at
+/// runtime with `brainfuck_core::interpret::run`, so the invoking crate needs
+/// `brainfuck-core` as a (dev-)dependency. Output bytes are compared as characters, like
+/// the output of `brainfuck!`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_testcases;
+///
+/// brainfuck_testcases! {
+///     prints_a: { code: "+++++[>+++++++++++++<-]>.", expect: "A" },
+///     echoes: { code: ",[.,]", input: "hello", expect: "hello" },
+/// }
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if an entry is missing `code` or `expect`,
+/// has an unknown or duplicate field, or if its code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_testcases(input: TokenStream) -> TokenStream {
+    let cases::Cases(cases) = parse_macro_input!(input as cases::Cases);
+
+    let mut tests = Vec::new();
+    for case in cases {
+        let name = &case.name;
+        let Some(expect) = &case.expect else {
+            let error = syn::Error::new(name.span(), format!("`{}` is missing `expect`", name));
+            return TokenStream::from(error.into_compile_error());
+        };
+        if let Err(e) = Interpreter::find_matching_brackets(&case.code.value()) {
+            let error_msg = format!("Brainfuck syntax error in `{}`: {}", name, e);
+            return TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            });
+        }
+
+        let tracking = case.code.tracking();
+        let literal = case.code.literal();
+        let input = case.input_bytes();
+        tests.push(quote! {
+            #[test]
+            fn #name() {
+                #tracking
+                let output = ::brainfuck_core::interpret::run(#literal, #input)
+                    .expect("Brainfuck program failed");
+                let output: ::std::string::String =
+                    output.into_iter().map(::std::primitive::char::from).collect();
+                ::std::assert_eq!(output, #expect);
+            }
+        });
+    }
+
+    TokenStream::from(quote! {
+        #(#tests)*
+    })
+}
+
+/// Generate a `#[test]` function grading an answer to a Brainfuck reading exercise.
+///
+/// The macro does not expand to the output of the program. The test runs the program
+/// with `brainfuck_core::interpret::run` and checks that it prints the answer, failing
+/// with `incorrect, differs at byte N` without revealing the expected output, so the
+/// test can grade students' answers to "what does this program print?" automatically.
+/// The invoking crate needs `brainfuck-core` as a (dev-)dependency.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_exercise;
+///
+/// brainfuck_exercise!("++++++++[>++++++++<-]>+.+.", answer = "AB");
+/// brainfuck_exercise!(",[+.,]", input = "HAL", answer = "IBM", name = "exercise_2");
+/// ```
+///
+/// # Options
+///
+/// - `answer = "<text>"` - The answer to grade, compared character by character with
+///   the output like `brainfuck!` (required)
+/// - `input = "<text>"` - Input read by `,`; end of input stores 0
+/// - `name = "<name>"` - The name of the test function (default `exercise`)
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if `answer` is missing or the code has
+/// unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_exercise(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as args::MacroArgs);
+    TokenStream::from(expand_exercise(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_exercise(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs { code, mut options } = args;
+    let answer = options.string("answer")?;
+    let input = options.string("input")?;
+    let name = options.string("name")?;
+    options.finish()?;
+
+    let Some(answer) = answer else {
+        return Err(syn::Error::new(
+            code.span(),
+            "`brainfuck_exercise!` expects an `answer`",
+        ));
+    };
+    if let Err(e) = Interpreter::find_matching_brackets(&code.value()) {
+        return Err(syn::Error::new(
+            code.span(),
+            format!("Brainfuck syntax error: {}", e),
+        ));
+    }
+    let name = match name {
+        Some(name) => function_name(Some(name))?,
+        None => syn::Ident::new("exercise", proc_macro2::Span::call_site()),
+    };
+
+    let tracking = code.tracking();
+    let literal = code.literal();
+    let input = input.map_or_else(String::new, |input| input.value());
+    let input = syn::LitByteStr::new(input.as_bytes(), proc_macro2::Span::call_site());
+    Ok(quote! {
+        #[test]
+        fn #name() {
+            #tracking
+            let output = ::brainfuck_core::interpret::run(#literal, #input)
+                .expect("Brainfuck program failed");
+            let mut answer = #answer.chars();
+            let mut output = output.into_iter().map(::std::primitive::char::from);
+            let mut index = 0;
+            loop {
+                match (answer.next(), output.next()) {
+                    (::std::option::Option::None, ::std::option::Option::None) => break,
+                    (a, o) if a == o => index += 1,
+                    _ => ::std::panic!("incorrect, differs at byte {}", index),
+                }
+            }
+        }
+    })
+}
+
+/// Generate tests checking that every instruction of a program carries weight.
+///
+/// The macro expands to a module of tests, named `mutants` unless `name` is given. Its
+/// test `original` checks that the program prints `expect`, and one test
+/// `mutant_<position>` per instruction applies every single-instruction mutation at that
+/// character position and checks that the mutated program no longer prints `expect`:
+/// commands are deleted and `+-<>` replaced by their opposite, loops are unwrapped at
+/// their `[` and deleted at their `]`. A mutant that fails to run, for example by
+/// exceeding the step limit, counts as caught. A surviving mutant points at an
+/// instruction the output does not depend on, which golfers can usually drop.
+///
+/// The programs run at runtime with `brainfuck_core::interpret::run`, so the invoking
+/// crate needs `brainfuck-core` as a (dev-)dependency.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_mutants;
+///
+/// brainfuck_mutants!("++++++++[>++++++++<-]>+.", expect = "A");
+/// // The output does not depend on the trailing `>`
+/// brainfuck_mutants!(",+.>", input = "a", expect = "b", allow = "3", name = "next");
+/// ```
+///
+/// # Options
+///
+/// - `expect = "<text>"` - The output of the program, compared character by character
+///   like `brainfuck!` (required)
+/// - `input = "<text>"` - Input read by `,`; end of input stores 0
+/// - `allow = "<positions>"` - Comma-separated character positions of instructions
+///   whose mutants may survive; they get no test
+/// - `name = "<name>"` - The name of the module of tests (default `mutants`)
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if `expect` is missing, the code has
+/// unmatched brackets, or `allow` lists something other than positions of instructions.
+#[proc_macro]
+pub fn brainfuck_mutants(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as args::MacroArgs);
+    TokenStream::from(expand_mutants(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_mutants(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs { code, mut options } = args;
+    let expect = options.string("expect")?;
+    let input = options.string("input")?;
+    let allow = options.string("allow")?;
+    let name = options.string("name")?;
+    options.finish()?;
+
+    let Some(expect) = expect else {
+        return Err(syn::Error::new(
+            code.span(),
+            "`brainfuck_mutants!` expects an `expect`",
+        ));
+    };
+    let source = code.value();
+    if let Err(e) = Interpreter::find_matching_brackets(&source) {
+        return Err(syn::Error::new(
+            code.span(),
+            format!("Brainfuck syntax error: {}", e),
+        ));
+    }
+    let mut allowed = Vec::new();
+    if let Some(allow) = &allow {
+        for position in allow.value().split(',').map(str::trim) {
+            let is_instruction = |position: &usize| {
+                source
+                    .chars()
+                    .nth(*position)
+                    .is_some_and(|c| "<>+-.,[]".contains(c))
+            };
+            match position.parse().ok().filter(is_instruction) {
+                Some(position) => allowed.push(position),
+                None => {
+                    return Err(syn::Error::new(
+                        allow.span(),
+                        format!(
+                            "option `allow`: `{}` is not the position of an instruction",
+                            position
+                        ),
+                    ))
+                }
+            }
+        }
+    }
+    let name = match name {
+        Some(name) => function_name(Some(name))?,
+        None => syn::Ident::new("mutants", proc_macro2::Span::call_site()),
+    };
+
+    let mut positions: Vec<(usize, Vec<mutants::Mutant>)> = Vec::new();
+    for mutant in mutants::mutants(&source) {
+        if allowed.contains(&mutant.position) {
+            continue;
+        }
+        match positions.last_mut() {
+            Some((position, group)) if *position == mutant.position => group.push(mutant),
+            _ => positions.push((mutant.position, vec![mutant])),
+        }
+    }
+    let tests = positions.iter().map(|(position, group)| {
+        let test = quote::format_ident!("mutant_{}", position);
+        let descriptions = group.iter().map(|mutant| &mutant.description);
+        let codes = group.iter().map(|mutant| &mutant.code);
+        quote! {
+            #[test]
+            pub(super) fn #test() {
+                let mutants: &[(&str, &str)] = &[#((#descriptions, #codes)),*];
+                for &(description, code) in mutants {
+                    ::std::assert!(
+                        !prints_expected(code),
+                        "mutant survived: {} still prints the expected output",
+                        description
+                    );
+                }
+            }
+        }
+    });
+
+    let tracking = code.tracking();
+    let literal = code.literal();
+    let input = input.map_or_else(String::new, |input| input.value());
+    let input = syn::LitByteStr::new(input.as_bytes(), proc_macro2::Span::call_site());
+    Ok(quote! {
+        #[cfg(test)]
+        mod #name {
+            fn prints_expected(code: &str) -> bool {
+                ::brainfuck_core::interpret::run(code, #input).is_ok_and(|output| {
+                    output
+                        .into_iter()
+                        .map(::std::primitive::char::from)
+                        .eq(#expect.chars())
+                })
+            }
+
+            #[test]
+            pub(super) fn original() {
+                #tracking
+                ::std::assert!(
+                    prints_expected(#literal),
+                    "the program does not print the expected output"
+                );
+            }
+
+            #(#tests)*
+        }
+    })
+}
+
+/// Generate one `#[test]` function per golden Brainfuck program in a directory.
+///
+/// The argument is a directory relative to `CARGO_MANIFEST_DIR`. Every program `X.b` (or
+/// `X.bf`) with a sibling `X.out` becomes a test named after the file stem in snake case,
+/// which runs the program with `brainfuck_core::interpret::run` over the contents of
+/// `X.in`, if there is one, and compares the output bytes to `X.out`. Programs without an
+/// `X.out` are skipped; an empty one is enough to start with.
+///
+/// With the environment variable `UPDATE_GOLDEN` set when running the tests, every test
+/// writes the output of its program to `X.out` instead of comparing, so expectations are
+/// updated with `UPDATE_GOLDEN=1 cargo test` and reviewed in the diff. The invoking crate
+/// needs `brainfuck-core` as a (dev-)dependency.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_golden_tests;
+///
+/// // Tests `hello` for tests/bf/hello.b + tests/bf/hello.out, ...
+/// brainfuck_golden_tests!("tests/bf");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the directory cannot be read, if none
+/// of its programs has an `.out` file, or if a program has unmatched brackets. Programs
+/// and inputs are tracked, but files added to the directory later are not noticed until
+/// the invoking crate is rebuilt.
+#[proc_macro]
+pub fn brainfuck_golden_tests(input: TokenStream) -> TokenStream {
+    let dir = parse_macro_input!(input as LitStr);
+
+    TokenStream::from(expand_golden_tests(dir).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_golden_tests(dir: LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    let programs =
+        files::find_programs(&dir.value()).map_err(|e| syn::Error::new(dir.span(), e))?;
+
+    let mut tests = Vec::new();
+    for path in programs {
+        let out_path = path.with_extension("out");
+        if !out_path.is_file() {
+            continue;
+        }
+        let code = std::fs::read_to_string(&path).map_err(|e| {
+            syn::Error::new(
+                dir.span(),
+                format!("Failed to read Brainfuck file '{}': {}", path.display(), e),
+            )
+        })?;
+        if let Err(e) = Interpreter::find_matching_brackets(&code) {
+            let error_msg = format!("Brainfuck syntax error in '{}': {}", path.display(), e);
+            return Err(syn::Error::new(dir.span(), error_msg));
+        }
+
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = syn::Ident::new(
+            &files::const_name(&stem).to_lowercase(),
+            proc_macro2::Span::call_site(),
+        );
+        let program = path.to_string_lossy();
+        let input_path = path.with_extension("in");
+        let input = if input_path.is_file() {
+            let input_path = input_path.to_string_lossy();
+            quote! { include_bytes!(#input_path) }
+        } else {
+            quote! { b"" }
+        };
+        let out_path = out_path.to_string_lossy();
+        tests.push(quote! {
+            #[test]
+            fn #name() {
+                let output = ::brainfuck_core::interpret::run(include_str!(#program), #input)
+                    .expect("Brainfuck program failed");
+                if ::std::env::var_os("UPDATE_GOLDEN").is_some() {
+                    ::std::fs::write(#out_path, &output).expect("failed to update golden file");
+                    return;
+                }
+                let expected = ::std::fs::read(#out_path).expect("failed to read golden file");
+                ::std::assert!(
+                    output == expected,
+                    "output of {} differs from {}\n  output: {:?}\nexpected: {:?}",
+                    #program,
+                    #out_path,
+                    ::std::string::String::from_utf8_lossy(&output),
+                    ::std::string::String::from_utf8_lossy(&expected),
+                );
+            }
+        });
+    }
+
+    if tests.is_empty() {
+        return Err(syn::Error::new(
+            dir.span(),
+            format!(
+                "No Brainfuck programs with an `.out` file in '{}'",
+                dir.value()
+            ),
+        ));
+    }
+    Ok(quote! {
+        #(#tests)*
+    })
+}
+
+/// Generate one Criterion benchmark function per Brainfuck program.
+///
+/// Entries are written as `name: { code: "...", input: "..." }`, where `input` is
+/// optional. Each becomes `pub fn name(c: &mut criterion::Criterion)`, which benchmarks
+/// the program transpiled to Rust (see `brainfuck_transpile!`) as a group called `name`
+/// with two functions: `unoptimized`, with only runs of commands folded, and `optimized`,
+/// with clear and multiplication loops replaced as well. Benchmarking alternative
+/// encodings of a program is a matter of adding one entry per encoding.
+///
+/// The invoking crate needs `criterion` as a (dev-)dependency, and the benchmark target
+/// `harness = false`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_bench;
+/// use criterion::{criterion_group, criterion_main};
+///
+/// brainfuck_bench! {
+///     hello: { code: include_str!("../programs/hello.b") },
+///     echo: { code: ",[.,]", input: "The quick brown fox" },
+/// }
+///
+/// criterion_group!(benches, hello, echo);
+/// criterion_main!(benches);
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if an entry has an unknown or duplicate
+/// field, or if its code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_bench(input: TokenStream) -> TokenStream {
+    let cases::Cases(cases) = parse_macro_input!(input as cases::Cases);
+
+    let mut benches = Vec::new();
+    for case in cases {
+        let name = &case.name;
+        if let Some(expect) = &case.expect {
+            let error = syn::Error::new(expect.span(), "benchmarks do not take `expect`");
+            return TokenStream::from(error.into_compile_error());
+        }
+        let ops = match ir::parse(&case.code.value()) {
+            Ok(ops) => ops,
+            Err(e) => {
+                let error_msg = format!("Brainfuck syntax error in `{}`: {}", name, e);
+                return TokenStream::from(quote! {
+                    compile_error!(#error_msg)
+                });
+            }
+        };
+
+        let transpile = |name: &str, ops: &[ir::Op]| -> proc_macro2::TokenStream {
+            transpile::to_rust(name, ops)
+                .parse()
+                .expect("transpiled code is valid Rust")
+        };
+        let unoptimized = transpile("unoptimized", &ops);
+        let optimized = transpile("optimized", &optimize::optimize(ops));
+        let tracking = case.code.tracking();
+        let group = name.to_string();
+        let input = case.input_bytes();
+        benches.push(quote! {
+            pub fn #name(c: &mut ::criterion::Criterion) {
+                #tracking
+                #unoptimized
+                #optimized
+                let input: &[u8] = #input;
+                let mut group = c.benchmark_group(#group);
+                group.bench_function("unoptimized", |b| {
+                    b.iter(|| unoptimized(::std::hint::black_box(input)))
+                });
+                group.bench_function("optimized", |b| {
+                    b.iter(|| optimized(::std::hint::black_box(input)))
+                });
+                group.finish();
+            }
+        });
+    }
+
+    TokenStream::from(quote! {
+        #(#benches)*
+    })
+}
+
+/// Expand run-length shorthand at compile time, producing plain Brainfuck code as a
+/// `&'static str`.
+///
+/// A decimal count written directly before a command repeats the command, so `65+` becomes
+/// sixty-five `+`. Digits that are not directly followed by a command are left as they are,
+/// as is everything else, so comments survive.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_expand;
+///
+/// let code = brainfuck_expand!("65+ . 3> 2- Cell 4");
+/// assert_eq!(code, format!("{} . >>> -- Cell 4", "+".repeat(65)));
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if a count is written before `[` or `]`, if
+/// a count exceeds the maximum step limit, or if the expanded code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_expand(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+
+    let error_msg = match shorthand::expand(&code.value()) {
+        Ok(expanded) => match Interpreter::find_matching_brackets(&expanded) {
+            Ok(_) => {
+                return TokenStream::from(code.track(quote! {
+                    #expanded
+                }))
+            }
+            Err(e) => format!("Brainfuck syntax error: {}", e),
+        },
+        Err(e) => format!("Brainfuck shorthand error: {}", e),
+    };
+
+    TokenStream::from(quote! {
+        compile_error!(#error_msg)
+    })
+}
+
+/// Define a documented constant holding the output of Brainfuck code.
+///
+/// The generated doc comment appends the formatted program, a table of the comments
+/// written next to commands in the source, and the computed output to any doc comment
+/// written on the item, so examples published as rustdoc explain themselves.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_doc;
+///
+/// brainfuck_doc! {
+///     /// Prints the letter A.
+///     pub const LETTER_A = "
+///         +++++              Set cell 0 to 5
+///         [>+++++++++++++<-] Add 13 to cell 1 five times
+///         >.                 Print cell 1
+///     ";
+/// }
+///
+/// assert_eq!(LETTER_A, "A");
+/// ```
+///
+/// The type may be spelled out (`pub const LETTER_A: &str = "..."`), but must be a
+/// string slice.
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn brainfuck_doc(input: TokenStream) -> TokenStream {
+    let DocItem {
+        attrs,
+        vis,
+        name,
+        ty,
+        code,
+    } = parse_macro_input!(input as DocItem);
+
+    let mut interpreter = Interpreter::new();
+    match interpreter.execute(&code.value()) {
+        Ok(output) => {
+            let doc = doc::render(&code.value(), &output);
+            let separator = (!attrs.is_empty()).then(|| quote! { #[doc = ""] });
+            let ty = ty.unwrap_or_else(|| syn::parse_quote!(&str));
+            let value = code.track(quote! { #output });
+            TokenStream::from(quote! {
+                #(#attrs)*
+                #separator
+                #[doc = #doc]
+                #vis const #name: #ty = #value;
+            })
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck execution error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg);
+            })
+        }
+    }
+}
+
+/// Input of `brainfuck_doc!`: `#[attrs] vis const NAME(: Type)? = code;`
+struct DocItem {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    name: syn::Ident,
+    ty: Option<syn::Type>,
+    code: source::Code,
+}
+
+impl syn::parse::Parse for DocItem {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<syn::Token![const]>()?;
+        let name = input.parse()?;
+        let ty = if input.parse::<Option<syn::Token![:]>>()?.is_some() {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        input.parse::<syn::Token![=]>()?;
+        let code = input.parse()?;
+        input.parse::<syn::Token![;]>()?;
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            ty,
+            code,
+        })
+    }
+}
+
+/// Execute the Brainfuck program of the arm matching the active configuration and produce
+/// its output as a `&'static str`.
+///
+/// Arms are tried in order, like `match` arms; a final `_` arm matches when no other
+/// does. Every program is executed, so errors are reported on every platform, not only
+/// the one being compiled for.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_cfg_match;
+///
+/// // The path separator: '\' on Windows, '/' elsewhere
+/// let separator = brainfuck_cfg_match! {
+///     cfg(windows) => "++++[>+++++++++++++++++++++++<-]>.",
+///     _ => "+++++[>+++++++++<-]>++.",
+/// };
+/// assert_eq!(separator, std::path::MAIN_SEPARATOR_STR);
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if any program fails under the same
+/// conditions as `brainfuck!`, or if no arm matches the configuration.
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::brainfuck_cfg_match;
+///
+/// // `any()` never holds, and there is no `_` arm
+/// let output = brainfuck_cfg_match! { cfg(any()) => "+." };
+/// ```
+#[proc_macro]
+pub fn brainfuck_cfg_match(input: TokenStream) -> TokenStream {
+    let CfgArms { arms, fallback } = parse_macro_input!(input as CfgArms);
+
+    // Each arm applies when its predicate holds and no earlier one does
+    let mut items = Vec::new();
+    let mut previous = Vec::new();
+    let has_fallback = fallback.is_some();
+    let fallback = fallback.map(|code| (None, code));
+    for (predicate, code) in arms.into_iter().map(|(p, c)| (Some(p), c)).chain(fallback) {
+        let output = match Interpreter::new().execute(&code.value()) {
+            Ok(output) => output,
+            Err(e) => return execution_error(&code, e),
+        };
+        let condition = match &predicate {
+            Some(predicate) => quote! { all(#predicate, not(any(#(#previous),*))) },
+            None => quote! { not(any(#(#previous),*)) },
+        };
+        items.push(code.tracking());
+        items.push(quote! {
+            #[cfg(#condition)]
+            const BRAINFUCK_ARM: &str = #output;
+        });
+        previous.extend(predicate);
+    }
+    if !has_fallback {
+        items.push(quote! {
+            #[cfg(not(any(#(#previous),*)))]
+            const BRAINFUCK_ARM: &str = ::core::compile_error!(
+                "no arm of brainfuck_cfg_match! matches the configuration; add a `_` arm"
+            );
+        });
+    }
+
+    TokenStream::from(quote! {
+        {
+            #(#items)*
+            BRAINFUCK_ARM
+        }
+    })
+}
+
+/// A compile error for a failed execution, at the code
+fn execution_error(code: &source::Code, error: brainfuck_core::machine::Error) -> TokenStream {
+    let error_msg = format!("Brainfuck execution error: {}", error);
+    TokenStream::from(quote::quote_spanned! {code.span()=>
+        compile_error!(#error_msg)
+    })
+}
+
+/// Input of `brainfuck_cfg_match!`: `cfg(predicate) => code` arms, then optionally
+/// `_ => code`, separated by commas
+struct CfgArms {
+    arms: Vec<(proc_macro2::TokenStream, source::Code)>,
+    fallback: Option<source::Code>,
+}
+
+impl syn::parse::Parse for CfgArms {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut arms = Vec::new();
+        let mut fallback = None;
+        while !input.is_empty() {
+            if fallback.is_some() {
+                return Err(input.error("the `_` arm must be the last one"));
+            }
+            if input.parse::<Option<syn::Token![_]>>()?.is_some() {
+                input.parse::<syn::Token![=>]>()?;
+                fallback = Some(input.parse()?);
+            } else {
+                let cfg: syn::Ident = input.parse()?;
+                if cfg != "cfg" {
+                    return Err(syn::Error::new(cfg.span(), "expected `cfg(...)` or `_`"));
+                }
+                let predicate;
+                syn::parenthesized!(predicate in input);
+                let predicate: proc_macro2::TokenStream = predicate.parse()?;
+                input.parse::<syn::Token![=>]>()?;
+                arms.push((predicate, input.parse()?));
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+        if arms.is_empty() && fallback.is_none() {
+            return Err(input.error("expected at least one `cfg(...) => code` arm"));
+        }
+        Ok(Self { arms, fallback })
+    }
+}
+
+/// Define an iterator type that executes Brainfuck code lazily at runtime, yielding each
+/// output byte as soon as it is produced.
+///
+/// The code is checked for unmatched brackets while compiling and executed by
+/// `brainfuck_core::interpret::Outputs`, so the invoking crate needs `brainfuck-core` as
+/// a dependency. There is no step limit, which makes the type suitable for programs with
+/// very long or infinite output. The type has a `new()` constructor (also available as
+/// `Default`) and `with_input(&[u8])` for programs reading input.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_iter;
+///
+/// brainfuck_iter! {
+///     /// Counts upwards forever.
+///     pub struct Counter = "+[>+.<]";
+/// }
+///
+/// let first: Vec<u8> = Counter::new().take(3).collect();
+/// assert_eq!(first, [1, 2, 3]);
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched
+/// brackets. Moving the pointer off the tape panics when the iterator reaches it.
+#[proc_macro]
+pub fn brainfuck_iter(input: TokenStream) -> TokenStream {
+    let IterItem {
+        attrs,
+        vis,
+        name,
+        code,
+    } = parse_macro_input!(input as IterItem);
+
+    if let Err(e) = Interpreter::find_matching_brackets(&code.value()) {
+        let error_msg = format!("Brainfuck syntax error: {}", e);
+        return TokenStream::from(quote! {
+            compile_error!(#error_msg);
+        });
+    }
+
+    let literal = code.literal();
+    let tracking = code.tracking();
+    TokenStream::from(quote! {
+        #(#attrs)*
+        #vis struct #name(::brainfuck_core::interpret::Outputs);
+
+        impl #name {
+            /// Start the program without input
+            pub fn new() -> Self {
+                Self::with_input(&[])
+            }
+
+            /// Start the program, reading `,` from `input`
+            pub fn with_input(input: &[u8]) -> Self {
+                Self(
+                    ::brainfuck_core::interpret::Outputs::new(#literal, input)
+                        .expect("brackets are checked at compile time"),
+                )
+            }
+        }
+
+        impl ::std::default::Default for #name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl ::std::iter::Iterator for #name {
+            type Item = u8;
+
+            fn next(&mut self) -> ::std::option::Option<u8> {
+                self.0
+                    .next()
+                    .map(|byte| byte.unwrap_or_else(|e| ::std::panic!("Brainfuck execution error: {}", e)))
+            }
+        }
+
+        #tracking
+    })
+}
+
+/// Input of `brainfuck_iter!`: `#[attrs] vis struct Name = code;`
+struct IterItem {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    name: syn::Ident,
+    code: source::Code,
+}
+
+impl syn::parse::Parse for IterItem {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<syn::Token![struct]>()?;
+        let name = input.parse()?;
+        input.parse::<syn::Token![=]>()?;
+        let code = input.parse()?;
+        input.parse::<syn::Token![;]>()?;
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            code,
+        })
+    }
+}
+
+/// Define a module holding everything known about a Brainfuck program: its source, its
+/// output, the number of steps it took, its final tape, and a transpiled `run` function.
+///
+/// The fields are those of `brainfuck_testcases!`: `code`, and optionally `input` read by
+/// `,`. The constants are:
+///
+/// - `SOURCE: &str` - The code
+/// - `OUTPUT: &str` - The output, computed at compile time
+/// - `STEPS: usize` - The number of steps taken, counting every character of the source
+///   like the step limit
+/// - `TAPE: &[u8]` - The tape after the program ended, up to the last nonzero cell
+///
+/// `run(input: &[u8]) -> Vec<u8>` runs the program at runtime, as generated by
+/// `brainfuck_transpile!`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_item;
+///
+/// brainfuck_item! {
+///     /// Doubles a number.
+///     pub mod double {
+///         code: "++[>++<-]>.",
+///     }
+/// }
+///
+/// assert_eq!(double::OUTPUT, "\u{4}");
+/// assert_eq!(double::TAPE, [0, 4]);
+/// assert_eq!(double::STEPS, 17);
+/// assert_eq!(double::run(&[]), double::OUTPUT.as_bytes());
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error under the same conditions as `brainfuck!`,
+/// except that `,` reads the input.
+#[proc_macro]
+pub fn brainfuck_item(input: TokenStream) -> TokenStream {
+    let ModuleItem { attrs, vis, case } = parse_macro_input!(input as ModuleItem);
+    let cases::Case {
+        name,
+        code,
+        input,
+        expect,
+    } = case;
+    if let Some(expect) = expect {
+        let error = syn::Error::new(expect.span(), "unknown field `expect`");
+        return TokenStream::from(error.into_compile_error());
+    }
+
+    let source = code.value();
+    let mut interpreter = match &input {
+        Some(input) => Interpreter::with_input(input.value().as_bytes()),
+        None => Interpreter::new(),
+    };
+    let output = match interpreter.execute(&source) {
+        Ok(output) => output,
+        Err(e) => return execution_error(&code, e),
+    };
+    let steps = interpreter.steps();
+    let used = interpreter.tape().iter().rposition(|&cell| cell != 0);
+    // Cells are 8 bits wide in the macros
+    let tape = interpreter.tape()[..used.map_or(0, |last| last + 1)]
+        .iter()
+        .map(|&cell| cell as u8);
+    // Execution succeeded, so the brackets match
+    let ops = ir::parse(&source).expect("brackets were checked by the execution");
+    let run: proc_macro2::TokenStream = transpile::to_rust("run", &optimize::optimize(ops))
+        .parse()
+        .expect("transpiled code is valid Rust");
+    let tracking = code.tracking();
+
+    TokenStream::from(quote! {
+        #(#attrs)*
+        #vis mod #name {
+            /// The source of the program
+            pub const SOURCE: &str = #source;
+            /// The output of the program, computed at compile time
+            pub const OUTPUT: &str = #output;
+            /// The number of steps the program took
+            pub const STEPS: usize = #steps;
+            /// The tape after the program ended, up to the last nonzero cell
+            pub const TAPE: &[u8] = &[#(#tape),*];
+            /// Run the program at runtime
+            #run
+            #tracking
+        }
+    })
+}
+
+/// Input of `brainfuck_item!`: `#[attrs] vis mod name { code: ..., input: ... }`
+struct ModuleItem {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    case: cases::Case,
+}
+
+impl syn::parse::Parse for ModuleItem {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<syn::Token![mod]>()?;
+        let name = input.parse()?;
+        let content;
+        syn::braced!(content in input);
+        let case = cases::Case::parse_fields(name, &content)?;
+        Ok(Self { attrs, vis, case })
+    }
+}
+
+/// Generate a `fn main()` that runs Brainfuck code at runtime against stdin and stdout.
+///
+/// The program is translated to Rust with the same backend as `brainfuck_transpile!`, so
+/// a Brainfuck program can be shipped as a standalone binary with a single macro call in
+/// `main.rs`. Output is flushed before every read, and `,` stores 0 at end of input.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// // src/main.rs: echo stdin to stdout
+/// brainfuck_macro::brainfuck_main!(",[.,]");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+/// Moving the pointer out of bounds panics at runtime.
+#[proc_macro]
+pub fn brainfuck_main(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_main", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let main: proc_macro2::TokenStream = transpile::to_rust_main(&optimize::optimize(ops))
+                .parse()
+                .expect("transpiled code is valid Rust");
+            let tracking = code.tracking();
+            TokenStream::from(quote! {
+                #main
+                #tracking
+            })
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg);
+            })
+        }
+    }
+}
+
+/// Generate a C ABI function that runs Brainfuck code at runtime, for exposing programs
+/// through FFI from a `cdylib`.
+///
+/// The macro expands to
+/// `#[no_mangle] pub unsafe extern "C" fn name(input: *const u8, len: usize, out: *mut u8,
+/// cap: usize) -> isize`, running the program transpiled like `brainfuck_transpile!` over
+/// the `len` input bytes and writing its output to the `cap` bytes at `out`. At end of
+/// input `,` stores 0. The function returns:
+///
+/// - the number of output bytes written
+/// - `-1` if the output did not fit into `cap` bytes; the first `cap` bytes are written
+/// - `-2` if the program moved the pointer off the tape
+///
+/// # Safety
+///
+/// `input` must be valid for reading `len` bytes and `out` for writing `cap` bytes. Either
+/// may be null if its length is 0.
+///
+/// # Example
+///
+/// ```rust
+/// brainfuck_macro::brainfuck_extern_fn!(",[+.,]", name = "shift");
+///
+/// let mut out = [0u8; 8];
+/// let written = unsafe { shift(b"HAL".as_ptr(), 3, out.as_mut_ptr(), out.len()) };
+/// assert_eq!(&out[..written as usize], b"IBM");
+/// ```
+///
+/// # Options
+///
+/// - `name = "<ident>"` - Name of the generated function and its symbol (default `"run"`)
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets
+/// or the name is not a valid identifier.
+#[proc_macro]
+pub fn brainfuck_extern_fn(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_extern_fn", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_extern_fn(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_extern_fn(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let name = function_name(options.string("name")?)?;
+    options.finish()?;
+
+    let ops = match ir::parse(&code.value()) {
+        Ok(ops) => optimize::optimize(ops),
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            return Ok(quote! {
+                compile_error!(#error_msg);
+            });
+        }
+    };
+    let run: proc_macro2::TokenStream = transpile::to_rust_no_std("run", &ops)
+        .parse()
+        .expect("transpiled code is valid Rust");
+    let tracking = code.tracking();
+    Ok(quote! {
+        #tracking
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn #name(
+            input: *const u8,
+            len: usize,
+            out: *mut u8,
+            cap: usize,
+        ) -> isize {
+            #run
+
+            let input: &[u8] = if len == 0 {
+                &[]
+            } else {
+                unsafe { ::core::slice::from_raw_parts(input, len) }
+            };
+            let mut written: usize = 0;
+            let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                let mut tape = ::std::vec![0u8; #TAPE_SIZE];
+                run(&mut tape, input, |byte| {
+                    if written < cap {
+                        unsafe { out.add(written).write(byte) };
+                    }
+                    written += 1;
+                });
+            }));
+            match result {
+                Err(_) => -2,
+                Ok(()) if written > cap => -1,
+                Ok(()) => written as isize,
+            }
+        }
+    })
+}
+
+/// Generate an `async fn` that runs Brainfuck code at runtime over an asynchronous reader
+/// and writer, for async servers.
+///
+/// The macro expands to `pub async fn name(input: impl AsyncRead + Unpin, output: impl
+/// AsyncWrite + Unpin) -> std::io::Result<()>` with the traits of `futures-io`, which the
+/// invoking crate needs as a dependency (Tokio streams implement them through
+/// `tokio-util`'s `compat`). The program is transpiled like `brainfuck_transpile!`, and
+/// yields to the executor at regular step intervals, so a long-running program does not
+/// block other tasks. Output is flushed before every read and at the end, and `,` stores 0
+/// at end of input.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// brainfuck_macro::brainfuck_async_fn!(",[.,]", name = "echo", yield_every = 1000);
+///
+/// let (reader, writer) = socket.split();
+/// echo(reader, writer).await?;
+/// ```
+///
+/// # Options
+///
+/// - `name = "<ident>"` - Name of the generated function (default `"run"`)
+/// - `yield_every = <n>` - Number of steps between yields, counting the operations of
+///   every loop iteration (default 10000)
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched
+/// brackets. Moving the pointer out of bounds panics at runtime, and I/O errors are
+/// returned.
+#[proc_macro]
+pub fn brainfuck_async_fn(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_async_fn", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_async_fn(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_async_fn(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let name = function_name(options.string("name")?)?;
+    let yield_every = match options.usize("yield_every")? {
+        Some(0) => {
+            return Err(syn::Error::new(
+                code.span(),
+                "option `yield_every` expects a positive number",
+            ))
+        }
+        Some(steps) => steps,
+        None => 10_000,
+    };
+    options.finish()?;
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let function: proc_macro2::TokenStream =
+                transpile::to_rust_async(&name.to_string(), &optimize::optimize(ops), yield_every)
+                    .parse()
+                    .expect("transpiled code is valid Rust");
+            let tracking = code.tracking();
+            Ok(quote! {
+                #function
+                #tracking
+            })
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            Ok(quote! {
+                compile_error!(#error_msg);
+            })
+        }
+    }
+}
+
+/// The name of a generated function from the `name` option, `run` by default
+fn function_name(name: Option<LitStr>) -> syn::Result<syn::Ident> {
+    match name {
+        Some(name) => name
+            .parse::<syn::Ident>()
+            .map_err(|_| syn::Error::new(name.span(), "option `name` expects an identifier")),
+        None => Ok(syn::Ident::new("run", proc_macro2::Span::call_site())),
+    }
+}
+
+/// Execute Brainfuck code at compile time and produce a trace of its execution as a
+/// `&'static str`.
+///
+/// Every executed instruction becomes one line showing the step number, the instruction
+/// and its position, the pointer, the value of the cell under the pointer, and the output
+/// produced so far. Comments are not listed. If the program fails at runtime (for example
+/// by exceeding the step limit), the trace ends with the error instead of failing
+/// compilation, so the trace can be used to debug the failure.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_trace;
+///
+/// let trace = brainfuck_trace!("++[-].", steps = 3);
+/// assert_eq!(
+///     trace,
+///     " step    ip  op    ptr  cell  output
+///     1     0  +      0     1  \"\"
+///     2     1  +      0     2  \"\"
+///     3     2  [      0     2  \"\"
+/// ... 5 more steps not shown
+/// "
+/// );
+/// ```
+///
+/// # Options
+///
+/// - `steps = <n>` - Number of steps to show (default 100)
+/// - `input = "<text>"` - Input read by `,`; end of input stores 0
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+/// - `tape = true` - Draw the tape after every step instead, as a row of boxed cells with
+///   a caret under the pointer (see below)
+/// - `json = true` - Produce the steps as JSON frames instead, for web-based visualizers
+///   (see `brainfuck_core::trace::Frames`): the step, the instruction and its position,
+///   the pointer, the cell changed and the output appended, followed by the error that
+///   ended the run, if any
+/// - `window = <n>` - With `tape`, the number of cells drawn per step (default 8)
+/// - `every = <n>` - With `tape` or `json`, keep only every n-th of the steps (default 1)
+/// - `file = "<path>"` - Also write the trace to a file, relative to `CARGO_MANIFEST_DIR`
+///
+/// With `tape = true`, the trace shows how the program moves over the tape:
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_trace;
+///
+/// let trace = brainfuck_trace!("+>++", tape = true, window = 3, steps = 2);
+/// assert!(trace.starts_with(
+///     "step 1: + at 0
+///     0     1     2
+/// ┌─────┬─────┬─────┐
+/// │   1 │   0 │   0 │
+/// └─────┴─────┴─────┘
+///    ^
+/// "
+/// ));
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched
+/// brackets, if `tape` and `json` are combined, if `window` is given without `tape = true`
+/// or `every` without `tape` or `json`, or if the file cannot be written.
+#[proc_macro]
+pub fn brainfuck_trace(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_trace", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+    TokenStream::from(expand_trace(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_trace(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let steps = options.usize("steps")?.unwrap_or(100);
+    let input = options.string("input")?;
+    let tape = options.flag("tape")?;
+    let json = options.flag("json")?;
+    let window = options.usize("window")?;
+    let every = options.usize("every")?;
+    let file = options.string("file")?;
+    options.finish()?;
+    if tape && json {
+        return Err(syn::Error::new(
+            code.span(),
+            "option `tape` cannot be combined with `json`",
+        ));
+    }
+    if !tape && window.is_some() {
+        return Err(syn::Error::new(
+            code.span(),
+            "option `window` requires `tape = true`",
+        ));
+    }
+    if !tape && !json && every.is_some() {
+        return Err(syn::Error::new(
+            code.span(),
+            "option `every` requires `tape = true` or `json = true`",
+        ));
+    }
+
+    let source = code.value();
+    if let Err(e) = Interpreter::find_matching_brackets(&source) {
+        let error_msg = format!("Brainfuck syntax error: {}", e);
+        return Ok(quote! {
+            compile_error!(#error_msg)
+        });
+    }
+
+    let mut interpreter = match &input {
+        Some(input) => Interpreter::with_input(input.value().as_bytes()),
+        None => Interpreter::new(),
+    };
+    let trace = if tape {
+        let mut trace = TapeTrace::new(steps)
+            .window(window.unwrap_or(8))
+            .every(every.unwrap_or(1));
+        let error = interpreter.execute_with_hook(&source, &mut trace).err();
+        let error = error.map(|e| e.to_string());
+        trace.render(interpreter.output(), error.as_deref())
+    } else if json {
+        let mut frames = Frames::new(steps).every(every.unwrap_or(1));
+        let error = interpreter.execute_with_hook(&source, &mut frames).err();
+        frames.to_json(error.map(|e| e.to_string()).as_deref())
+    } else {
+        let mut trace = Trace::new(steps);
+        let error = interpreter.execute_with_hook(&source, &mut trace).err();
+        let error = error.map(|e| e.to_string());
+        trace.render(interpreter.output(), error.as_deref())
+    };
+    if let Some(file) = file {
+        let path = files::resolve(&file.value());
+        std::fs::write(&path, &trace).map_err(|e| {
+            syn::Error::new(
+                file.span(),
+                format!("couldn't write trace to '{}': {}", path.display(), e),
+            )
+        })?;
+    }
+    Ok(code.track(quote! {
+        #trace
+    }))
+}
+
+/// Run Brainfuck code at compile time and produce a Markdown report of the run as a
+/// `&'static str`, optionally also written to a file.
+///
+/// The report has the formatted source, statistics (characters, instructions, steps,
+/// cells used and output bytes), the trace table of `brainfuck_trace!` truncated to the
+/// first steps, and the output, ready to paste into course notes or blog posts. Like
+/// the trace, a runtime error ends the report instead of failing compilation.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_report;
+///
+/// let report = brainfuck_report!("+++++[>+++++++++++++<-]>.", title = "Printing A");
+/// assert!(report.starts_with("# Printing A\n\n## Source\n"));
+/// assert!(report.contains("| Steps | 93 |\n"));
+/// assert!(report.ends_with("## Output\n\n```text\nA\n```\n"));
+/// ```
+///
+/// # Options
+///
+/// - `path = "<path>"` - Also write the report to a file, relative to `CARGO_MANIFEST_DIR`
+/// - `title = "<text>"` - The title of the report (default `Brainfuck program`)
+/// - `steps = <n>` - Number of steps shown in the trace (default 20)
+/// - `input = "<text>"` - Input read by `,`; end of input stores 0
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched
+/// brackets, or if the file cannot be written.
+#[proc_macro]
+pub fn brainfuck_report(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as args::MacroArgs);
+    TokenStream::from(expand_report(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_report(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let path = options.string("path")?;
+    let title = options.string("title")?;
+    let steps = options.usize("steps")?.unwrap_or(20);
+    let input = options.string("input")?;
+    options.finish()?;
+
+    let source = code.value();
+    if let Err(e) = Interpreter::find_matching_brackets(&source) {
+        let error_msg = format!("Brainfuck syntax error: {}", e);
+        return Ok(quote! {
+            compile_error!(#error_msg)
+        });
+    }
+
+    let title = title.map_or_else(|| "Brainfuck program".to_string(), |title| title.value());
+    let input = input.map(|input| input.value());
+    let report = report::render(&title, &source, input.as_ref().map(String::as_bytes), steps);
+    if let Some(path) = path {
+        let full = files::resolve(&path.value());
+        std::fs::write(&full, &report).map_err(|e| {
+            syn::Error::new(
+                path.span(),
+                format!("couldn't write report to '{}': {}", full.display(), e),
+            )
+        })?;
+    }
+    Ok(code.track(quote! {
+        #report
+    }))
+}
+
+/// Produce a human-readable listing of the optimized intermediate representation of
+/// Brainfuck code as a `&'static str`.
+///
+/// This is the program as the code generation backends see it, after these passes:
+///
+/// - Runs of `+`/`-` and `<`/`>` are folded (`add n`, `move n`), dropping runs that cancel out
+/// - Clear loops (`[-]`, `[+]`) become `clear`
+/// - Multiplication loops such as `[->+++>--<<]` become `mul [+1] += 3, [+2] += -2`, which
+///   adds the current cell times each factor to the cell at each offset and then clears
+///   the current cell
+///
+/// Remaining loops are listed as `loop` ... `end` with their bodies indented.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_ir;
+///
+/// let ir = brainfuck_ir!("+++++[>+++++++++++++<-]>. [-]");
+/// assert_eq!(ir, "add 5\nmul [+1] += 13\nmove +1\noutput\nclear\n");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_ir(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_ir", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let dump = ir::dump(&optimize::optimize(ops));
+            TokenStream::from(code.track(quote! {
+                #dump
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Produce the control-flow graph of Brainfuck code in Graphviz DOT format as a
+/// `&'static str`.
+///
+/// Straight-line runs of commands become boxes labelled with the range of character
+/// positions they cover. Every bracket becomes a diamond with a `zero` and a `nonzero`
+/// edge, so the loop structure of the program is visible at a glance. Render the result
+/// with e.g. `dot -Tsvg`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_cfg;
+///
+/// let dot = brainfuck_cfg!("++[-].");
+/// assert!(dot.starts_with("digraph brainfuck {"));
+/// assert!(dot.contains("n1 -> n4 [label=\"zero\"];"));
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_cfg(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_cfg", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+    let source = code.value();
+
+    match Interpreter::find_matching_brackets(&source) {
+        Ok(_) => {
+            let dot = cfg::to_dot(&source);
+            TokenStream::from(code.track(quote! {
+                #dot
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Decode a Brainloller image at compile time, execute the program it contains and
+/// produce the output as a `&'static str`.
+///
+/// [Brainloller](https://esolangs.org/wiki/Brainloller) stores Brainfuck commands as the
+/// pixels of a PNG image. The instruction pointer starts at the top-left pixel moving
+/// right, and the program ends when it leaves the image:
+///
+/// - `#FF0000` `>`, `#800000` `<`
+/// - `#00FF00` `+`, `#008000` `-`
+/// - `#0000FF` `.`, `#000080` `,`
+/// - `#FFFF00` `[`, `#808000` `]`
+/// - `#00FFFF` rotates the direction of travel clockwise, `#008080` counterclockwise
+/// - any other color is ignored
+///
+/// The path is resolved relative to the `CARGO_MANIFEST_DIR` of the crate invoking the
+/// macro, and the image is tracked like with `brainfuck_file!`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_image;
+///
+/// let hello = brainfuck_image!("art/hello.png");
+/// assert_eq!(hello, "Hello World!\n");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the file cannot be read or is not a
+/// valid PNG, and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn brainfuck_image(input: TokenStream) -> TokenStream {
+    let path_lit = parse_macro_input!(input as LitStr);
+
+    let path = files::resolve(&path_lit.value());
+    let code = std::fs::read(&path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))
+        .and_then(|bytes| brainloller::decode_png(&bytes))
+        .map(|image| brainloller::to_brainfuck(&image));
+    let code = match code {
+        Ok(code) => code,
+        Err(e) => {
+            let error_msg = format!("Brainloller error: {}", e);
+            return TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            });
+        }
+    };
+
+    let tracked = files::track(&path);
+    let output = expand_execution(&code);
+    let expanded = quote! {
+        {
+            #tracked
+            #output
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+/// Validate Brainfuck code at compile time and defer running it to its first use at
+/// runtime, producing a `std::sync::LazyLock<String>`.
+///
+/// This is the escape hatch for programs too slow to run while compiling: the code is
+/// still checked for unmatched brackets, but executed by `brainfuck_core::interpret::run`
+/// the first time the value is dereferenced. The invoking crate needs `brainfuck-core` as
+/// a dependency. Output bytes become characters like with `brainfuck!`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use brainfuck_macro::brainfuck_lazy;
+/// use std::sync::LazyLock;
+///
+/// static MANDELBROT: LazyLock<String> = brainfuck_lazy!(include_str!("mandelbrot.b"));
+///
+/// println!("{}", *MANDELBROT);
+/// ```
+///
+/// # Options
+///
+/// - `input = "<text>"` - Input for `,` (default empty; `,` stores 0 once it is exhausted)
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched
+/// brackets. Errors while running (leaving the tape or exceeding the step limit) panic on
+/// first use.
+#[proc_macro]
+pub fn brainfuck_lazy(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_lazy", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_lazy(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_lazy(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let input = options
+        .string("input")?
+        .map(|input| input.value())
+        .unwrap_or_default();
+    options.finish()?;
+
+    if let Err(e) = Interpreter::find_matching_brackets(&code.value()) {
+        let error_msg = format!("Brainfuck syntax error: {}", e);
+        return Ok(quote! {
+            compile_error!(#error_msg)
+        });
+    }
+
+    let literal = code.literal();
+    let input = syn::LitByteStr::new(input.as_bytes(), proc_macro2::Span::call_site());
+    Ok(code.track(quote! {
+        ::std::sync::LazyLock::new(|| {
+            let output = ::brainfuck_core::interpret::run(#literal, #input)
+                .unwrap_or_else(|e| ::std::panic!("Brainfuck execution error: {}", e));
+            output
+                .into_iter()
+                .map(::std::primitive::char::from)
+                .collect::<::std::string::String>()
+        })
+    }))
+}
+
+/// Run Brainfuck code and expand to its output as a string literal, or to a compile error
+fn expand_execution(code: &str) -> proc_macro2::TokenStream {
+    expand_interpreter(Interpreter::new(), code)
+}
+
+/// Execute code with a configured interpreter and expand to its output, or to a compile
+/// error
+fn expand_interpreter(mut interpreter: Interpreter, code: &str) -> proc_macro2::TokenStream {
+    expand_result(interpreter.execute(code))
+}
+
+/// Expand the result of an execution to its output, or to a compile error
+fn expand_result(
+    result: Result<String, brainfuck_core::machine::Error>,
+) -> proc_macro2::TokenStream {
+    match result {
+        Ok(output) => quote! {
+            #output
+        },
+        Err(e) => {
+            let error_msg = format!("Brainfuck execution error: {}", e);
+            quote! {
+                compile_error!(#error_msg)
+            }
+        }
+    }
+}
+
+/// Translate Brainfuck code into Rust source and produce it as a `&'static str`.
+///
+/// The generated source defines `pub fn run(input: &[u8]) -> Vec<u8>`, which executes
+/// the program at runtime. The program is optimized first (see `brainfuck_ir!`), so runs
+/// of `+`/`-` and `<`/`>` become single statements and clear and multiplication loops
+/// become plain assignments.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_transpile;
+///
+/// let source = brainfuck_transpile!("+++[>++<-]>[.>]");
+/// assert!(source.starts_with("#[allow(unused_mut)]\npub fn run(_input: &[u8]) -> Vec<u8> {"));
+/// assert!(source.contains("while tape[ptr] != 0 {"));
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+/// - `no_std = true` - Define `pub fn run(tape: &mut [u8], input: &[u8], output: impl
+///   FnMut(u8))` instead, which needs neither `std` nor an allocator: the program runs on
+///   the tape passed in, and every output byte is passed to `output`
+/// - `wasm_bindgen = true` - Export the function to JavaScript with `#[wasm_bindgen]`,
+///   for web playgrounds compiled to `wasm32-unknown-unknown`
+/// - `unchecked = true` - Index the tape with `get_unchecked` in `unsafe` blocks when
+///   `brainfuck_core::ir::pointer_range` proves the pointer stays on it, for speed.
+///   Cannot be combined with `no_std` or `wasm_bindgen`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+/// Unlike `brainfuck!`, the input operation (`,`) is supported: it reads from `input`,
+/// storing 0 once the input is exhausted.
+#[proc_macro]
+pub fn brainfuck_transpile(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_transpile", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_transpile(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_transpile(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let no_std = options.flag("no_std")?;
+    let wasm_bindgen = options.flag("wasm_bindgen")?;
+    let unchecked = options.flag("unchecked")?;
+    if no_std && wasm_bindgen {
+        return Err(syn::Error::new(
+            code.span(),
+            "option `wasm_bindgen` cannot be combined with `no_std`",
+        ));
+    }
+    if unchecked && (no_std || wasm_bindgen) {
+        let other = if no_std { "no_std" } else { "wasm_bindgen" };
+        return Err(syn::Error::new(
+            code.span(),
+            format!("option `unchecked` cannot be combined with `{}`", other),
+        ));
+    }
+    options.finish()?;
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let ops = optimize::optimize(ops);
+            let source = if no_std {
+                transpile::to_rust_no_std("run", &ops)
+            } else if wasm_bindgen {
+                transpile::to_rust_wasm_bindgen("run", &ops)
+            } else if unchecked {
+                transpile::to_rust_unchecked("run", &ops)
+            } else {
+                transpile::to_rust("run", &ops)
+            };
+            Ok(code.track(quote! {
+                #source
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            Ok(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Translate Brainfuck code into C99 source and produce it as a `&'static str`.
+///
+/// The generated source defines `void run(FILE *input, FILE *output)`, sharing the
+/// optimizer with `brainfuck_transpile!`. Pointer moves are not bounds checked in C, so a
+/// program moving off the tape is undefined behavior. At end of input `,` stores 0.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_to_c;
+///
+/// let source = brainfuck_to_c!(",[.,]", name = "echo", main = true);
+/// assert!(source.contains("void echo(FILE *input, FILE *output) {"));
+/// assert!(source.contains("    echo(stdin, stdout);"));
+/// ```
+///
+/// # Options
+///
+/// - `name = "<ident>"` - Name of the generated function (default `"run"`)
+/// - `main = true` - Also emit an `int main(void)` running the program on stdin and stdout
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets
+/// or the name is not a valid C identifier.
+#[proc_macro]
+pub fn brainfuck_to_c(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_to_c", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_to_c(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_to_c(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs {
+        mut code,
+        mut options,
+    } = args;
+    options.decode_dialect(&mut code)?;
+    let name = match options.string("name")? {
+        Some(name) if !transpile_c::is_identifier(&name.value()) => {
+            return Err(syn::Error::new(
+                name.span(),
+                "option `name` expects a valid C identifier",
+            ))
+        }
+        Some(name) => name.value(),
+        None => "run".to_string(),
+    };
+    let main = options.flag("main")?;
+    options.finish()?;
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let source = transpile_c::to_c(&name, &optimize::optimize(ops), main);
+            Ok(code.track(quote! {
+                #source
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            Ok(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Translate Brainfuck code into a WebAssembly text (WAT) module and produce it as a
+/// `&'static str`.
+///
+/// The module's exported `memory` is the tape and its exported `run` function executes
+/// the program. Output goes through the imported `env.putc(i32)`, and programs reading
+/// input also import `env.getc() -> i32`, which should return 0 at end of input. The
+/// program is optimized like for `brainfuck_transpile!`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_to_wat;
+///
+/// let wat = brainfuck_to_wat!("+++++[>+++++++++++++<-]>.");
+/// assert!(wat.starts_with("(module\n  (import \"env\" \"putc\" (func $putc (param i32)))"));
+/// assert!(wat.contains("(func (export \"run\")"));
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_to_wat(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_to_wat", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+
+    match ir::parse(&code.value()) {
+        Ok(ops) => {
+            let wat = transpile_wat::to_wat(&optimize::optimize(ops));
+            TokenStream::from(code.track(quote! {
+                #wat
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            TokenStream::from(quote! {
+                compile_error!(#error_msg)
+            })
+        }
+    }
+}
+
+/// Encode Brainfuck code in another esolang that substitutes one token per command,
+/// producing the encoded program as a `&'static str`.
+///
+/// Comments are dropped. The supported dialects are `ook` (Ook!), `blub`, `alphuck`,
+/// `pikalang`, `spoon`, `morsefuck` and `headsecks`, which is encoded with the letters
+/// `h` to `o`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_encode;
+///
+/// assert_eq!(brainfuck_encode!("+[-]>.", dialect = "alphuck"), "episaj");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - The dialect to encode in (required)
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the dialect is unknown or the Brainfuck
+/// code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_encode(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_encode", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_encode(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_encode(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs { code, mut options } = args;
+    let dialect = match options.string("dialect")? {
+        Some(name) => frontend::find(&name)?,
+        None => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "missing option `dialect`",
+            ))
+        }
+    };
+    options.finish()?;
+
+    Ok(expand_encoding(&code, dialect))
+}
+
+/// Encode Brainfuck code as Ook!, producing the encoded program as a `&'static str`.
+///
+/// Shorthand for `brainfuck_encode!(code, dialect = "ook")`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_to_ook;
+///
+/// assert_eq!(brainfuck_to_ook!("+."), "Ook. Ook. Ook! Ook.");
+/// ```
+///
+/// # Options
+///
+/// - `dialect = "<name>"` - Decode the code from a dialect first, as in `brainfuck!`
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the Brainfuck code has unmatched brackets.
+#[proc_macro]
+pub fn brainfuck_to_ook(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("brainfuck_to_ook", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let code = match parse_macro_input!(input as args::MacroArgs).into_code() {
+        Ok(code) => code,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
+    let dialect = dialect::find("ook").expect("Ook! is a built-in dialect");
+
+    TokenStream::from(expand_encoding(&code, dialect))
+}
+
+/// Execute an Ook! program at compile time and produce its output as a `&'static str`.
+///
+/// [Ook!](https://esolangs.org/wiki/Ook!) writes every Brainfuck command as a pair of the
+/// words `Ook.`, `Ook?` and `Ook!`, separated by any whitespace. The program is decoded to
+/// Brainfuck and executed like with `brainfuck!`. Syntax errors point at the offending
+/// Ook! token.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::ook;
+///
+/// // +++[>++<-]>.
+/// let six = ook!("
+///     Ook. Ook. Ook. Ook. Ook. Ook. Ook! Ook? Ook. Ook? Ook. Ook. Ook. Ook.
+///     Ook? Ook. Ook! Ook! Ook? Ook! Ook. Ook? Ook! Ook.
+/// ");
+/// assert_eq!(six, "\u{6}");
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::ook;
+///
+/// // error: Ook! syntax error: Unmatched `Ook! Ook?` at position 10
+/// ook!("Ook. Ook. Ook! Ook?");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if a word is not part of an Ook! token,
+/// if the brackets are unmatched, and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn ook(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+    let dialect = dialect::find("ook").expect("Ook! is a built-in dialect");
+
+    TokenStream::from(expand_dialect(&code, dialect))
+}
+
+/// Execute a Blub program at compile time and produce its output as a `&'static str`.
+///
+/// [Blub](https://esolangs.org/wiki/Blub) is Ook! with every `Ook` replaced by `Blub`.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::blub;
+///
+/// // ++.
+/// let two = blub!("Blub. Blub. Blub. Blub. Blub! Blub.");
+/// assert_eq!(two, "\u{2}");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if a word is not part of a Blub token,
+/// if the brackets are unmatched, and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn blub(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+    let dialect = dialect::find("blub").expect("Blub is a built-in dialect");
+
+    TokenStream::from(expand_dialect(&code, dialect))
+}
+
+/// Arguments of `brainfuck_tbs!`: code followed by `map = { "command" = "token", ... }`
+struct TbsArgs {
+    code: source::Code,
+    dialect: dialect::Substitution,
+}
+
+impl syn::parse::Parse for TbsArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let code = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let name: syn::Ident = input.parse()?;
+        if name != "map" {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("unknown option `{}`", name),
+            ));
+        }
+        input.parse::<syn::Token![=]>()?;
+        let content;
+        let braces = syn::braced!(content in input);
+        input.parse::<Option<syn::Token![,]>>()?;
+
+        let mut tokens: [Option<LitStr>; 8] = Default::default();
+        while !content.is_empty() {
+            let command: LitStr = content.parse()?;
+            content.parse::<syn::Token![=]>()?;
+            let token: LitStr = content.parse()?;
+            let index = dialect::COMMANDS
+                .iter()
+                .position(|c| command.value() == c.to_string())
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        command.span(),
+                        format!("`{}` is not a Brainfuck command", command.value()),
+                    )
+                })?;
+            if tokens[index].is_some() {
+                return Err(syn::Error::new(
+                    command.span(),
+                    format!("duplicate token for `{}`", command.value()),
+                ));
+            }
+            tokens[index] = Some(token);
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<syn::Token![,]>()?;
+        }
+
+        if let Some(index) = tokens.iter().position(Option::is_none) {
+            return Err(syn::Error::new(
+                braces.span.join(),
+                format!("missing token for `{}`", dialect::COMMANDS[index]),
+            ));
+        }
+        let tokens = tokens.map(|token| token.expect("all tokens are present"));
+        let dialect = dialect::Substitution::new(tokens.clone().map(|token| token.value()))
+            .map_err(|(index, message)| syn::Error::new(tokens[index].span(), message))?;
+        Ok(Self { code, dialect })
+    }
+}
+
+/// Execute a program written in a user-defined substitution dialect at compile time and
+/// produce its output as a `&'static str`.
+///
+/// The `map` gives the token for each of the eight Brainfuck commands, so dialects like
+/// [TrivialBrainfuckSubstitution](https://esolangs.org/wiki/TrivialBrainfuckSubstitution)
+/// derivatives can be run without built-in support. Tokens may be several characters
+/// long, but none may be a prefix of another. Characters that do not start a token are
+/// comments.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::brainfuck_tbs;
+///
+/// let six = brainfuck_tbs!(
+///     "inc inc inc (r inc inc l dec) r out",
+///     map = {
+///         ">" = "r", "<" = "l", "+" = "inc", "-" = "dec",
+///         "." = "out", "," = "get", "[" = "(", "]" = ")",
+///     }
+/// );
+/// assert_eq!(six, "\u{6}");
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::brainfuck_tbs;
+///
+/// // error: the token "a" for `+` is a prefix of the token "ab" for `-`
+/// brainfuck_tbs!("a", map = {
+///     ">" = ">", "<" = "<", "+" = "a", "-" = "ab",
+///     "." = ".", "," = ",", "[" = "[", "]" = "]",
+/// });
+/// ```
+///
+/// # Options
+///
+/// - `map = { "<command>" = "<token>", ... }` - The token for every Brainfuck command
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if the map does not give exactly one
+/// non-empty token per command, if a token is a prefix of another, if the brackets are
+/// unmatched, and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn brainfuck_tbs(input: TokenStream) -> TokenStream {
+    let TbsArgs { code, dialect } = parse_macro_input!(input as TbsArgs);
+
+    let decoded = dialect.decode(&code.value());
+    TokenStream::from(code.track(expand_decoded_execution(
+        "Substitution",
+        Ok(decoded),
+        |command, _| dialect.token(command).to_string(),
+    )))
+}
+
+/// Execute a pbrain program at compile time and produce its output as a `&'static str`.
+///
+/// [pbrain](https://esolangs.org/wiki/Pbrain) extends Brainfuck with procedures. The
+/// procedures are numbered by cell values, so up to 256 can be defined; defining one
+/// again replaces it.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::pbrain;
+///
+/// // Procedure 0 doubles cell 1 into cell 2 and prints it
+/// let output = pbrain!("(>[->++<]>.<<) >+++< :");
+/// assert_eq!(output, "\u{6}");
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::pbrain;
+///
+/// // error: Brainfuck execution error: Call of undefined procedure 1 at position 1
+/// pbrain!("+:");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error under the same conditions as `brainfuck!`,
+/// and if:
+/// - The parentheses are unmatched
+/// - An undefined procedure is called
+/// - Procedure calls are nested more than 1000 levels deep
+///
+/// # Supported Operations
+///
+/// Besides the Brainfuck commands:
+///
+/// - `(` - Start the definition of the procedure numbered by the current cell
+/// - `)` - End a procedure definition (return from the procedure when called)
+/// - `:` - Call the procedure numbered by the current cell
+#[proc_macro]
+pub fn pbrain(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_procedures();
+
+    TokenStream::from(code.track(expand_interpreter(interpreter, &code.value())))
+}
+
+/// Execute a Boolfuck program at compile time and produce its output as a `&'static str`.
+///
+/// [Boolfuck](https://esolangs.org/wiki/Boolfuck) runs on a tape of bits. Output bits are
+/// assembled into bytes starting with the least significant bit; a final incomplete byte
+/// is padded with zeros.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::boolfuck;
+///
+/// // 'A' is 0b0100_0001
+/// let a = boolfuck!("+;+; ;;;; +;+;");
+/// assert_eq!(a, "A");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error under the same conditions as `brainfuck!`.
+/// The tape holds 30,000 bits.
+///
+/// # Supported Operations
+///
+/// - `>` / `<` - Move pointer right / left
+/// - `+` - Flip the current bit
+/// - `;` - Output the current bit
+/// - `[` - Loop start (jump to matching `]` if the bit is 0)
+/// - `]` - Loop end (jump to matching `[` if the bit is 1)
+///
+/// All other characters are treated as comments and ignored.
+#[proc_macro]
+pub fn boolfuck(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+
+    let expanded = match boolfuck::execute(&code.value()) {
+        Ok(output) => quote! {
+            #output
+        },
+        Err(e) => {
+            let error_msg = format!("Boolfuck execution error: {}", e);
+            quote! {
+                compile_error!(#error_msg)
+            }
+        }
+    };
+    TokenStream::from(code.track(expanded))
+}
+
+/// Execute a Spoon program at compile time and produce its output as a `&'static str`.
+///
+/// [Spoon](https://esolangs.org/wiki/Spoon) writes every Brainfuck command as a string of
+/// binary digits, chosen so no token is a prefix of another. Whitespace is ignored, even
+/// inside a token. Besides the eight commands, `00101111` (EXIT) ends the program and
+/// `00101110` (DUMP) is accepted but does nothing, since memory cannot be printed while
+/// compiling.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::spoon;
+///
+/// // +++[>++<-]>. EXIT
+/// let six = spoon!("111 00100 010 11 011 000 0011 010 001010 00101111");
+/// assert_eq!(six, "\u{6}");
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::spoon;
+///
+/// // error: Spoon syntax error: Unexpected character `2` at position 3
+/// spoon!("1 02");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if a character other than whitespace is
+/// not part of a token, if the source ends inside a token, if the brackets are unmatched,
+/// and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn spoon(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+    let dialect = dialect::find("spoon").expect("Spoon is a built-in dialect");
+
+    TokenStream::from(expand_dialect(&code, dialect))
+}
+
+/// Execute an Alphuck program at compile time and produce its output as a `&'static str`.
+///
+/// [Alphuck](https://esolangs.org/wiki/Alphuck) writes the commands `>`, `<`, `+`, `-`,
+/// `.`, `,`, `[` and `]` as the letters `a`, `c`, `e`, `i`, `j`, `o`, `p` and `s`. Since
+/// the commands are ordinary letters, comments written in words would silently turn into
+/// commands. By default, any other letter is therefore an error, while characters that
+/// are not letters are comments.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::alphuck;
+///
+/// // +++[>++<-]>.
+/// let six = alphuck!("eee p aee ci s aj // 3 * 2");
+/// assert_eq!(six, "\u{6}");
+/// ```
+///
+/// ```rust,compile_fail
+/// use brainfuck_macro::alphuck;
+///
+/// // error: Alphuck syntax error: Unexpected character `t` at position 3
+/// alphuck!("ee three");
+/// ```
+///
+/// # Options
+///
+/// - `strict = false` - Treat every character other than the eight command letters as a
+///   comment, as most Alphuck interpreters do
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if strict and a letter is not a command,
+/// if the brackets are unmatched, and otherwise under the same conditions as `brainfuck!`.
+#[proc_macro]
+pub fn alphuck(input: TokenStream) -> TokenStream {
+    if let Some(expanded) = define::redirect("alphuck", input.clone().into()) {
+        return TokenStream::from(expanded);
+    }
+    let args = parse_macro_input!(input as args::MacroArgs);
+
+    TokenStream::from(expand_alphuck(args).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn expand_alphuck(args: args::MacroArgs) -> syn::Result<proc_macro2::TokenStream> {
+    let args::MacroArgs { code, mut options } = args;
+    let strict = options.flag_or("strict", true)?;
+    options.finish()?;
+
+    let dialect = dialect::find("alphuck").expect("Alphuck is a built-in dialect");
+    let decoded = if strict {
+        dialect.decode(&code.value())
+    } else {
+        Ok(dialect.decode_lenient(&code.value()))
+    };
+    Ok(code.track(expand_decoded_execution(
+        dialect.title,
+        decoded,
+        |command, _| dialect.token(command).to_string(),
+    )))
+}
+
+/// Execute a COW program at compile time and produce its output as a `&'static str`.
+///
+/// [COW](https://esolangs.org/wiki/COW) programs are made of twelve variations of "moo";
+/// all other text is ignored. Cells are bytes, like in Brainfuck.
+///
+/// # Example
+///
+/// ```rust
+/// use brainfuck_macro::cow;
+///
+/// // Multiply 3 by 2 and print the result as a number
+/// let six = cow!("MoO MoO MoO MOO moO MoO MoO mOo MOo moo moO OOM");
+/// assert_eq!(six, "6\n");
+/// ```
+///
+/// # Errors
+///
+/// The macro will produce a compile-time error if a `MOO` or `moo` has no match when it is
+/// executed, if the program reads input, and otherwise under the same conditions as
+/// `brainfuck!`.
+///
+/// # Supported Operations
+///
+/// - `moo` - Loop end: continue at the matching `MOO`, searching backwards and skipping
+///   the instruction right before
+/// - `mOo` / `moO` - Move pointer left / right
+/// - `mOO` - Execute the current cell as an instruction number; 3 or more than 11 exits
+/// - `Moo` - Output the current cell as a character (reads input if the cell is 0)
+/// - `MOo` / `MoO` - Decrement / increment the current cell
+/// - `MOO` - Loop start: if the current cell is 0, continue after the matching `moo`,
+///   searching forwards and skipping the instruction right after
+/// - `OOO` - Set the current cell to 0
+/// - `MMM` - Copy the current cell into the empty register, or move the register into the
+///   current cell
+/// - `OOM` - Output the current cell as a decimal number followed by a newline
+/// - `oom` - Read a number into the current cell
+#[proc_macro]
+pub fn cow(input: TokenStream) -> TokenStream {
+    let code = parse_macro_input!(input as source::Code);
+
+    let expanded = match cow::execute(&code.value()) {
+        Ok(output) => quote! {
+            #output
+        },
+        Err(e) => {
+            let error_msg = format!("COW execution error: {}", e);
+            quote! {
+                compile_error!(#error_msg)
+            }
+        }
+    };
+    TokenStream::from(code.track(expanded))
+}
+
+/// Decode a program with a frontend, run it and expand to its output
+fn expand_dialect(
+    code: &source::Code,
+    frontend: &dyn frontend::DialectFrontend,
+) -> proc_macro2::TokenStream {
+    let source = code.value();
+    code.track(expand_decoded_execution(
+        frontend.title(),
+        frontend.decode(&source),
+        |command, position| frontend.token_at(&source, command, position),
+    ))
+}
+
+/// Run a program decoded from a dialect and expand to its output, or to a compile error
+/// with positions in terms of the dialect's source. `token` gives the dialect's token for
+/// a bracket at a position in the source.
+fn expand_decoded_execution(
+    title: &str,
+    decoded: Result<frontend::Decoded, frontend::DecodeError>,
+    token: impl Fn(char, usize) -> String,
+) -> proc_macro2::TokenStream {
+    let error_msg = match decoded {
+        Ok(decoded) => match Interpreter::find_matching_brackets(&decoded.code) {
+            Ok(_) => {
+                // Decoded code only contains Brainfuck commands and `@`
+                let mut interpreter = Interpreter::new();
+                interpreter.enable_extensions(Extensions {
+                    end: true,
+                    ..Default::default()
+                });
+                return expand_interpreter(interpreter, &decoded.code);
+            }
+            Err(
+                BrainfuckError::UnmatchedOpenBracket(index)
+                | BrainfuckError::UnmatchedCloseBracket(index),
+            ) => {
+                let command = decoded.code.as_bytes()[index] as char;
+                format!(
+                    "{} syntax error: Unmatched `{}` at position {}",
+                    title,
+                    token(command, decoded.positions[index]),
+                    decoded.positions[index]
+                )
+            }
+            Err(e) => format!("{} syntax error: {}", title, e),
+        },
+        Err(e) => format!("{} syntax error: {}", title, e),
+    };
+    quote! {
+        compile_error!(#error_msg)
+    }
+}
+
+/// Encode code in a dialect and expand to the result, or to a compile error
+fn expand_encoding(
+    code: &source::Code,
+    dialect: &dyn frontend::DialectFrontend,
+) -> proc_macro2::TokenStream {
+    let source = code.value();
+    match Interpreter::find_matching_brackets(&source) {
+        Ok(_) => {
+            let encoded = dialect.encode(&source);
+            code.track(quote! {
+                #encoded
+            })
+        }
+        Err(e) => {
+            let error_msg = format!("Brainfuck syntax error: {}", e);
+            quote! {
+                compile_error!(#error_msg)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_debug() {
+        let args = syn::parse2(quote!(
+            "Ook. Ook. Ook! Ook.",
+            dialect = "ook",
+            expand_debug = true
+        ));
+        let expanded = expand_brainfuck(args.unwrap()).unwrap().to_string();
+        assert!(expanded.contains("const BRAINFUCK_SOURCE : & str = \"Ook. Ook. Ook! Ook.\""));
+        assert!(expanded.contains("\" - Dialect: ook\""));
+        assert!(expanded.contains("\" - Characters: 19\""));
+        assert!(expanded.contains("const BRAINFUCK_OUTPUT : & str = \"\\u{1}\""));
+        // Dialect code is not formatted as Brainfuck
+        assert!(!expanded.contains("# Program"));
+
+        let args = syn::parse2(quote!("++[>+<-]>.", expand_debug = true));
+        let expanded = expand_brainfuck(args.unwrap()).unwrap().to_string();
+        assert!(expanded.contains("# Program"));
+        // Without the option, the output is a plain literal
+        let args = syn::parse2(quote!("++[>+<-]>."));
+        let expanded = expand_brainfuck(args.unwrap()).unwrap().to_string();
+        assert_eq!(expanded, "\"\\u{2}\"");
+    }
+
+    #[test]
+    fn test_expand_batch() {
+        let args = syn::parse2(quote!(["+.", "++."], batch = true));
+        let expanded = expand_brainfuck(args.unwrap()).unwrap().to_string();
+        assert_eq!(expanded, "[\"\\u{1}\" , \"\\u{2}\"]");
+
+        let args = syn::parse2(quote!("+.", batch = true));
+        let error = expand_brainfuck(args.unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "option `batch` expects an array of programs"
+        );
+        let args = syn::parse2(quote!(["+."], batch = true, with_source = true));
+        assert!(expand_brainfuck(args.unwrap()).is_err());
     }
 
     #[test]
-    fn test_comments() {
-        let code = "This is a comment +++ with text . interspersed"; // Should output ASCII 3
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code).unwrap();
-        assert_eq!(result, "\u{03}");
+    fn test_decoded_program() {
+        let ook = LitStr::new("ook", proc_macro2::Span::call_site());
+        assert_eq!(decoded_program("Ook. Ook. Ook! Ook.", Some(&ook)), "+.");
+        assert_eq!(decoded_program("+. comment", None), "+. comment");
+        // Errors are reported by the execution
+        assert_eq!(decoded_program("Ook?", Some(&ook)), "Ook?");
     }
 
     #[test]
-    fn test_wrapping() {
-        // Test that cells wrap at 256
-        let code = "--------."; // 0 - 8 = 248 (wrapping)
-        let mut interpreter = BrainfuckInterpreter::new();
-        let result = interpreter.execute(code).unwrap();
-        assert_eq!(result, "\u{f8}");
+    fn test_proven_step_limit() {
+        // Proven to stop, but only after about 255^5 steps
+        let args = syn::parse2(quote!("-[>-[>-[>-[>-[-]<-]<-]<-]<-]"));
+        let expanded = expand_brainfuck(args.unwrap()).unwrap().to_string();
+        assert!(expanded.contains("Execution exceeded maximum steps"));
     }
 }
@@ -0,0 +1,92 @@
+//! Static lint passes that flag suspicious but valid Brainfuck code.
+
+/// A single lint finding
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Lint {
+    /// Position (in characters) of the offending instruction
+    pub position: usize,
+    /// Human readable description
+    pub message: &'static str,
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+/// Run all lint passes over the code. Non-command characters are ignored.
+pub(crate) fn check(code: &str) -> Vec<Lint> {
+    let commands: Vec<(usize, char)> = code
+        .chars()
+        .enumerate()
+        .filter(|(_, ch)| "<>+-.,[]".contains(*ch))
+        .collect();
+
+    let mut lints = Vec::new();
+    for (index, &(position, ch)) in commands.iter().enumerate() {
+        let prev = index.checked_sub(1).map(|i| commands[i].1);
+        let next = commands.get(index + 1).map(|&(_, ch)| ch);
+
+        match (prev, ch, next) {
+            (_, '+', Some('-')) | (_, '-', Some('+')) => lints.push(Lint {
+                position,
+                message: "Cancelling '+' and '-' pair",
+            }),
+            (_, '>', Some('<')) | (_, '<', Some('>')) => lints.push(Lint {
+                position,
+                message: "Cancelling '>' and '<' pair",
+            }),
+            (_, '[', Some(']')) => lints.push(Lint {
+                position,
+                message: "Empty loop never terminates once entered",
+            }),
+            (None, '[', _) => lints.push(Lint {
+                position,
+                message: "Loop at program start is never entered",
+            }),
+            (Some(']'), '[', _) => lints.push(Lint {
+                position,
+                message: "Loop directly after another loop is never entered",
+            }),
+            _ => {}
+        }
+    }
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_code() {
+        assert!(check("+++[>++<-]>.").is_empty());
+    }
+
+    #[test]
+    fn test_cancelling_pairs() {
+        let lints = check("++ - ><");
+        assert_eq!(lints.len(), 2);
+        assert_eq!(lints[0].position, 1);
+        assert_eq!(lints[1].position, 5);
+    }
+
+    #[test]
+    fn test_dead_loops() {
+        let lints = check("[.]+[-][.]");
+        let messages: Vec<_> = lints.iter().map(|lint| lint.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Loop at program start is never entered",
+                "Loop directly after another loop is never entered",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_loop() {
+        assert_eq!(check("+[]")[0].position, 1);
+    }
+}
@@ -0,0 +1,126 @@
+//! Single-instruction mutations of `brainfuck_mutants!`.
+
+/// A program differing from the original in one instruction
+pub(crate) struct Mutant {
+    /// The character position of the mutated instruction
+    pub position: usize,
+    /// What the mutation did, for the failure message
+    pub description: String,
+    /// The mutated program
+    pub code: String,
+}
+
+/// Every single-instruction mutation of a program with matched brackets, in order of
+/// position: commands are deleted and `+-<>` also replaced by their opposite, loops are
+/// unwrapped at their `[` (the body runs once, unconditionally) and deleted at their `]`
+pub(crate) fn mutants(code: &str) -> Vec<Mutant> {
+    let chars: Vec<char> = code.chars().collect();
+    let without = |skip: &[usize], replace: Option<(usize, char)>| -> String {
+        chars
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !skip.contains(i))
+            .map(|(i, &c)| match replace {
+                Some((at, with)) if at == i => with,
+                _ => c,
+            })
+            .collect()
+    };
+
+    let mut mutants = Vec::new();
+    let mut opens = Vec::new();
+    for (position, &command) in chars.iter().enumerate() {
+        let mut push = |description: String, code: String| {
+            mutants.push(Mutant {
+                position,
+                description,
+                code,
+            })
+        };
+        match command {
+            '+' | '-' | '<' | '>' | '.' | ',' => {
+                push(
+                    format!("deleting '{}' at {}", command, position),
+                    without(&[position], None),
+                );
+                let opposite = match command {
+                    '+' => '-',
+                    '-' => '+',
+                    '<' => '>',
+                    '>' => '<',
+                    _ => continue,
+                };
+                push(
+                    format!(
+                        "replacing '{}' at {} with '{}'",
+                        command, position, opposite
+                    ),
+                    without(&[], Some((position, opposite))),
+                );
+            }
+            '[' => {
+                opens.push(position);
+                let close = matching_close(&chars, position);
+                push(
+                    format!("unwrapping the loop at {}", position),
+                    without(&[position, close], None),
+                );
+            }
+            ']' => {
+                let open = opens.pop().expect("brackets are matched");
+                let loop_positions: Vec<usize> = (open..=position).collect();
+                push(
+                    format!("deleting the loop at {}..={}", open, position),
+                    without(&loop_positions, None),
+                );
+            }
+            _ => {}
+        }
+    }
+    mutants
+}
+
+/// The position of the `]` matching the `[` at `open`
+fn matching_close(chars: &[char], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    unreachable!("brackets are matched")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutants() {
+        let mutants: Vec<(usize, String)> = mutants("+x[>.]")
+            .into_iter()
+            .map(|mutant| (mutant.position, mutant.code))
+            .collect();
+        let expected = [
+            (0, "x[>.]"),
+            (0, "-x[>.]"),
+            (2, "+x>."),
+            (3, "+x[.]"),
+            (3, "+x[<.]"),
+            (4, "+x[>]"),
+            (5, "+x"),
+        ];
+        let expected: Vec<(usize, String)> = expected
+            .into_iter()
+            .map(|(position, code)| (position, code.to_string()))
+            .collect();
+        assert_eq!(mutants, expected);
+    }
+}
@@ -0,0 +1,93 @@
+//! Markdown reports of `brainfuck_report!`.
+
+use brainfuck_core::machine::Interpreter;
+use brainfuck_core::trace::{CellStats, Trace};
+
+/// Run a program over `input` and render a Markdown report of the run: the formatted
+/// source, statistics, the trace of the first `steps` instructions and the output
+pub(crate) fn render(title: &str, code: &str, input: Option<&[u8]>, steps: usize) -> String {
+    let mut interpreter = match input {
+        Some(input) => Interpreter::with_input(input),
+        None => Interpreter::new(),
+    };
+    let mut hooks = (Trace::new(steps), CellStats::new());
+    let error = interpreter.execute_with_hook(code, &mut hooks).err();
+    let (trace, cells) = hooks;
+    let output = interpreter.output();
+
+    let formatted = brainfuck_core::format::format(code, &Default::default())
+        .unwrap_or_else(|_| code.trim().to_string());
+    let mut report = format!(
+        "# {}\n\n## Source\n\n```brainfuck\n{}\n```\n",
+        title, formatted
+    );
+
+    let instructions = code.chars().filter(|c| "<>+-.,[]".contains(*c)).count();
+    report.push_str("\n## Statistics\n\n| Statistic | Value |\n| --- | ---: |\n");
+    for (name, value) in [
+        ("Characters", code.chars().count()),
+        ("Instructions", instructions),
+        ("Steps", interpreter.steps()),
+        ("Cells used", cells.cells_used()),
+        ("Output bytes", output.len()),
+    ] {
+        report.push_str(&format!("| {} | {} |\n", name, value));
+    }
+
+    let error = error.map(|e| e.to_string());
+    report.push_str("\n## Trace\n\n```text\n");
+    report.push_str(&trace.render(output, error.as_deref()));
+    report.push_str("```\n\n## Output\n\n```text\n");
+    report.push_str(output);
+    if !output.ends_with('\n') {
+        report.push('\n');
+    }
+    report.push_str("```\n");
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let expected = "\
+# Add
+
+## Source
+
+```brainfuck
+,>,
+[<+>-]
+<.
+```
+
+## Statistics
+
+| Statistic | Value |
+| --- | ---: |
+| Characters | 11 |
+| Instructions | 11 |
+| Steps | 16 |
+| Cells used | 2 |
+| Output bytes | 1 |
+
+## Trace
+
+```text
+ step    ip  op    ptr  cell  output
+    1     0  ,      0     1  \"\"
+    2     1  >      1     0  \"\"
+... 14 more steps not shown
+```
+
+## Output
+
+```text
+\u{3}
+```
+";
+        assert_eq!(render("Add", ",>,[<+>-]<.", Some(b"\x01\x02"), 2), expected);
+    }
+}
@@ -0,0 +1,96 @@
+//! Run-length shorthand: a decimal count directly before a command repeats it.
+
+use crate::MAX_STEPS;
+
+/// Error produced while expanding shorthand
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ShorthandError {
+    /// A count was written directly before a bracket
+    CountBeforeBracket(usize),
+    /// A count exceeds the maximum number of execution steps
+    CountTooLarge(usize),
+}
+
+impl std::fmt::Display for ShorthandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShorthandError::CountBeforeBracket(pos) => {
+                write!(f, "Brackets cannot be repeated (count at position {})", pos)
+            }
+            ShorthandError::CountTooLarge(pos) => {
+                write!(
+                    f,
+                    "Count at position {} exceeds the maximum of {}",
+                    pos, MAX_STEPS
+                )
+            }
+        }
+    }
+}
+
+/// Expand every `<count><command>` into `count` copies of the command.
+///
+/// Digits not directly followed by a command are left untouched, as are all other
+/// characters, so comments survive the expansion.
+pub(crate) fn expand(code: &str) -> Result<String, ShorthandError> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut out = String::with_capacity(code.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let digits: String = chars[start..i].iter().collect();
+
+        match chars.get(i) {
+            Some(&command @ ('+' | '-' | '<' | '>' | '.' | ',')) => {
+                let count: usize = digits
+                    .parse()
+                    .ok()
+                    .filter(|&count| count <= MAX_STEPS)
+                    .ok_or(ShorthandError::CountTooLarge(start))?;
+                out.extend(std::iter::repeat_n(command, count));
+                i += 1;
+            }
+            Some('[' | ']') => return Err(ShorthandError::CountBeforeBracket(start)),
+            _ => out.push_str(&digits),
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expands_counts() {
+        assert_eq!(expand("3+ . 2> 4-").unwrap(), "+++ . >> ----");
+        assert_eq!(expand("12+").unwrap(), "+".repeat(12));
+        assert_eq!(expand("0+.").unwrap(), ".");
+    }
+
+    #[test]
+    fn test_keeps_comments() {
+        assert_eq!(expand("Cell 0 = 4\n2+").unwrap(), "Cell 0 = 4\n++");
+        assert_eq!(expand("+[-]").unwrap(), "+[-]");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(expand("+2[-]"), Err(ShorthandError::CountBeforeBracket(1)));
+        assert_eq!(
+            expand("99999999999999999999+"),
+            Err(ShorthandError::CountTooLarge(0))
+        );
+    }
+}
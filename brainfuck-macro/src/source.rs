@@ -0,0 +1,551 @@
+//! The code argument of the macros: a string literal or a supported macro invocation.
+//!
+//! Procedural macros receive their input unexpanded, so `concat!(...)` or
+//! `include_str!(...)` would otherwise be rejected. The supported macros are evaluated
+//! here instead, mirroring what the compiler would produce.
+//!
+//! Macros taking options also accept the code as raw tokens, reconstructed from the
+//! punctuation Rust splits it into.
+
+use std::path::PathBuf;
+
+use proc_macro2::{Delimiter, Span, TokenTree};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Ident, Lit, LitStr, Token};
+
+use crate::frontend::DialectFrontend;
+use crate::{files, shorthand};
+
+/// Macros accepted in place of a string literal, listed in error messages
+const SUPPORTED_MACROS: &str = "concat!, include_str!, brainfuck_expand!, text_to_brainfuck!";
+
+/// Brainfuck code given to a macro, with the files it was read from
+pub(crate) struct Code {
+    value: String,
+    span: Span,
+    tracked: Vec<PathBuf>,
+    /// Environment variables the code was read from
+    env: Vec<LitStr>,
+    /// Items emitting warnings about the code
+    warnings: Vec<proc_macro2::TokenStream>,
+    /// The lines of code given as an array, which `value` joins with newlines
+    lines: Vec<String>,
+}
+
+impl Code {
+    /// Code read from nothing but the invocation
+    fn new(value: String, span: Span) -> Self {
+        Self {
+            value,
+            span,
+            tracked: Vec::new(),
+            env: Vec::new(),
+            warnings: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// The code itself
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
+
+    /// The code as a string literal
+    pub fn literal(&self) -> LitStr {
+        LitStr::new(&self.value, self.span)
+    }
+
+    /// The span of the code argument
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The lines of code given as an array, if it was
+    pub fn lines(&self) -> Option<&[String]> {
+        (!self.lines.is_empty()).then_some(&self.lines[..])
+    }
+
+    /// A line of the code on its own, with the span of the whole code
+    pub fn line(&self, index: usize) -> Code {
+        Self::new(self.lines[index].clone(), self.span)
+    }
+
+    /// Replace the code by the result of `f`. The lines of an array are replaced one by
+    /// one, so they stay apart.
+    pub fn transform<E>(&mut self, mut f: impl FnMut(&str) -> Result<String, E>) -> Result<(), E> {
+        if self.lines.is_empty() {
+            self.value = f(&self.value)?;
+        } else {
+            self.lines = self
+                .lines
+                .iter()
+                .map(|line| f(line))
+                .collect::<Result<_, _>>()?;
+            self.value = self.lines.join("\n");
+        }
+        Ok(())
+    }
+
+    /// Append more code, tracking its files too. Code following an array continues its
+    /// last line.
+    pub fn append(&mut self, other: Code) {
+        if let Some(last) = self.lines.last_mut() {
+            last.push_str(&other.value);
+        }
+        self.value.push_str(&other.value);
+        self.tracked.extend(other.tracked);
+        self.env.extend(other.env);
+        self.warnings.extend(other.warnings);
+    }
+
+    /// Replace the code by its Brainfuck decoded with a frontend
+    pub fn decode(&mut self, frontend: &dyn DialectFrontend) -> syn::Result<()> {
+        let decoded = frontend.decode(&self.value).map_err(|e| {
+            syn::Error::new(
+                self.span,
+                format!("{} syntax error: {}", frontend.title(), e),
+            )
+        })?;
+        self.value = decoded.code;
+        Ok(())
+    }
+
+    /// Warn about the code when the macro is expanded
+    pub fn warn(&mut self, span: Span, message: &str) {
+        self.warnings.push(warning(span, message));
+    }
+
+    /// Items tracking every file and environment variable the code was read from,
+    /// followed by the warnings
+    pub fn tracking(&self) -> proc_macro2::TokenStream {
+        let tracking = self.tracked.iter().map(|path| files::track(path));
+        let env = self.env.iter().map(|name| {
+            quote::quote! {
+                const _: &str = ::core::env!(#name);
+            }
+        });
+        tracking
+            .chain(env)
+            .chain(self.warnings.iter().cloned())
+            .collect()
+    }
+
+    /// Wrap an expression so that every file and environment variable the code was read
+    /// from is tracked and the warnings are emitted
+    pub fn track(&self, expr: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.tracked.is_empty() && self.env.is_empty() && self.warnings.is_empty() {
+            return expr;
+        }
+        let tracking = self.tracking();
+        quote::quote! {
+            {
+                #tracking
+                #expr
+            }
+        }
+    }
+}
+
+impl Parse for Code {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        let mut code = if input.peek(syn::token::Bracket) {
+            let lines = parse_lines(input)?;
+            Self {
+                lines: lines.clone(),
+                ..Self::new(lines.join("\n"), span)
+            }
+        } else {
+            let mut tracked = Vec::new();
+            let value = parse_string(input, &mut tracked)?;
+            Self {
+                tracked,
+                ..Self::new(value, span)
+            }
+        };
+        // Adjacent string literals are joined, so long programs can be split across lines
+        while input.peek(LitStr) {
+            let literal = input.parse::<LitStr>()?;
+            code.append(Self::new(literal.value(), literal.span()));
+        }
+        Ok(code)
+    }
+}
+
+impl Code {
+    /// Parse the code, also accepting `env = "<name>"` for code read from an environment
+    /// variable and raw tokens such as `+[->+<]`. Raw code ends at a comma followed by an
+    /// option, a string literal or nothing.
+    pub fn parse_with_raw(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Ident) && input.peek2(Token![=]) {
+            let fork = input.fork();
+            if fork.parse::<Ident>()? == "env" {
+                input.parse::<Ident>()?;
+                input.parse::<Token![=]>()?;
+                return Self::from_env(input.parse()?);
+            }
+        }
+        if input.peek(LitStr)
+            || input.fork().parse::<syn::Macro>().is_ok()
+            || parse_lines(&input.fork()).is_ok()
+        {
+            return input.parse();
+        }
+        if let Some(error) = runtime_value(input, true) {
+            return Err(error);
+        }
+        let span = input.span();
+        let mut value = String::new();
+        while !input.is_empty() && !at_options(input) {
+            push_tokens(&mut value, input.parse()?);
+        }
+        if value.is_empty() {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "expected a string literal, one of {} or raw tokens as Brainfuck code",
+                    SUPPORTED_MACROS
+                ),
+            ));
+        }
+        Ok(Self::new(value, span))
+    }
+
+    /// Read the code from the environment variable `name` of the compiler, which build
+    /// scripts can set with `cargo:rustc-env`
+    fn from_env(name: LitStr) -> syn::Result<Self> {
+        let value = std::env::var(name.value()).map_err(|e| {
+            syn::Error::new(
+                name.span(),
+                format!(
+                    "couldn't read environment variable `{}`: {}",
+                    name.value(),
+                    e
+                ),
+            )
+        })?;
+        Ok(Self {
+            env: vec![name.clone()],
+            ..Self::new(value, name.span())
+        })
+    }
+}
+
+/// Parse an array of string literals, one per line, joined with newlines
+fn parse_lines(input: ParseStream) -> syn::Result<Vec<String>> {
+    let content;
+    let brackets = syn::bracketed!(content in input);
+    let lines = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+    if lines.is_empty() {
+        return Err(syn::Error::new(
+            brackets.span.join(),
+            "expected at least one line of code",
+        ));
+    }
+    Ok(lines.iter().map(LitStr::value).collect())
+}
+
+/// Whether raw code ends here, at a comma followed by an option, a string literal or
+/// nothing
+fn at_options(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![,]>().is_ok()
+        && (fork.is_empty() || fork.peek(LitStr) || fork.peek(Ident) && fork.peek2(Token![=]))
+}
+
+/// An error for code given as a variable or another expression only known at runtime,
+/// such as `code`, `self.code` or `load()`, which raw code would otherwise read as
+/// comments. In `raw` code, the expression must be all of the code.
+fn runtime_value(input: ParseStream, raw: bool) -> Option<syn::Error> {
+    let fork = input.fork();
+    let expr: syn::Expr = fork.parse().ok()?;
+    let ended = if raw {
+        at_options(&fork)
+    } else {
+        fork.peek(Token![,])
+    };
+    if !fork.is_empty() && !ended {
+        return None;
+    }
+    let what = match &expr {
+        syn::Expr::Path(path) if path.path.get_ident().is_some() => "a variable or constant",
+        syn::Expr::Path(_)
+        | syn::Expr::Field(_)
+        | syn::Expr::MethodCall(_)
+        | syn::Expr::Call(_)
+        | syn::Expr::Reference(_)
+        | syn::Expr::Index(_) => "an expression",
+        _ => return None,
+    };
+    Some(syn::Error::new_spanned(
+        &expr,
+        format!(
+            "Brainfuck code must be known at compile time, but `{}` is {}; \
+             the code must be a string literal, an array of string literals or one of {}. \
+             To run code only known at runtime, use `brainfuck_core::interpret::run`",
+            quote::ToTokens::to_token_stream(&expr),
+            what,
+            SUPPORTED_MACROS
+        ),
+    ))
+}
+
+/// Append the source of a raw token tree. Identifiers and literals are comments, kept
+/// apart by spaces; whitespace between other tokens is lost.
+fn push_tokens(code: &mut String, tree: TokenTree) {
+    match tree {
+        TokenTree::Group(group) => {
+            let (open, close) = match group.delimiter() {
+                Delimiter::Parenthesis => ("(", ")"),
+                Delimiter::Bracket => ("[", "]"),
+                Delimiter::Brace => ("{", "}"),
+                Delimiter::None => ("", ""),
+            };
+            code.push_str(open);
+            for tree in group.stream() {
+                push_tokens(code, tree);
+            }
+            code.push_str(close);
+        }
+        TokenTree::Punct(punct) => code.push(punct.as_char()),
+        word => {
+            if code.ends_with(|ch: char| !"<>+-.,[](){}".contains(ch) && !ch.is_whitespace()) {
+                code.push(' ');
+            }
+            code.push_str(&word.to_string());
+        }
+    }
+}
+
+/// An item producing a warning at `span`.
+///
+/// Procedural macros cannot emit warnings on stable Rust, so this uses a deprecated item,
+/// whose deprecation note is shown as the warning.
+pub(crate) fn warning(span: Span, message: &str) -> proc_macro2::TokenStream {
+    quote::quote_spanned! {span=>
+        const _: () = {
+            #[deprecated(note = #message)]
+            #[allow(non_camel_case_types)]
+            struct brainfuck_warning;
+            let _ = brainfuck_warning;
+        };
+    }
+}
+
+/// Parse a string literal or a supported macro invocation producing a string
+fn parse_string(input: ParseStream, tracked: &mut Vec<PathBuf>) -> syn::Result<String> {
+    if input.peek(LitStr) {
+        return Ok(input.parse::<LitStr>()?.value());
+    }
+
+    if let Some(error) = runtime_value(input, false) {
+        return Err(error);
+    }
+    let mac: syn::Macro = input.parse().map_err(|e| {
+        syn::Error::new(
+            e.span(),
+            format!(
+                "expected a string literal or one of {} as Brainfuck code",
+                SUPPORTED_MACROS
+            ),
+        )
+    })?;
+    let name = mac
+        .path
+        .segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+        .unwrap_or_default();
+
+    match name.as_str() {
+        "concat" => {
+            let parser = Punctuated::<ConcatPart, Token![,]>::parse_terminated;
+            let parts = mac.parse_body_with(parser)?;
+            let mut value = String::new();
+            for part in parts {
+                value.push_str(&part.value);
+                tracked.extend(part.tracked);
+            }
+            Ok(value)
+        }
+        "include_str" => {
+            let path: LitStr = mac.parse_body()?;
+            let full = resolve_include(&path.value());
+            let value = std::fs::read_to_string(&full).map_err(|e| {
+                syn::Error::new(
+                    path.span(),
+                    format!("couldn't read '{}': {}", full.display(), e),
+                )
+            })?;
+            tracked.push(full.canonicalize().unwrap_or(full));
+            Ok(value)
+        }
+        "brainfuck_expand" => {
+            let code = mac.parse_body_with(|input: ParseStream| parse_string(input, tracked))?;
+            shorthand::expand(&code).map_err(|e| {
+                syn::Error::new_spanned(&mac, format!("Brainfuck shorthand error: {}", e))
+            })
+        }
+        "text_to_brainfuck" => {
+            let text = mac.parse_body_with(|input: ParseStream| parse_string(input, tracked))?;
+            let bytes: Vec<u8> = text
+                .chars()
+                .map(u8::try_from)
+                .collect::<Result<_, _>>()
+                .map_err(|_| {
+                    syn::Error::new_spanned(&mac, "text contains characters above U+00FF")
+                })?;
+            Ok(brainfuck_core::generate::print_bytes(&bytes))
+        }
+        _ => Err(syn::Error::new_spanned(
+            &mac.path,
+            format!(
+                "`{}!` cannot be evaluated as Brainfuck code; supported macros are {}",
+                name, SUPPORTED_MACROS
+            ),
+        )),
+    }
+}
+
+/// Resolve an `include_str!` path like the compiler does: relative to the invoking file
+fn resolve_include(path: &str) -> PathBuf {
+    let base = proc_macro::Span::call_site()
+        .local_file()
+        .and_then(|file| file.parent().map(PathBuf::from));
+    match base {
+        Some(dir) => dir.join(path),
+        None => files::resolve(path),
+    }
+}
+
+/// One argument of `concat!`: any literal or a nested supported macro
+struct ConcatPart {
+    value: String,
+    tracked: Vec<PathBuf>,
+}
+
+impl Parse for ConcatPart {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut tracked = Vec::new();
+        let value = if input.peek(Lit) && !input.peek(LitStr) {
+            match input.parse::<Lit>()? {
+                Lit::Char(c) => c.value().to_string(),
+                Lit::Int(i) => i.base10_digits().to_string(),
+                Lit::Float(f) => f.base10_digits().to_string(),
+                Lit::Bool(b) => b.value.to_string(),
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        "unsupported literal in concat!",
+                    ))
+                }
+            }
+        } else {
+            parse_string(input, &mut tracked)?
+        };
+        Ok(Self { value, tracked })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(tokens: proc_macro2::TokenStream) -> syn::Result<String> {
+        syn::parse2::<Code>(tokens).map(|code| code.value())
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(parse(quote::quote!("+++.")).unwrap(), "+++.");
+    }
+
+    #[test]
+    fn test_adjacent_literals() {
+        let code = parse(quote::quote!("++++" "[>+<-]"
+            ">."));
+        assert_eq!(code.unwrap(), "++++[>+<-]>.");
+        assert_eq!(parse(quote::quote!(concat!("+", "+") "-")).unwrap(), "++-");
+    }
+
+    fn parse_raw(tokens: proc_macro2::TokenStream) -> syn::Result<String> {
+        syn::parse::Parser::parse2(Code::parse_with_raw, tokens).map(|code| code.value())
+    }
+
+    #[test]
+    fn test_raw_tokens() {
+        assert_eq!(
+            parse_raw(quote::quote!(++++[>+<-]>.)).unwrap(),
+            "++++[>+<-]>."
+        );
+        // Joint punctuation such as `<-` or `..` is split into commands
+        assert_eq!(parse_raw(quote::quote!(+<-->..)).unwrap(), "+<-->..");
+        assert_eq!(
+            parse_raw(quote::quote!(add two: ++ then print.)).unwrap(),
+            "add two:++then print."
+        );
+        // Literals are comments in their source form, so `3.` still prints
+        assert_eq!(parse_raw(quote::quote!(+++ 3.)).unwrap(), "+++3.");
+        assert!(parse_raw(quote::quote!()).is_err());
+    }
+
+    #[test]
+    fn test_runtime_value() {
+        let error = |tokens| parse_raw(tokens).err().unwrap().to_string();
+        assert!(error(quote::quote!(code)).contains("`code` is a variable or constant"));
+        assert!(error(quote::quote!(self.code, dialect = "ook")).contains("is an expression"));
+        assert!(error(quote::quote!(&load())).contains("brainfuck_core::interpret::run"));
+        // Words followed by code are comments
+        assert_eq!(parse_raw(quote::quote!(print it.)).unwrap(), "print it.");
+        let error = syn::parse2::<Code>(quote::quote!(code)).err().unwrap();
+        assert!(error.to_string().contains("must be known at compile time"));
+    }
+
+    #[test]
+    fn test_env() {
+        std::env::set_var("BRAINFUCK_MACRO_TEST_PROGRAM", "+++.");
+        let code = parse_raw(quote::quote!(env = "BRAINFUCK_MACRO_TEST_PROGRAM"));
+        assert_eq!(code.unwrap(), "+++.");
+        let err = parse_raw(quote::quote!(env = "BRAINFUCK_MACRO_TEST_UNSET"))
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("couldn't read environment variable `BRAINFUCK_MACRO_TEST_UNSET`"));
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines = quote::quote!(["+++++", "[>+++<-]", ">.",]);
+        assert_eq!(parse(lines.clone()).unwrap(), "+++++\n[>+++<-]\n>.");
+        assert_eq!(parse_raw(lines).unwrap(), "+++++\n[>+++<-]\n>.");
+        // A loop in raw tokens is not an array of lines
+        assert_eq!(parse_raw(quote::quote!([-]+.)).unwrap(), "[-]+.");
+        assert_eq!(parse_raw(quote::quote!([]+.)).unwrap(), "[]+.");
+    }
+
+    #[test]
+    fn test_concat() {
+        let code = parse(quote::quote!(concat!("++", 3, std::concat!("[-]", '.'))));
+        assert_eq!(code.unwrap(), "++3[-].");
+    }
+
+    #[test]
+    fn test_own_macros() {
+        assert_eq!(
+            parse(quote::quote!(brainfuck_expand!("3+."))).unwrap(),
+            "+++."
+        );
+        assert_eq!(
+            parse(quote::quote!(text_to_brainfuck!(concat!("\u{1}", "\u{2}")))).unwrap(),
+            "+.+."
+        );
+    }
+
+    #[test]
+    fn test_unsupported_macro() {
+        let err = parse(quote::quote!(format!("{}", 1))).err().unwrap();
+        assert!(err.to_string().starts_with("`format!` cannot be evaluated"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Per-invocation reports of `stats_file = true`.
+//!
+//! Every report is a small `key: value` text file in `$OUT_DIR/brainfuck-stats`, named
+//! by the hash of the program, so build engineers can find the invocations that
+//! dominate compile time with nothing but `grep` and `sort`.
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use brainfuck_core::ir::{self, Op};
+use brainfuck_core::machine::{Error, Interpreter};
+use brainfuck_core::optimize::{self, Pass};
+use brainfuck_core::trace::{ExecutionHook, TraceStep};
+
+/// The optimizer passes of the macros, named for the report
+const PASSES: [(&str, Pass); 3] = [
+    ("clear_loops", optimize::clear_loops),
+    ("mul_loops", optimize::mul_loops),
+    ("dce", optimize::dce),
+];
+
+/// Tracks the highest cell the pointer reached
+struct Peak(usize);
+
+impl ExecutionHook for Peak {
+    fn on_instruction(&mut self, step: &TraceStep) {
+        self.0 = self.0.max(step.pointer + 1);
+    }
+}
+
+/// Execute code like `Interpreter::execute_with_hook` and write a report on the run
+pub(crate) fn execute(
+    mut interpreter: Interpreter,
+    code: &str,
+    hook: &mut impl ExecutionHook,
+) -> syn::Result<Result<String, Error>> {
+    let mut hooks = (Peak(1), hook);
+    let start = Instant::now();
+    let result = interpreter.execute_with_hook(code, &mut hooks);
+    let peak = hooks.0;
+    let elapsed = start.elapsed();
+
+    let hash = fnv1a(code.as_bytes());
+    let mut report = format!(
+        "program: {:016x}\ncharacters: {}\nsteps: {}\nwall_time_us: {}\npeak_cells: {}\noutput_bytes: {}\n",
+        hash,
+        code.chars().count(),
+        interpreter.steps(),
+        elapsed.as_micros(),
+        peak.0,
+        interpreter.output().len(),
+    );
+    match &result {
+        Ok(_) => report.push_str("result: ok\n"),
+        Err(e) => report.push_str(&format!("result: error: {}\n", e)),
+    }
+    if let Ok(mut ops) = ir::parse(code) {
+        report.push_str(&format!("ops: {}\n", count_ops(&ops)));
+        for (name, pass) in PASSES {
+            ops = pass(ops);
+            report.push_str(&format!("ops_after_{}: {}\n", name, count_ops(&ops)));
+        }
+    }
+
+    let dir = stats_dir().join("brainfuck-stats");
+    std::fs::create_dir_all(&dir)
+        .and_then(|()| std::fs::write(dir.join(format!("{:016x}.txt", hash)), report))
+        .map_err(|e| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "option `stats_file`: cannot write to '{}': {}",
+                    dir.display(),
+                    e
+                ),
+            )
+        })?;
+    Ok(result)
+}
+
+/// `OUT_DIR` of the crate being compiled if it has a build script, `target` in its
+/// manifest directory otherwise
+fn stats_dir() -> PathBuf {
+    std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| crate::files::resolve("target"))
+}
+
+/// The number of operations, counting those in loop bodies
+fn count_ops(ops: &[Op]) -> usize {
+    ops.iter()
+        .map(|op| match op {
+            Op::Loop(body) => 1 + count_ops(body),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// The 64-bit FNV-1a hash, stable across compiler versions unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_ops() {
+        let ops = ir::parse("++[>+<-]").unwrap();
+        assert_eq!(count_ops(&ops), 6);
+        assert_eq!(count_ops(&optimize::mul_loops(ops)), 2);
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,143 @@
+//! C code generation backend.
+//!
+//! Turns IR into C99 source defining `void name(FILE *input, FILE *output)`, optionally
+//! followed by a `main` running it against stdin and stdout. Unlike the Rust backend,
+//! pointer moves are not bounds checked.
+
+use crate::TAPE_SIZE;
+use brainfuck_core::ir::{uses_input, Op};
+
+/// Generate the C source of a function called `name` that runs the program
+pub(crate) fn to_c(name: &str, ops: &[Op], main: bool) -> String {
+    let mut out = String::new();
+    out.push_str("#include <stdio.h>\n\n");
+    if uses_input(ops) {
+        out.push_str("static unsigned char read_byte(FILE *input) {\n");
+        out.push_str("    int c = getc(input);\n");
+        out.push_str("    return c == EOF ? 0 : (unsigned char)c;\n");
+        out.push_str("}\n\n");
+    }
+    out.push_str(&format!("void {}(FILE *input, FILE *output) {{\n", name));
+    out.push_str(&format!("    unsigned char tape[{}] = {{0}};\n", TAPE_SIZE));
+    out.push_str("    size_t ptr = 0;\n");
+    out.push_str("    (void)input;\n");
+    emit_ops(&mut out, ops, 1);
+    out.push_str("    fflush(output);\n");
+    out.push_str("}\n");
+    if main {
+        out.push_str("\nint main(void) {\n");
+        out.push_str(&format!("    {}(stdin, stdout);\n", name));
+        out.push_str("    return 0;\n");
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// Emit a sequence of operations at the given indentation depth
+fn emit_ops(out: &mut String, ops: &[Op], depth: usize) {
+    for op in ops {
+        let indent = "    ".repeat(depth);
+        match op {
+            Op::Add(n) if *n > 128 => {
+                out.push_str(&format!("{}tape[ptr] -= {};\n", indent, n.wrapping_neg()));
+            }
+            Op::Add(n) => {
+                out.push_str(&format!("{}tape[ptr] += {};\n", indent, n));
+            }
+            Op::Move(n) if *n < 0 => {
+                out.push_str(&format!("{}ptr -= {};\n", indent, -n));
+            }
+            Op::Move(n) => {
+                out.push_str(&format!("{}ptr += {};\n", indent, n));
+            }
+            Op::Output => {
+                out.push_str(&format!("{}putc(tape[ptr], output);\n", indent));
+            }
+            Op::Input => {
+                out.push_str(&format!("{}fflush(output);\n", indent));
+                out.push_str(&format!("{}tape[ptr] = read_byte(input);\n", indent));
+            }
+            Op::Loop(body) => {
+                out.push_str(&format!("{}while (tape[ptr]) {{\n", indent));
+                emit_ops(out, body, depth + 1);
+                out.push_str(&format!("{}}}\n", indent));
+            }
+            Op::Clear => {
+                out.push_str(&format!("{}tape[ptr] = 0;\n", indent));
+            }
+            Op::MulLoop(terms) => {
+                out.push_str(&format!("{}if (tape[ptr]) {{\n", indent));
+                for &(offset, factor) in terms {
+                    let cell = if offset < 0 {
+                        format!("tape[ptr - {}]", -offset)
+                    } else {
+                        format!("tape[ptr + {}]", offset)
+                    };
+                    out.push_str(&format!(
+                        "{}    {} += tape[ptr] * {};\n",
+                        indent, cell, factor
+                    ));
+                }
+                out.push_str(&format!("{}    tape[ptr] = 0;\n", indent));
+                out.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+}
+
+/// Whether `name` can be used as a C function name
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brainfuck_core::ir::parse;
+    use brainfuck_core::optimize::optimize;
+
+    #[test]
+    fn test_simple_program() {
+        let ops = optimize(parse("+++[->++<]>-.").unwrap());
+        let expected = "\
+#include <stdio.h>
+
+void run(FILE *input, FILE *output) {
+    unsigned char tape[30000] = {0};
+    size_t ptr = 0;
+    (void)input;
+    tape[ptr] += 3;
+    if (tape[ptr]) {
+        tape[ptr + 1] += tape[ptr] * 2;
+        tape[ptr] = 0;
+    }
+    ptr += 1;
+    tape[ptr] -= 1;
+    putc(tape[ptr], output);
+    fflush(output);
+}
+";
+        assert_eq!(to_c("run", &ops, false), expected);
+    }
+
+    #[test]
+    fn test_input_and_main() {
+        let source = to_c("echo", &parse(",[.,]").unwrap(), true);
+        assert!(source.contains("static unsigned char read_byte(FILE *input) {"));
+        assert!(source.contains("    while (tape[ptr]) {\n        putc(tape[ptr], output);"));
+        assert!(source.ends_with("int main(void) {\n    echo(stdin, stdout);\n    return 0;\n}\n"));
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert!(is_identifier("run_bf2"));
+        assert!(is_identifier("_run"));
+        assert!(!is_identifier("2run"));
+        assert!(!is_identifier("run-bf"));
+        assert!(!is_identifier(""));
+    }
+}
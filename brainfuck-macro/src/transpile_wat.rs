@@ -0,0 +1,155 @@
+//! WebAssembly text code generation backend.
+//!
+//! Turns IR into a WAT module whose linear memory is the tape. The module imports
+//! `env.putc` (called with each output byte) and, if the program reads input, `env.getc`
+//! (returning the next input byte, or 0 at end of input), and exports `memory` and a
+//! `run` function. Pointer moves are not bounds checked: moving off the tape only traps
+//! once the pointer leaves the memory.
+
+use crate::TAPE_SIZE;
+use brainfuck_core::ir::{uses_input, Op};
+
+/// Size of a WebAssembly memory page in bytes
+const PAGE_SIZE: usize = 65_536;
+
+/// Generate a WAT module that runs the program
+pub(crate) fn to_wat(ops: &[Op]) -> String {
+    let mut out = String::new();
+    out.push_str("(module\n");
+    out.push_str("  (import \"env\" \"putc\" (func $putc (param i32)))\n");
+    if uses_input(ops) {
+        out.push_str("  (import \"env\" \"getc\" (func $getc (result i32)))\n");
+    }
+    out.push_str(&format!(
+        "  (memory (export \"memory\") {})\n",
+        TAPE_SIZE.div_ceil(PAGE_SIZE)
+    ));
+    out.push_str("  (func (export \"run\")\n");
+    out.push_str("    (local $ptr i32)\n");
+    emit_ops(&mut out, ops, 2, &mut 0);
+    out.push_str("  )\n");
+    out.push_str(")\n");
+    out
+}
+
+/// The address of the cell at `offset` from the pointer
+fn cell(offset: isize) -> String {
+    match offset {
+        0 => "(local.get $ptr)".to_string(),
+        n if n < 0 => format!("(i32.sub (local.get $ptr) (i32.const {}))", -n),
+        n => format!("(i32.add (local.get $ptr) (i32.const {}))", n),
+    }
+}
+
+/// Emit a sequence of operations at the given indentation depth. `labels` counts the
+/// loops emitted so far, giving every loop unique block labels.
+fn emit_ops(out: &mut String, ops: &[Op], depth: usize, labels: &mut usize) {
+    let indent = "  ".repeat(depth);
+    let current = cell(0);
+    for op in ops {
+        match op {
+            Op::Add(n) => {
+                let (instr, n) = if *n > 128 {
+                    ("i32.sub", n.wrapping_neg())
+                } else {
+                    ("i32.add", *n)
+                };
+                out.push_str(&format!(
+                    "{}(i32.store8 {} ({} (i32.load8_u {}) (i32.const {})))\n",
+                    indent, current, instr, current, n
+                ));
+            }
+            Op::Move(n) => {
+                out.push_str(&format!("{}(local.set $ptr {})\n", indent, cell(*n)));
+            }
+            Op::Output => {
+                out.push_str(&format!(
+                    "{}(call $putc (i32.load8_u {}))\n",
+                    indent, current
+                ));
+            }
+            Op::Input => {
+                out.push_str(&format!(
+                    "{}(i32.store8 {} (call $getc))\n",
+                    indent, current
+                ));
+            }
+            Op::Loop(body) => {
+                let label = *labels;
+                *labels += 1;
+                out.push_str(&format!("{}(block $end{}\n", indent, label));
+                out.push_str(&format!("{}  (loop $loop{}\n", indent, label));
+                out.push_str(&format!(
+                    "{}    (br_if $end{} (i32.eqz (i32.load8_u {})))\n",
+                    indent, label, current
+                ));
+                emit_ops(out, body, depth + 2, labels);
+                out.push_str(&format!("{}    (br $loop{})))\n", indent, label));
+            }
+            Op::Clear => {
+                out.push_str(&format!(
+                    "{}(i32.store8 {} (i32.const 0))\n",
+                    indent, current
+                ));
+            }
+            Op::MulLoop(terms) => {
+                out.push_str(&format!("{}(if (i32.load8_u {})\n", indent, current));
+                out.push_str(&format!("{}  (then\n", indent));
+                for &(offset, factor) in terms {
+                    let target = cell(offset);
+                    out.push_str(&format!(
+                        "{}    (i32.store8 {} (i32.add (i32.load8_u {}) (i32.mul (i32.load8_u {}) (i32.const {}))))\n",
+                        indent, target, target, current, factor
+                    ));
+                }
+                out.push_str(&format!(
+                    "{}    (i32.store8 {} (i32.const 0))))\n",
+                    indent, current
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brainfuck_core::ir::parse;
+    use brainfuck_core::optimize::optimize;
+
+    #[test]
+    fn test_simple_program() {
+        let ops = optimize(parse("++[->+++<]>[.-]").unwrap());
+        let expected = r#"(module
+  (import "env" "putc" (func $putc (param i32)))
+  (memory (export "memory") 1)
+  (func (export "run")
+    (local $ptr i32)
+    (i32.store8 (local.get $ptr) (i32.add (i32.load8_u (local.get $ptr)) (i32.const 2)))
+    (if (i32.load8_u (local.get $ptr))
+      (then
+        (i32.store8 (i32.add (local.get $ptr) (i32.const 1)) (i32.add (i32.load8_u (i32.add (local.get $ptr) (i32.const 1))) (i32.mul (i32.load8_u (local.get $ptr)) (i32.const 3))))
+        (i32.store8 (local.get $ptr) (i32.const 0))))
+    (local.set $ptr (i32.add (local.get $ptr) (i32.const 1)))
+    (block $end0
+      (loop $loop0
+        (br_if $end0 (i32.eqz (i32.load8_u (local.get $ptr))))
+        (call $putc (i32.load8_u (local.get $ptr)))
+        (i32.store8 (local.get $ptr) (i32.sub (i32.load8_u (local.get $ptr)) (i32.const 1)))
+        (br $loop0)))
+  )
+)
+"#;
+        assert_eq!(to_wat(&ops), expected);
+    }
+
+    #[test]
+    fn test_input_import_and_labels() {
+        let source = to_wat(&parse(",[>[<]],").unwrap());
+        assert!(source.contains("(import \"env\" \"getc\" (func $getc (result i32)))"));
+        assert!(source.contains("(i32.store8 (local.get $ptr) (call $getc))"));
+        assert!(source.contains("(block $end0"));
+        assert!(source.contains("(block $end1"));
+        assert!(source.contains("(local.set $ptr (i32.sub (local.get $ptr) (i32.const 1)))"));
+    }
+}